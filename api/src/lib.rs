@@ -1,4 +1,8 @@
-use fsm::fsm::{FSMError, FSMOutput, StateMachine};
+use fsm::{
+    explain::Explanation,
+    fsm::{FSMError, FSMOutput, StateMachine},
+    transducer::Transduction,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -92,6 +96,12 @@ pub enum SubmissionVerdict {
         successes: usize,
         first_failure_seed: i64,
         first_failure_expected_result: FSMOutput,
+        /// Why the state machine gave the wrong answer on that test; missing for older submissions
+        #[serde(default)]
+        first_failure_explanation: Option<Explanation>,
+        /// For transducer tasks, the expected output and the state machine's, if they differed on that test
+        #[serde(default)]
+        first_failure_transductions: Option<(Transduction, Transduction)>,
     },
 
     /// The state machine is invalid
@@ -99,12 +109,26 @@ pub enum SubmissionVerdict {
 
     /// The task is invalid -- this is the jury's fault
     TaskInternalError(String),
+
+    /// The state machine is proven to accept the same words as the model solution
+    Equivalent,
+
+    /// The state machine is proven to differ from the model solution: the shortest word where they differ is provided
+    Counterexample {
+        word: String,
+        expected_result: FSMOutput,
+        /// How many words of the same length the model solution accepts
+        expected_count: u64,
+        /// How many words of the same length the state machine accepts
+        actual_count: u64,
+    },
 }
 
 impl SubmissionVerdict {
     pub fn is_ok(&self) -> bool {
         match self {
             SubmissionVerdict::Ok(_) => true,
+            SubmissionVerdict::Equivalent => true,
             _ => false,
         }
     }
@@ -138,6 +162,24 @@ pub struct SmallSubmissionInfo {
     pub verdict: SubmissionVerdict,
 }
 
+/// Submissions to one task from different users, whose machines share a fingerprint.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubmissionCluster {
+    pub shared: SharedFingerprint,
+    /// The shared fingerprint, in hexadecimal
+    pub fingerprint: String,
+    /// The earliest such submission of every user, earliest first
+    pub submissions: Vec<(SmallUserInfo, SmallSubmissionInfo)>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SharedFingerprint {
+    /// The machines are drawn the same way, apart from node names and positions
+    Drawing,
+    /// The machines are wrong, but accept the same wrong words
+    WrongLanguage,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct OthersSubmissionInfo {
     pub id: i64,
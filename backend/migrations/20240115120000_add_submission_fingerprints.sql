@@ -0,0 +1,3 @@
+-- The fingerprint of the language of each submitted machine, computed once when it is submitted.
+-- NULL means it has not been computed yet; an empty string means the machine has no usable one.
+ALTER TABLE user_submission ADD COLUMN language_fingerprint TEXT;
@@ -0,0 +1,3 @@
+-- The fingerprint of the drawing of each submitted machine, computed once when it is submitted.
+-- NULL means it has not been computed yet; an empty string means the machine has no nodes.
+ALTER TABLE user_submission ADD COLUMN drawing_fingerprint TEXT;
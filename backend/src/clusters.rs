@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use api::{SharedFingerprint, SmallSubmissionInfo, SmallUserInfo, SubmissionCluster};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use fsm::fsm::StateMachine;
+use subtle::ConstantTimeEq;
+
+use crate::{result::AppError, task::read_submission, AppState};
+
+/// The header that teachers send their token in, so that it stays out of the URLs that end up in logs.
+pub const TEACHER_TOKEN_HEADER: &str = "x-teacher-token";
+
+/// The fingerprint of the machine's drawing, as stored in `user_submission.drawing_fingerprint`.
+///
+/// It is empty for machines with no nodes, which every empty canvas would share.
+pub(crate) fn drawing_fingerprint(fsm: &StateMachine) -> String {
+    if fsm.nodes.is_empty() {
+        String::new()
+    } else {
+        format!("{:016x}", fsm.drawing_hash())
+    }
+}
+
+/// The fingerprint of the machine's language, as stored in `user_submission.language_fingerprint`.
+///
+/// It is empty for machines that accept nothing, since that is the usual way to start,
+/// and for machines whose language cannot be worked out.
+pub(crate) fn language_fingerprint(fsm: &StateMachine) -> String {
+    match fsm.canonical_form() {
+        Ok(dfa) if dfa.accept.contains(&true) => format!("{:016x}", dfa.canonical_hash()),
+        _ => String::new(),
+    }
+}
+
+/// List the groups of submissions to a task, from at least two users, whose machines share a fingerprint.
+///
+/// Correct machines all accept the same words, so only wrong ones are grouped by their language.
+/// Only teachers may see this, since it names students; they send their token in the [TEACHER_TOKEN_HEADER] header.
+pub async fn get_task_clusters(
+    State(AppState { db, teacher_token }): State<AppState>,
+    Path((_group_slug, task_slug)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<Option<Vec<SubmissionCluster>>>), AppError> {
+    // The comparison takes the same time however much of the token is right, so it cannot be guessed piece by piece.
+    let is_teacher = match (&teacher_token, headers.get(TEACHER_TOKEN_HEADER)) {
+        (Some(expected), Some(given)) => bool::from(expected.as_bytes().ct_eq(given.as_bytes())),
+        _ => false,
+    };
+    if !is_teacher {
+        return Ok((StatusCode::FORBIDDEN, Json(None)));
+    }
+
+    let task = sqlx::query!("SELECT * FROM task WHERE slug=?", task_slug)
+        .fetch_optional(&db)
+        .await?;
+    let task = if let Some(t) = task {
+        t
+    } else {
+        return Ok((StatusCode::NOT_FOUND, Json(None)));
+    };
+
+    let rows = sqlx::query!("SELECT user_submission.id, when_unix_time, task_id, solution_json, verdict_json, language_fingerprint, drawing_fingerprint, account.id AS account_id, account.user_name AS account_name, account.rudn_id AS account_rudn_id FROM user_submission JOIN account ON account.id=user_submission.user_id WHERE task_id=? ORDER BY when_unix_time", task.id)
+        .fetch_all(&db)
+        .await?;
+
+    let mut groups: HashMap<
+        (SharedFingerprint, String),
+        Vec<(SmallUserInfo, SmallSubmissionInfo)>,
+    > = HashMap::new();
+    for row in rows {
+        let Some((fsm, verdict)) = read_submission(row.id, &row.solution_json, &row.verdict_json)
+        else {
+            continue;
+        };
+        let mut fingerprints = vec![];
+        let drawing = match row.drawing_fingerprint {
+            Some(drawing) => drawing,
+            None => {
+                // Submissions from before drawing fingerprints were stored get theirs now, once.
+                let drawing = drawing_fingerprint(&fsm);
+                sqlx::query!(
+                    "UPDATE user_submission SET drawing_fingerprint=? WHERE id=?",
+                    drawing,
+                    row.id
+                )
+                .execute(&db)
+                .await?;
+                drawing
+            }
+        };
+        if !drawing.is_empty() {
+            fingerprints.push((SharedFingerprint::Drawing, drawing));
+        }
+        if !verdict.is_ok() {
+            let language = match row.language_fingerprint {
+                Some(language) => language,
+                None => {
+                    // Submissions from before fingerprints were stored get theirs now, once.
+                    let machine = fsm.clone();
+                    let language =
+                        tokio::task::spawn_blocking(move || language_fingerprint(&machine)).await?;
+                    sqlx::query!(
+                        "UPDATE user_submission SET language_fingerprint=? WHERE id=?",
+                        language,
+                        row.id
+                    )
+                    .execute(&db)
+                    .await?;
+                    language
+                }
+            };
+            if !language.is_empty() {
+                fingerprints.push((SharedFingerprint::WrongLanguage, language));
+            }
+        }
+
+        let user = SmallUserInfo {
+            id: row.account_id,
+            name: row.account_name,
+            rudn_id: row.account_rudn_id,
+        };
+        let submission = SmallSubmissionInfo {
+            id: row.id,
+            task_id: row.task_id,
+            when_unix_time: row.when_unix_time,
+            node_count: fsm.nodes.len(),
+            link_count: fsm.links.len(),
+            verdict,
+        };
+        for fingerprint in fingerprints {
+            let group = groups.entry(fingerprint).or_default();
+            // The rows come earliest first, so this keeps every user's earliest submission.
+            if group.iter().all(|(u, _)| u.id != user.id) {
+                group.push((user.clone(), submission.clone()));
+            }
+        }
+    }
+
+    let mut clusters: Vec<SubmissionCluster> = groups
+        .into_iter()
+        .filter(|(_, submissions)| submissions.len() > 1)
+        .map(|((shared, fingerprint), submissions)| SubmissionCluster {
+            shared,
+            fingerprint,
+            submissions,
+        })
+        .collect();
+    // The biggest groups first, then the ones that started earliest.
+    clusters.sort_by_key(|c| {
+        (
+            usize::MAX - c.submissions.len(),
+            c.submissions[0].1.when_unix_time,
+        )
+    });
+
+    Ok((StatusCode::OK, Json(Some(clusters))))
+}
@@ -1,3 +1,4 @@
+mod clusters;
 mod others_submissions;
 mod result;
 pub mod submit;
@@ -15,6 +16,8 @@ use tower_http::cors::Any;
 #[derive(Clone)]
 pub struct AppState {
     pub db: SqlitePool,
+    /// The token that unlocks the pages meant for teachers; if it is not set, those pages are disabled.
+    pub teacher_token: Option<String>,
 }
 
 #[tokio::main]
@@ -31,8 +34,21 @@ pub async fn main() -> anyhow::Result<()> {
         .expect("Failed to connect to database");
 
     sqlx::migrate!().run(&conn).await?;
+    task::upgrade_model_solutions(&conn).await?;
 
-    let app_state = AppState { db: conn };
+    let teacher_token = std::env::var("TEACHER_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty());
+    if teacher_token.is_none() {
+        tracing::warn!(
+            "No TEACHER_TOKEN environment variable provided, so the teacher pages are disabled"
+        );
+    }
+
+    let app_state = AppState {
+        db: conn,
+        teacher_token,
+    };
 
     let app = Router::new()
         .route("/", get(root))
@@ -46,6 +62,10 @@ pub async fn main() -> anyhow::Result<()> {
         )
         .route("/task-by-id/:id", get(task::get_task_by_id))
         .route("/tasks/:group/:task", get(task::get_task))
+        .route(
+            "/tasks/:group/:task/clusters",
+            get(clusters::get_task_clusters),
+        )
         .route(
             "/tasks/:group/:task/:token",
             get(task::get_task_and_userdata).post(submit::submit_task),
@@ -91,5 +111,6 @@ async fn root() -> &'static str {
         "GET /users/:userid -- get a particular user's submissions\n",
         "GET /submissions/:submissionid/:token -- get a particular submission, including its contents if the given user has also solved it",
         "GET /task-by-id/:task-id -- get info about a task by its ID",
+        "GET /tasks/:group/:task/clusters -- get groups of users with suspiciously similar submissions (teachers only; send the token in the X-Teacher-Token header)",
     )
 }
@@ -8,10 +8,10 @@ use axum::{
 };
 use fsm::fsm::StateMachine;
 
-use crate::{result::AppError, AppState};
+use crate::{result::AppError, task::read_submission, AppState};
 
 pub async fn view_users(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
 ) -> Result<Json<Vec<UserAndSubmissionStats>>, AppError> {
     let mut data = vec![];
 
@@ -36,7 +36,7 @@ pub async fn view_users(
 }
 
 pub async fn view_specific_user(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path(user_id): Path<i64>,
 ) -> Result<Json<UserAndSubmissions>, AppError> {
     let user = sqlx::query!(
@@ -54,12 +54,16 @@ pub async fn view_specific_user(
         .await?;
         let mut submissions = vec![];
         for row in rows {
-            let fsm: StateMachine = serde_json::from_str(&row.solution_json)?;
+            let Some((fsm, verdict)) =
+                read_submission(row.id, &row.solution_json, &row.verdict_json)
+            else {
+                continue;
+            };
             submissions.push(SmallSubmissionInfo {
                 id: row.id,
                 task_id: row.task_id,
                 when_unix_time: row.when_unix_time,
-                verdict: serde_json::from_str(&row.verdict_json)?,
+                verdict,
                 node_count: fsm.nodes.len(),
                 link_count: fsm.links.len(),
             });
@@ -78,7 +82,7 @@ pub async fn view_specific_user(
 }
 
 pub async fn view_submission(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path((sid, token)): Path<(i64, String)>,
 ) -> Result<Json<Option<OthersSubmissionInfo>>, AppError> {
     // Check whether this submission exists
@@ -126,8 +130,9 @@ pub async fn view_submission(
     .await?;
     if let Some(_) = my_user_submission {
         // User has solved this task already
-        info.details =
-            OthersSubmissionDetails::Ok(serde_json::from_str(&submission_row.solution_json)?);
+        info.details = OthersSubmissionDetails::Ok(StateMachine::from_stored_json(
+            &submission_row.solution_json,
+        )?);
     }
 
     Ok(Json(Some(info)))
@@ -8,10 +8,14 @@ use fsm::{
     tester::{FSMTester, FSMTestingOutput},
 };
 
-use crate::{result::AppError, AppState};
+use crate::{
+    clusters::{drawing_fingerprint, language_fingerprint},
+    result::AppError,
+    AppState,
+};
 
 pub async fn submit_task(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path((_group_slug, task_slug, user_token)): Path<(String, String, String)>,
     Json(fsm): Json<StateMachine>,
 ) -> Result<Json<UserTaskSubmission>, AppError> {
@@ -38,8 +42,24 @@ pub async fn submit_task(
         return Err(anyhow::anyhow!("no such task to submit task to").into());
     };
 
+    if let Err(why) = fsm.validate() {
+        return Err(anyhow::anyhow!("the submitted machine is invalid: {why}").into());
+    }
+
+    // The model solution is only used if it is readable; otherwise, random testing still works.
+    let model = StateMachine::from_stored_json(&task.model_solution_json).ok();
+
     let seed = rand::random();
-    let verdict = match testing_inner(&fsm, &task.script, seed) {
+    // Grading runs scripts and determinizes machines, which can take a while, so it stays off the async workers.
+    let (graded, script) = (fsm.clone(), task.script.clone());
+    let (result, fingerprint) = tokio::task::spawn_blocking(move || {
+        (
+            testing_inner(&graded, &script, model.as_ref(), seed),
+            language_fingerprint(&graded),
+        )
+    })
+    .await?;
+    let verdict = match result {
         Err(why) => SubmissionVerdict::TaskInternalError(format!("{why}")),
         Ok(v) => match v {
             FSMTestingOutput::Ok(tests) => SubmissionVerdict::Ok(tests),
@@ -48,27 +68,46 @@ pub async fn submit_task(
                 total_tests,
                 first_failure_seed,
                 first_failure_expected_result,
+                first_failure_explanation,
+                first_failure_transductions,
             } => SubmissionVerdict::WrongAnswer {
                 total_tests,
                 successes,
                 first_failure_seed,
                 first_failure_expected_result,
+                first_failure_explanation,
+                first_failure_transductions,
             },
             FSMTestingOutput::FSMInvalid(validity) => SubmissionVerdict::InvalidFSM(validity),
+            FSMTestingOutput::Equivalent => SubmissionVerdict::Equivalent,
+            FSMTestingOutput::Counterexample {
+                word,
+                expected_result,
+                expected_count,
+                actual_count,
+            } => SubmissionVerdict::Counterexample {
+                word,
+                expected_result,
+                expected_count,
+                actual_count,
+            },
         },
     };
-    let is_ok = matches!(verdict, SubmissionVerdict::Ok(_));
+    let is_ok = verdict.is_ok();
 
-    let fsm_json = serde_json::to_string(&fsm).unwrap();
+    let drawing = drawing_fingerprint(&fsm);
+    let fsm_json = fsm.to_stored_json();
     let verdict_json = serde_json::to_string(&verdict).unwrap();
-    let rowid = sqlx::query!("INSERT INTO user_submission (when_unix_time, task_id, user_id, solution_json, init_random_seed, verdict_json, is_success) VALUES (?,?,?,?,?,?,?)",
+    let rowid = sqlx::query!("INSERT INTO user_submission (when_unix_time, task_id, user_id, solution_json, init_random_seed, verdict_json, is_success, language_fingerprint, drawing_fingerprint) VALUES (?,?,?,?,?,?,?,?,?)",
         now,
         task.id,
         user_id,
         fsm_json,
         seed,
         verdict_json,
-        is_ok
+        is_ok,
+        fingerprint,
+        drawing
     ).execute(&db).await?.last_insert_rowid();
 
     Ok(Json(UserTaskSubmission {
@@ -80,8 +119,18 @@ pub async fn submit_task(
     }))
 }
 
-fn testing_inner(fsm: &StateMachine, script: &str, seed: i64) -> anyhow::Result<FSMTestingOutput> {
+fn testing_inner(
+    fsm: &StateMachine,
+    script: &str,
+    model: Option<&StateMachine>,
+    seed: i64,
+) -> anyhow::Result<FSMTestingOutput> {
     let mut tester = FSMTester::new(fsm.clone(), script)?;
+    if let Some(model) = model {
+        if let Some(res) = tester.run_equivalence(model, seed)? {
+            return Ok(res);
+        }
+    }
     let res = tester.run_testing(seed)?;
     Ok(res)
 }
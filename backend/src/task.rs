@@ -10,11 +10,70 @@ use axum::{
     Json,
 };
 use fsm::fsm::StateMachine;
+use sqlx::SqlitePool;
 
 use crate::{result::AppError, AppState};
 
+/// Read the solution and verdict of a stored submission.
+///
+/// A submission that cannot be read is logged and left out, so that one bad row does not break the whole page.
+pub(crate) fn read_submission(
+    id: i64,
+    solution_json: &str,
+    verdict_json: &str,
+) -> Option<(StateMachine, SubmissionVerdict)> {
+    let solution = match StateMachine::from_stored_json(solution_json) {
+        Ok(solution) => solution,
+        Err(why) => {
+            tracing::warn!("Skipping submission {id}, whose solution cannot be read: {why}");
+            return None;
+        }
+    };
+    match serde_json::from_str(verdict_json) {
+        Ok(verdict) => Some((solution, verdict)),
+        Err(why) => {
+            tracing::warn!("Skipping submission {id}, whose verdict cannot be read: {why}");
+            None
+        }
+    }
+}
+
+/// Bring the model solutions of all tasks up to the current stored format,
+/// so that rows written before a format change read the same as they did when they were written.
+///
+/// Rows that are already current are left alone, so this is cheap to run on every start.
+pub async fn upgrade_model_solutions(db: &SqlitePool) -> anyhow::Result<()> {
+    let tasks = sqlx::query!("SELECT id, slug, model_solution_json FROM task")
+        .fetch_all(db)
+        .await?;
+    for task in tasks {
+        let model = match StateMachine::from_stored_json(&task.model_solution_json) {
+            Ok(model) => model,
+            Err(why) => {
+                tracing::warn!(
+                    "Leaving the model solution of task {} as it is: {why}",
+                    task.slug
+                );
+                continue;
+            }
+        };
+        let upgraded = model.to_stored_json();
+        if upgraded != task.model_solution_json {
+            sqlx::query!(
+                "UPDATE task SET model_solution_json=? WHERE id=?",
+                upgraded,
+                task.id
+            )
+            .execute(db)
+            .await?;
+            tracing::info!("Upgraded the model solution of task {}", task.slug);
+        }
+    }
+    Ok(())
+}
+
 pub async fn get_taskgroups(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     // Path(token): Path<String>,
 ) -> Result<Json<Vec<TaskGroupInfo>>, AppError> {
     let mut task_groups: Vec<_> = sqlx::query!("SELECT * FROM task_group")
@@ -51,7 +110,7 @@ pub async fn get_taskgroups(
 }
 
 pub async fn get_taskgroup(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path(slug): Path<String>,
 ) -> Result<(StatusCode, Json<Option<TaskGroupInfo>>), AppError> {
     let task_grp: Option<_> = sqlx::query!("SELECT * FROM task_group WHERE slug=?", slug)
@@ -82,7 +141,7 @@ pub async fn get_taskgroup(
 }
 
 pub async fn get_taskgroup_leaderboard(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path(slug): Path<String>,
 ) -> Result<(StatusCode, Json<Option<TaskGroupLeaderboard>>), AppError> {
     let task_grp: Option<_> = sqlx::query!("SELECT * FROM task_group WHERE slug=?", slug)
@@ -123,10 +182,13 @@ pub async fn get_taskgroup_leaderboard(
                     SubmissionVerdict,
                 )> = user_top_submissions.get_mut(&submission.user_id);
 
-                let fsm: StateMachine = serde_json::from_str(&submission.solution_json)
-                    .map_err(|v| anyhow::anyhow!("Invalid JSON in database: {v}"))?;
-                let verdict: SubmissionVerdict = serde_json::from_str(&submission.verdict_json)
-                    .map_err(|v| anyhow::anyhow!("Invalid JSON in database: {v}"))?;
+                let Some((fsm, verdict)) = read_submission(
+                    submission.id,
+                    &submission.solution_json,
+                    &submission.verdict_json,
+                ) else {
+                    continue;
+                };
 
                 match existing_by_user {
                     Some(existing) => {
@@ -179,7 +241,7 @@ pub async fn get_taskgroup_leaderboard(
 }
 
 pub async fn get_task(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path((_group_slug, task_slug)): Path<(String, String)>,
 ) -> Result<(StatusCode, Json<Option<TaskInfo>>), AppError> {
     let task = sqlx::query!("SELECT * FROM task WHERE slug=?", task_slug)
@@ -202,7 +264,7 @@ pub async fn get_task(
 }
 
 pub async fn get_task_by_id(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path(id): Path<i64>,
 ) -> Result<(StatusCode, Json<Option<TaskInfo>>), AppError> {
     let task = sqlx::query!("SELECT * FROM task WHERE id=?", id)
@@ -225,7 +287,7 @@ pub async fn get_task_by_id(
 }
 
 pub async fn get_task_and_userdata(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path((_group_slug, task_slug, user_token)): Path<(String, String, String)>,
 ) -> Result<(StatusCode, Json<Option<(TaskInfo, UserTaskSubmissions)>>), AppError> {
     let user_id = match sqlx::query!("SELECT * FROM account WHERE user_token=?", user_token)
@@ -252,14 +314,16 @@ pub async fn get_task_and_userdata(
                 .fetch_all(&db)
                 .await?
                 .iter()
-                .map(|v| UserTaskSubmission {
-                    id: v.id,
-                    task_id: v.task_id,
-                    when_unix_time: v.when_unix_time,
-                    solution: serde_json::from_str(&v.solution_json)
-                        .expect("Invalid solution JSON found in database?"),
-                    verdict: serde_json::from_str(&v.verdict_json)
-                        .expect("Invalid verdict JSON found in database?"),
+                .filter_map(|v| {
+                    let (solution, verdict) =
+                        read_submission(v.id, &v.solution_json, &v.verdict_json)?;
+                    Some(UserTaskSubmission {
+                        id: v.id,
+                        task_id: v.task_id,
+                        when_unix_time: v.when_unix_time,
+                        solution,
+                        verdict,
+                    })
                 })
                 .collect();
 
@@ -267,7 +331,7 @@ pub async fn get_task_and_userdata(
                     submissions.iter().max_by_key(|v| v.when_unix_time).cloned();
                 let latest_ok_submission = submissions
                     .iter()
-                    .filter(|v| matches!(v.verdict, SubmissionVerdict::Ok(_)))
+                    .filter(|v| v.verdict.is_ok())
                     .max_by_key(|v| v.when_unix_time)
                     .cloned();
                 UserTaskSubmissions {
@@ -296,7 +360,7 @@ pub async fn get_task_and_userdata(
 }
 
 pub async fn get_task_success(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path((_group_slug, task_slug, user_token)): Path<(String, String, String)>,
 ) -> Result<(StatusCode, Json<bool>), AppError> {
     let user_id = match sqlx::query!("SELECT * FROM account WHERE user_token=?", user_token)
@@ -8,7 +8,7 @@ use axum::{
 use rand::{distributions::Alphanumeric, Rng};
 
 pub async fn create_user(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Json(RegisterRequest { name, rudn_id }): Json<RegisterRequest>,
 ) -> Result<Json<UserInfo>, AppError> {
     let mut token = String::with_capacity(16);
@@ -35,7 +35,7 @@ pub async fn create_user(
 }
 
 pub async fn get_user(
-    State(AppState { db }): State<AppState>,
+    State(AppState { db, .. }): State<AppState>,
     Path(token): Path<String>,
 ) -> Result<Json<UserInfoResult>, AppError> {
     let data = match sqlx::query!("SELECT * FROM account WHERE user_token=?", token)
@@ -6,7 +6,7 @@ use web_sys::CanvasRenderingContext2d;
 use super::{
     any_link::LinkStuff,
     node::Node,
-    utils::{draw_arrow, draw_text, Circle, HIT_TARGET_PADDING, SNAP_TO_PADDING},
+    utils::{draw_arrow, draw_text, label_caption, Circle, HIT_TARGET_PADDING, SNAP_TO_PADDING},
     SelectionContext,
 };
 
@@ -179,7 +179,7 @@ impl NormalLink {
 
             draw_text(
                 c,
-                &self.text,
+                &label_caption(&self.text),
                 text_x,
                 text_y,
                 Some(-text_angle),
@@ -193,7 +193,7 @@ impl NormalLink {
             let text_angle = f64::atan2(end.0 - start.0, start.1 - end.1);
             draw_text(
                 c,
-                &self.text,
+                &label_caption(&self.text),
                 text_x,
                 text_y,
                 Some(text_angle + self.line_angle_adjust),
@@ -1,7 +1,7 @@
 use super::{
     any_link::LinkStuff,
     node::Node,
-    utils::{draw_arrow, draw_text, Circle, HIT_TARGET_PADDING},
+    utils::{draw_arrow, draw_text, label_caption, Circle, HIT_TARGET_PADDING},
     SelectionContext,
 };
 use anyhow::anyhow;
@@ -123,7 +123,7 @@ impl SelfLink {
         let text_y = circle.pos.1 + circle.radius * self.anchor_angle.sin();
         draw_text(
             c,
-            &self.text,
+            &label_caption(&self.text),
             text_x,
             text_y,
             Some(self.anchor_angle),
@@ -1,6 +1,6 @@
 use super::{
     node::Node,
-    utils::{draw_arrow, draw_text, HIT_TARGET_PADDING, SNAP_TO_PADDING},
+    utils::{draw_arrow, draw_text, label_caption, HIT_TARGET_PADDING, SNAP_TO_PADDING},
     SelectionContext,
 };
 use anyhow::anyhow;
@@ -75,7 +75,7 @@ impl StartLink {
         let text_angle = (start.1 - end.1).atan2(start.0 - end.0);
         draw_text(
             c,
-            &self.text,
+            &label_caption(&self.text),
             start.0,
             start.1,
             Some(text_angle),
@@ -1,7 +1,18 @@
+use fsm::label::Label;
 use web_sys::CanvasRenderingContext2d;
 
 use super::SelectionContext;
 
+/// The text to draw for a link label: `\e` is shown as ε, and labels that cannot be read are marked.
+pub(super) fn label_caption(text: &str) -> String {
+    match text.parse::<Label>() {
+        Ok(_) if text.is_empty() => String::new(),
+        Ok(label) if label.alternatives.iter().all(|a| a.is_empty()) => String::from("ε"),
+        Ok(label) => label.to_string(),
+        Err(_) => format!("{text}⚠"),
+    }
+}
+
 pub(super) fn draw_text(
     c: &CanvasRenderingContext2d,
     real_text: &str,
@@ -4,7 +4,13 @@ use std::{
     time::Duration,
 };
 
-use fsm::fsm::{FSMError, FSMOutput, StateMachine, StateMachineEvaluator};
+use fsm::{
+    fsm::{FSMError, FSMOutput, StateMachine, StateMachineEvaluator},
+    lint::Lint,
+    pushdown::PushdownAcceptance,
+    transducer::Transduction,
+    turing::{TuringEvaluator, BLANK, DEFAULT_STEP_BUDGET},
+};
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
@@ -14,6 +20,10 @@ use crate::canvas::Canvas;
 
 pub struct CanvasPlayer {
     fsm: StateMachine,
+    transducer: bool,
+    pushdown: Option<PushdownAcceptance>,
+    /// Present when the machine is run as a Turing machine, instead of `eval`.
+    turing: Option<Result<TuringEvaluator, FSMError>>,
     eval: Result<Result<StateMachineEvaluator, FSMError>, String>,
     word: String,
     link_highlights: Rc<HashMap<usize, JsValue>>,
@@ -83,6 +93,22 @@ pub struct CanvasPlayerProps {
 
     #[prop_or_default]
     pub play_pulse: usize,
+
+    /// Highlight the problems found by [StateMachine::lint] while nothing is running.
+    #[prop_or(false)]
+    pub show_lints: bool,
+
+    /// Run the machine as a transducer: step through its inputs, and show what it writes.
+    #[prop_or(false)]
+    pub transducer: bool,
+
+    /// Run the machine as a Turing machine, showing its tape instead of the word.
+    #[prop_or(false)]
+    pub turing: bool,
+
+    /// Run the machine as a pushdown automaton accepting this way, showing every cursor's stack.
+    #[prop_or_default]
+    pub pushdown: Option<PushdownAcceptance>,
 }
 
 #[derive(Debug)]
@@ -92,6 +118,8 @@ pub enum CanvasPlayerMsg {
     NewFSMApplied(StateMachine),
     ResetFSM,
     AdvanceFSM,
+    StepBack,
+    ScrubTo(u64),
     ToggleAuto,
 }
 
@@ -108,59 +136,74 @@ impl CanvasPlayer {
             self.status = None;
         }
 
-        if let Ok(Ok(eval)) = &mut self.eval {
-            log::debug!("step!");
+        if let Some(Ok(eval)) = &mut self.turing {
             eval.step();
-            complexity = eval.link_cursors().len() + eval.node_cursors().len();
-            let mut color_idx = 0;
-
             let mut new_nodehl = HashMap::new();
             let mut new_linkhl = HashMap::new();
             let mut new_node_crosses: HashSet<usize> = (*self.node_crosses).clone();
-            for (node_idx, remaining_string) in eval.node_cursors() {
-                new_nodehl.insert(*node_idx, JsValue::from_str(COLORS[color_idx]));
-                color_idx += 1;
-                color_idx = color_idx % COLORS.len();
-
-                if remaining_string.is_empty() && self.fsm.nodes[*node_idx].accept_state {
+            for (color_idx, (node_idx, _tape)) in eval.cursors().iter().enumerate() {
+                new_nodehl.insert(
+                    *node_idx,
+                    JsValue::from_str(COLORS[color_idx % COLORS.len()]),
+                );
+                if self.fsm.nodes[*node_idx].accept_state {
                     new_node_crosses.insert(*node_idx);
-                    self.status = Some(FSMOutput::Accept);
                 }
             }
-            color_idx = 0;
-            for (link_idx, _before_remaining_string, _after_remaining_string) in eval.link_cursors()
-            {
-                new_linkhl.insert(*link_idx, JsValue::from_str(COLORS[color_idx]));
-                color_idx += 1;
-                color_idx = color_idx % COLORS.len();
+            for (color_idx, link_idx) in eval.links_taken().iter().enumerate() {
+                new_linkhl.insert(
+                    *link_idx,
+                    JsValue::from_str(COLORS[color_idx % COLORS.len()]),
+                );
             }
+            self.node_highlights = Rc::new(new_nodehl);
+            self.link_highlights = Rc::new(new_linkhl);
+            self.node_crosses = Rc::new(new_node_crosses);
 
-            // If only nodes with no exit are highlighted, then we reject.
-            if eval.link_cursors().is_empty() && self.status.is_none() {
-                let mut do_reject = true;
-                for (node_idx, remaining_string) in eval.node_cursors() {
-                    for link in self
-                        .fsm
-                        .links
-                        .iter()
-                        .filter(|l| l.get_nodes().0 == Some(*node_idx))
-                    {
-                        if remaining_string.starts_with(link.get_text()) {
-                            do_reject = false;
-                        }
-                    }
+            match eval.outcome() {
+                None => {}
+                Some(Ok(output)) => {
+                    self.status = Some(output);
+                    needs_reset = true;
                 }
-
-                if do_reject {
+                Some(Err(err)) => {
+                    // The machine ran out of steps or configurations: keep showing why, instead of starting over.
+                    self.turing = Some(Err(err));
                     self.status = Some(FSMOutput::Reject);
+                    self.auto_mode = false;
+                    ctx.props().on_is_running.emit(false);
+                    ctx.props().on_terminate.emit(FSMOutput::Reject);
+                    return;
                 }
             }
 
-            needs_reset = eval.link_cursors().is_empty() && eval.node_cursors().is_empty();
+            if !self.max_step.1 {
+                self.max_step.0 = self.current_step + 1;
+            }
+            if needs_reset {
+                self.max_step.1 = true;
+            } else {
+                self.current_step += 1;
+            }
+        }
 
-            self.node_highlights = Rc::new(new_nodehl);
-            self.link_highlights = Rc::new(new_linkhl);
-            self.node_crosses = Rc::new(new_node_crosses);
+        if let (None, Ok(Ok(eval))) = (&self.turing, &mut self.eval) {
+            log::debug!("step!");
+            eval.step();
+            complexity = eval.link_cursors().len() + eval.node_cursors().len();
+            needs_reset = eval.is_finished();
+            if needs_reset && !eval.accepted() {
+                if let Some(err) = eval.error().cloned() {
+                    // A stack grew too deep, so the word is not known to be rejected: show why instead.
+                    self.eval = Ok(Err(err));
+                    self.status = Some(FSMOutput::Reject);
+                    self.auto_mode = false;
+                    ctx.props().on_is_running.emit(false);
+                    ctx.props().on_terminate.emit(FSMOutput::Reject);
+                    return;
+                }
+            }
+            self.show_eval(false);
 
             if !self.max_step.1 {
                 self.max_step.0 = self.current_step + 1;
@@ -196,12 +239,120 @@ impl CanvasPlayer {
         }
     }
 
+    /// Show the evaluator's current cursors, and decide whether the word is accepted or rejected by now.
+    ///
+    /// The nodes where the word was accepted are kept from the previous steps,
+    /// unless `from_scratch` is set: then they are found again from the evaluator's history.
+    fn show_eval(&mut self, from_scratch: bool) {
+        let Ok(Ok(eval)) = &mut self.eval else {
+            return;
+        };
+        let accepting = |eval: &StateMachineEvaluator, cursors: &Vec<(usize, String, String)>| {
+            cursors
+                .iter()
+                .filter(|(node_idx, remaining_string, stack)| {
+                    remaining_string.is_empty() && eval.accepts(*node_idx, stack)
+                })
+                .map(|(node_idx, ..)| *node_idx)
+                .collect::<Vec<_>>()
+        };
+
+        let mut new_node_crosses: HashSet<usize> = if from_scratch {
+            let mut crosses = HashSet::new();
+            for position in 0..eval.position() {
+                if let Some(step) = eval.step_at(position).cloned() {
+                    crosses.extend(accepting(eval, &step.node_cursors));
+                }
+            }
+            crosses
+        } else {
+            (*self.node_crosses).clone()
+        };
+        new_node_crosses.extend(accepting(eval, eval.node_cursors()));
+
+        let mut new_nodehl = HashMap::new();
+        let mut new_linkhl = HashMap::new();
+        let mut color_idx = 0;
+        for (node_idx, _remaining_string, _stack) in eval.node_cursors() {
+            new_nodehl.insert(*node_idx, JsValue::from_str(COLORS[color_idx]));
+            color_idx += 1;
+            color_idx = color_idx % COLORS.len();
+        }
+        color_idx = 0;
+        for (link_idx, _before_remaining_string, _after_remaining_string, _stack) in
+            eval.link_cursors()
+        {
+            new_linkhl.insert(*link_idx, JsValue::from_str(COLORS[color_idx]));
+            color_idx += 1;
+            color_idx = color_idx % COLORS.len();
+        }
+
+        self.status = None;
+        if !new_node_crosses.is_empty() {
+            self.status = Some(FSMOutput::Accept);
+        } else if eval.link_cursors().is_empty() && eval.position() > 0 {
+            // If only nodes with no exit are highlighted, then we reject.
+            // The exits are found by the evaluator, so that label patterns and stacks are read the way it reads them.
+            let do_reject =
+                eval.node_cursors()
+                    .iter()
+                    .all(|(node_idx, remaining_string, stack)| {
+                        eval.links_matching(Some(*node_idx), remaining_string, stack)
+                            .is_empty()
+                    });
+
+            if do_reject {
+                self.status = Some(FSMOutput::Reject);
+            }
+        }
+
+        self.node_highlights = Rc::new(new_nodehl);
+        self.link_highlights = Rc::new(new_linkhl);
+        self.node_crosses = Rc::new(new_node_crosses);
+    }
+
+    /// Move the evaluator to the cursors after this many steps, and show them.
+    fn scrub_to(&mut self, position: usize) {
+        if self.turing.is_some() {
+            return;
+        }
+        if let Ok(Ok(eval)) = &mut self.eval {
+            eval.jump_to(position);
+            self.current_step = eval.position() as u64;
+        }
+        self.show_eval(true);
+    }
+
+    /// The machine whose links are stepped through: for a transducer, the acceptor of its inputs,
+    /// and for a pushdown automaton, its links without the stack parts.
+    fn machine_to_run(&self) -> StateMachine {
+        if self.transducer {
+            self.fsm.input_machine()
+        } else if self.pushdown.is_some() {
+            self.fsm.pushdown_input_machine()
+        } else {
+            self.fsm.clone()
+        }
+    }
+
+    /// Start the evaluator on the word, with a stack if the machine is a pushdown automaton.
+    fn start_eval(&self) -> Result<StateMachineEvaluator, FSMError> {
+        match self.pushdown {
+            Some(acceptance) => {
+                StateMachineEvaluator::new_pushdown(self.fsm.clone(), self.word.clone(), acceptance)
+            }
+            None => StateMachineEvaluator::new(self.machine_to_run(), self.word.clone()),
+        }
+    }
+
     fn reset(&mut self, changed: bool) {
         log::info!("FSM Reset with changed={changed}");
-        self.eval = Ok(StateMachineEvaluator::new(
-            self.fsm.clone(),
-            self.word.clone(),
-        ));
+        // The same machine on the same word can just be rewound, keeping the history for scrubbing.
+        match &mut self.eval {
+            Ok(Ok(eval)) if !changed => eval.jump_to(0),
+            _ => self.eval = Ok(self.start_eval()),
+        }
+        self.turing = turing_evaluator(&self.fsm, &self.word, self.turing.is_some());
         self.link_highlights = Default::default();
         self.node_highlights = Default::default();
         self.node_crosses = Default::default();
@@ -230,7 +381,7 @@ impl CanvasPlayer {
                     let mut this_char_cursors = vec![];
 
                     color_idx = 0;
-                    for (node_id, remaining_word) in eval.node_cursors() {
+                    for (node_id, remaining_word, _stack) in eval.node_cursors() {
                         let remaining_word_len = remaining_word.chars().count();
                         let remaining_word_start_idx = word_len - remaining_word_len;
                         if remaining_word_start_idx == char_idx {
@@ -244,7 +395,7 @@ impl CanvasPlayer {
                 }
 
                 color_idx = 0;
-                for (node_id, remaining_word) in eval.node_cursors() {
+                for (node_id, remaining_word, _stack) in eval.node_cursors() {
                     if remaining_word.is_empty() {
                         final_char_cursors.push((*node_id, COLORS[color_idx]));
                     }
@@ -254,7 +405,7 @@ impl CanvasPlayer {
 
                 let mut under_char_cursors = vec![vec![]; word_len + 1];
                 color_idx = 0;
-                for (link_id, before_word, after_word) in eval.link_cursors() {
+                for (link_id, before_word, after_word, _stack) in eval.link_cursors() {
                     let before_word_len = before_word.chars().count();
                     let after_word_len = after_word.chars().count();
                     let before_word_start = word_len - before_word_len;
@@ -297,17 +448,107 @@ impl CanvasPlayer {
             Err(text) => {
                 html!(<p>{"Не могу обработать автомат: "}{text}</p>)
             }
-            Ok(Err(e)) => match e {
-                FSMError::InfiniteLoop => {
-                    html!(<p class="text-danger" style="flex: 1;">{"Автомат содержит бесконечный цикл"}</p>)
-                }
-                FSMError::NoEntryLinks => {
-                    html!(<p class="text-danger" style="flex: 1;">{"Автомат не имеет входных стрелочек"}</p>)
-                }
-                FSMError::DisjointedLink(_) => {
-                    html!(<p class="text-danger" style="flex: 1;">{"Автомат имеет ошибку связности"}</p>)
-                }
-            },
+            Ok(Err(e)) => self.error_indicator(e),
+        }
+    }
+
+    fn error_indicator(&self, e: &FSMError) -> Html {
+        match e {
+            FSMError::InfiniteLoop => {
+                html!(<p class="text-danger" style="flex: 1;">{"Автомат содержит бесконечный цикл"}</p>)
+            }
+            FSMError::NoEntryLinks => {
+                html!(<p class="text-danger" style="flex: 1;">{"Автомат не имеет входных стрелочек"}</p>)
+            }
+            FSMError::DisjointedLink(_) => {
+                html!(<p class="text-danger" style="flex: 1;">{"Автомат имеет ошибку связности"}</p>)
+            }
+            FSMError::InvalidLabel(link, why) => {
+                let text = self.fsm.links.get(*link).map_or("", |l| l.get_text());
+                html!(<p class="text-danger" style="flex: 1;">{"Непонятный текст на стрелочке «"}{text}{"»: "}{why.to_string()}</p>)
+            }
+            FSMError::InvalidPushdownLabel(link, why) => {
+                let text = self.fsm.links.get(*link).map_or("", |l| l.get_text());
+                html!(<p class="text-danger" style="flex: 1;">{"Непонятный текст на стрелочке магазинного автомата «"}{text}{"»: "}{why.to_string()}</p>)
+            }
+            FSMError::InvalidTuringLabel(link, why) => {
+                let text = self.fsm.links.get(*link).map_or("", |l| l.get_text());
+                html!(<p class="text-danger" style="flex: 1;">{"Непонятный текст на стрелочке машины Тьюринга «"}{text}{"»: "}{why.to_string()}</p>)
+            }
+            FSMError::WildcardNotExpressible => {
+                html!(<p class="text-danger" style="flex: 1;">{"Точку на стрелочке здесь нельзя записать без исключений"}</p>)
+            }
+            FSMError::TooManyStates(limit) => {
+                html!(<p class="text-danger" style="flex: 1;">{"Детерминированный автомат получился бы больше "}{limit}{" состояний"}</p>)
+            }
+            FSMError::StackTooDeep(limit) => {
+                html!(<p class="text-danger" style="flex: 1;">{"Магазин автомата стал глубже "}{limit}{" символов, а слово так и не принято"}</p>)
+            }
+            FSMError::TooManyConfigurations(limit) => {
+                html!(<p class="text-danger" style="flex: 1;">{"Автомат побывал больше чем в "}{limit}{" конфигурациях, а слово так и не решено"}</p>)
+            }
+        }
+    }
+
+    /// Show the stack of every node cursor of a pushdown automaton, in the cursor's color, with the top on the right.
+    fn stack_indicator(&self) -> Html {
+        let Ok(Ok(eval)) = &self.eval else {
+            return html!();
+        };
+        let stacks = eval
+            .node_cursors()
+            .iter()
+            .enumerate()
+            .map(|(color_idx, (_node, _remaining_word, stack))| {
+                let color = COLORS[color_idx % COLORS.len()];
+                let stack = if stack.is_empty() { "ε" } else { stack.as_str() };
+                html!(<div style={format!("border-left: 0.2em solid; padding-left: 0.3em; border-left-color: {color};")}>{stack}</div>)
+            })
+            .collect::<Html>();
+        html!(<div class="font-monospace mb-2">{"Магазин:"}{stacks}</div>)
+    }
+
+    /// Show the tape of every configuration the Turing machine is in, with the head in the cursor's color.
+    fn tape_indicator(&self) -> Html {
+        match &self.turing {
+            Some(Ok(eval)) => {
+                let tapes = eval
+                    .cursors()
+                    .iter()
+                    .enumerate()
+                    .map(|(color_idx, (_node, tape))| {
+                        let color = COLORS[color_idx % COLORS.len()];
+                        let cells = tape
+                            .cells()
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, cell)| {
+                                let cell = if *cell == BLANK { '␣' } else { *cell };
+                                if idx == tape.head() {
+                                    html!(<span style={format!("border: 0.1em solid; border-color: {color};")}>{cell}</span>)
+                                } else {
+                                    html!(<span style="border: 0.1em solid transparent;">{cell}</span>)
+                                }
+                            })
+                            .collect::<Html>();
+                        html!(<div>{cells}</div>)
+                    })
+                    .collect::<Html>();
+                html!(<span class="fs-3 font-monospace" style="display: inline-block; flex: 1;">{tapes}</span>)
+            }
+            Some(Err(FSMError::InfiniteLoop)) => {
+                html!(<p class="text-danger" style="flex: 1;">{"Машина Тьюринга не остановилась за "}{DEFAULT_STEP_BUDGET}{" шагов"}</p>)
+            }
+            Some(Err(e)) => self.error_indicator(e),
+            None => self.word_indicator(),
+        }
+    }
+
+    /// Whether the machine can be run at all.
+    fn is_valid(&self) -> bool {
+        match &self.turing {
+            Some(turing) => turing.is_ok(),
+            None => matches!(self.eval, Ok(Ok(_))),
         }
     }
 
@@ -325,6 +566,15 @@ impl CanvasPlayer {
 
     fn status_indicator(&self) -> Html {
         match self.status {
+            Some(FSMOutput::Accept) if self.transducer => {
+                let output = match self.fsm.transduce(&self.word) {
+                    Ok(Transduction::Output(output)) if output.is_empty() => String::from("ε"),
+                    Ok(Transduction::Output(output)) => output,
+                    Ok(Transduction::Ambiguous(a, b)) => format!("{a} | {b} ?"),
+                    Ok(Transduction::Rejected) | Err(_) => String::from("?"),
+                };
+                html!(<span class="text-success fs-4" style="flex: 1;">{"ACCEPT: "}<span class="font-monospace">{output}</span></span>)
+            }
             Some(FSMOutput::Accept) => {
                 html!(<span class="text-success fs-4" style="flex: 1;">{"ACCEPT"}</span>)
             }
@@ -344,6 +594,33 @@ fn duration_from_slider(slider: u32) -> Duration {
     Duration::from_millis(time as u64)
 }
 
+/// Start the machine as a Turing machine, if it is meant to be one.
+fn turing_evaluator(
+    fsm: &StateMachine,
+    word: &str,
+    turing: bool,
+) -> Option<Result<TuringEvaluator, FSMError>> {
+    turing.then(|| TuringEvaluator::new(fsm.clone(), word, DEFAULT_STEP_BUDGET))
+}
+
+/// Color the nodes and links that the lints are about.
+pub fn lint_highlights(
+    lints: &[Lint],
+) -> (Rc<HashMap<usize, JsValue>>, Rc<HashMap<usize, JsValue>>) {
+    let color = JsValue::from_str("orange");
+    let nodes = lints
+        .iter()
+        .flat_map(|lint| lint.nodes())
+        .map(|node| (node, color.clone()))
+        .collect();
+    let links = lints
+        .iter()
+        .flat_map(|lint| lint.links())
+        .map(|link| (link, color.clone()))
+        .collect();
+    (Rc::new(nodes), Rc::new(links))
+}
+
 impl Component for CanvasPlayer {
     type Message = CanvasPlayerMsg;
 
@@ -351,12 +628,12 @@ impl Component for CanvasPlayer {
 
     fn create(ctx: &Context<Self>) -> Self {
         let fsm = ctx.props().fsm.clone();
-        Self {
-            fsm: fsm.clone(),
-            eval: Ok(StateMachineEvaluator::new(
-                fsm,
-                ctx.props().word.to_string(),
-            )),
+        let mut player = Self {
+            turing: turing_evaluator(&fsm, &ctx.props().word, ctx.props().turing),
+            fsm,
+            transducer: ctx.props().transducer,
+            pushdown: ctx.props().pushdown,
+            eval: Err(String::new()),
             word: ctx.props().word.to_string(),
             node_highlights: Default::default(),
             node_crosses: Default::default(),
@@ -368,7 +645,9 @@ impl Component for CanvasPlayer {
             current_step: 0,
             max_step: (0, false),
             status: None,
-        }
+        };
+        player.eval = Ok(player.start_eval());
+        player
     }
 
     fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
@@ -376,8 +655,18 @@ impl Component for CanvasPlayer {
         // log::info!("OLD = {:?}", old_props);
         self.fsm = ctx.props().fsm.clone();
         self.word = ctx.props().word.to_string();
+        self.transducer = ctx.props().transducer;
+        self.pushdown = ctx.props().pushdown;
+        if ctx.props().turing != old_props.turing {
+            self.turing = turing_evaluator(&self.fsm, &self.word, ctx.props().turing);
+        }
 
-        if self.fsm != old_props.fsm || ctx.props().word != old_props.word {
+        if self.fsm != old_props.fsm
+            || ctx.props().word != old_props.word
+            || ctx.props().transducer != old_props.transducer
+            || ctx.props().turing != old_props.turing
+            || ctx.props().pushdown != old_props.pushdown
+        {
             self.reset(true);
             self.auto_mode = self.auto_mode || ctx.props().play_on_change;
         }
@@ -387,7 +676,12 @@ impl Component for CanvasPlayer {
             self.reset(false);
         }
 
-        if ctx.props().word != old_props.word || ctx.props().fsm != old_props.fsm {
+        if ctx.props().word != old_props.word
+            || ctx.props().fsm != old_props.fsm
+            || ctx.props().transducer != old_props.transducer
+            || ctx.props().turing != old_props.turing
+            || ctx.props().pushdown != old_props.pushdown
+        {
             self.status = None;
         }
         // self.auto_mode = ctx.props().auto_play && ctx.props().auto_restart;
@@ -400,11 +694,19 @@ impl Component for CanvasPlayer {
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
+        let (node_highlights, link_highlights) = if ctx.props().show_lints
+            && self.node_highlights.is_empty()
+            && self.link_highlights.is_empty()
+        {
+            lint_highlights(&self.machine_to_run().lint())
+        } else {
+            (self.node_highlights.clone(), self.link_highlights.clone())
+        };
         html! {
             <div style="width: 800px; margin: 0 auto;">
                 <Canvas init={self.fsm.clone()}
-                    node_highlights={self.node_highlights.clone()}
-                    link_highlights={self.link_highlights.clone()}
+                    {node_highlights}
+                    {link_highlights}
                     node_crosses={self.node_crosses.clone()}
                     immutable={!ctx.props().editable}
                     onchange={
@@ -415,7 +717,7 @@ impl Component for CanvasPlayer {
 
                 <div style="display: flex;" class="mb-2">
                     if ctx.props().show_word_indicator {
-                        {self.word_indicator()}
+                        {self.tape_indicator()}
                     }
 
                     if ctx.props().show_status_indicator {
@@ -424,6 +726,10 @@ impl Component for CanvasPlayer {
 
                 </div>
 
+                if self.pushdown.is_some() && self.turing.is_none() {
+                    {self.stack_indicator()}
+                }
+
                 if ctx.props().show_transport_buttons {
                     <button class="btn btn-outline-success ml-3" onclick={
                         ctx.link().callback(|_ev: MouseEvent| {
@@ -440,6 +746,11 @@ impl Component for CanvasPlayer {
                                 CanvasPlayerMsg::ResetFSM
                             })
                         }>{BI::ARROW_REPEAT}</button>
+                        <button class="btn btn-outline-primary" disabled={self.turing.is_some() || self.current_step == 0} onclick={
+                            ctx.link().callback(|_ev: MouseEvent| {
+                                CanvasPlayerMsg::StepBack
+                            })
+                        }>{BI::REWIND_FILL}</button>
                         <button class="btn btn-outline-primary" onclick={
                             ctx.link().callback(|_ev: MouseEvent| {
                                 CanvasPlayerMsg::AdvanceFSM
@@ -462,6 +773,16 @@ impl Component for CanvasPlayer {
 
                 if ctx.props().show_steps_indicator {
                     {self.steps_indicator()}
+                    if self.turing.is_none() {
+                        <input class="form-input mx-2" type="range" min="0" max={self.max_step.0.to_string()} value={self.current_step.to_string()} disabled={self.auto_mode} oninput={
+                            ctx.link().callback(|ev: InputEvent| {
+                                let target: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+                                let value = target.value();
+                                let value = value.parse().unwrap();
+                                CanvasPlayerMsg::ScrubTo(value)
+                            })
+                        }/>
+                    }
                 }
             </div>
         }
@@ -487,11 +808,7 @@ impl Component for CanvasPlayer {
                 ctx.props().on_fsm_apply.emit(fsm.clone());
                 self.fsm = fsm;
                 self.reset(true);
-                let validity = match self.eval {
-                    Ok(Ok(_)) => true,
-                    _ => false,
-                };
-                ctx.props().on_validity.emit(validity);
+                ctx.props().on_validity.emit(self.is_valid());
             }
             CanvasPlayerMsg::ResetFSM => {
                 self.reset(false);
@@ -504,6 +821,12 @@ impl Component for CanvasPlayer {
             CanvasPlayerMsg::AdvanceFSM => {
                 self.step(ctx);
             }
+            CanvasPlayerMsg::StepBack => {
+                self.scrub_to(self.current_step.saturating_sub(1) as usize);
+            }
+            CanvasPlayerMsg::ScrubTo(step) => {
+                self.scrub_to(step as usize);
+            }
         };
 
         true
@@ -1,11 +1,20 @@
-use fsm::fsm::StateMachine;
+use fsm::{
+    fsm::{FSMError, StateMachine},
+    layout::LayoutAlgorithm,
+    regex::Regex,
+    transducer::Transduction,
+    turing::{TuringEvaluator, DEFAULT_STEP_BUDGET},
+};
 use shadow_clone::shadow_clone;
 use wasm_bindgen::JsCast;
-use web_sys::HtmlInputElement;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
 use yew::prelude::*;
 use yew_bootstrap::component::{Column, Row};
 
-use crate::canvas::Canvas;
+use crate::{
+    canvas::Canvas,
+    canvas_player::{lint_highlights, CanvasPlayer},
+};
 
 #[function_component(Editor)]
 pub fn editor() -> Html {
@@ -13,14 +22,24 @@ pub fn editor() -> Html {
     let fsm_to_load = use_state(|| None);
 
     let fsm_output = use_state(|| None);
+    let transduce_output = use_state(|| None);
+    let turing_output = use_state(|| None);
     let fsm_json_content = use_state(|| String::from("{}"));
 
     let save_fsm = {
-        shadow_clone!(fsm, fsm_output, fsm_json_content);
-        move |v| {
-            fsm_json_content.set(serde_json::to_string(&v).unwrap());
+        shadow_clone!(
+            fsm,
+            fsm_output,
+            transduce_output,
+            turing_output,
+            fsm_json_content
+        );
+        move |v: StateMachine| {
+            fsm_json_content.set(v.to_stored_json());
             fsm.set(v);
             fsm_output.set(None);
+            transduce_output.set(None);
+            turing_output.set(None);
         }
     };
 
@@ -34,11 +53,18 @@ pub fn editor() -> Html {
 
     let word_to_check_state = use_state(String::new);
     let word_input = {
-        shadow_clone!(word_to_check_state, fsm_output);
+        shadow_clone!(
+            word_to_check_state,
+            fsm_output,
+            transduce_output,
+            turing_output
+        );
         move |ev: InputEvent| {
             let target: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
             word_to_check_state.set(target.value());
             fsm_output.set(None);
+            transduce_output.set(None);
+            turing_output.set(None);
         }
     };
 
@@ -52,12 +78,39 @@ pub fn editor() -> Html {
         }
     };
 
+    let do_transduce = {
+        shadow_clone!(fsm, transduce_output, word_to_check_state);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            transduce_output.set(Some(fsm.transduce(&word_to_check_state)));
+        }
+    };
+
+    let do_run_turing = {
+        shadow_clone!(fsm, turing_output, word_to_check_state);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            let result =
+                TuringEvaluator::new((*fsm).clone(), &word_to_check_state, DEFAULT_STEP_BUDGET)
+                    .and_then(|mut eval| loop {
+                        match eval.outcome() {
+                            Some(outcome) => {
+                                let tape = eval.cursors().first().map(|(_, tape)| tape.contents());
+                                break outcome.map(|output| (output, eval.steps(), tape));
+                            }
+                            None => eval.step(),
+                        }
+                    });
+            turing_output.set(Some(result));
+        }
+    };
+
     let do_load_json = {
         shadow_clone!(fsm_output, fsm_json_content, fsm_to_load);
         move |ev: MouseEvent| {
             ev.prevent_default();
             let fsm_text: String = (&*fsm_json_content).clone();
-            match serde_json::from_str::<StateMachine>(&fsm_text) {
+            match StateMachine::from_pasted_json(&fsm_text) {
                 Ok(new_fsm) => {
                     fsm_to_load.set(Some(new_fsm));
                     fsm_output.set(None);
@@ -69,6 +122,246 @@ pub fn editor() -> Html {
         }
     };
 
+    let do_determinize = {
+        shadow_clone!(fsm, fsm_output, fsm_to_load);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            match fsm.determinize() {
+                Ok(new_fsm) => {
+                    fsm_to_load.set(Some(new_fsm));
+                    fsm_output.set(None);
+                }
+                Err(why) => {
+                    gloo::dialogs::alert(&format!("Cannot determinize: {why}"));
+                }
+            }
+        }
+    };
+
+    // Replace the machine with the result of a construction on it.
+    let transform =
+        |name: &'static str, construction: fn(&StateMachine) -> Result<StateMachine, FSMError>| {
+            shadow_clone!(fsm, fsm_output, fsm_to_load);
+            move |ev: MouseEvent| {
+                ev.prevent_default();
+                match construction(&fsm) {
+                    Ok(new_fsm) => {
+                        fsm_to_load.set(Some(new_fsm));
+                        fsm_output.set(None);
+                    }
+                    Err(why) => {
+                        gloo::dialogs::alert(&format!("Cannot build {name}: {why}"));
+                    }
+                }
+            }
+        };
+    let do_star = transform("star", StateMachine::star);
+    let do_plus = transform("plus", StateMachine::plus);
+    let do_reverse = transform("reversal", StateMachine::reverse);
+
+    // Rearrange the machine on the canvas; it stays the same machine, so the last check still applies.
+    let tidy_up = |algorithm: LayoutAlgorithm| {
+        shadow_clone!(fsm, fsm_to_load);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            fsm_to_load.set(Some(fsm.layout(algorithm)));
+        }
+    };
+    let do_tidy_layered = tidy_up(LayoutAlgorithm::Layered);
+    let do_tidy_force = tidy_up(LayoutAlgorithm::ForceDirected);
+
+    let minimize_report = use_state(|| html!());
+    let do_minimize = {
+        shadow_clone!(fsm, fsm_output, fsm_to_load, minimize_report);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            match fsm.minimize() {
+                Ok(result) => {
+                    let groups = result
+                        .indistinguishable
+                        .iter()
+                        .map(|group| {
+                            let nodes: Vec<String> = group.iter().map(|v| v.to_string()).collect();
+                            html!(<li>{"Nodes "}{nodes.join(", ")}{" are indistinguishable"}</li>)
+                        })
+                        .collect::<Html>();
+                    minimize_report.set(html!(
+                        <div>
+                            <p>{"Minimal machine has "}{result.machine.nodes.len()}{" nodes (this one had "}{fsm.nodes.len()}{")"}</p>
+                            <ul>{groups}</ul>
+                        </div>
+                    ));
+                    fsm_to_load.set(Some(result.machine));
+                    fsm_output.set(None);
+                }
+                Err(why) => {
+                    gloo::dialogs::alert(&format!("Cannot minimize: {why}"));
+                }
+            }
+        }
+    };
+
+    let dot_content = use_state(String::new);
+    let oninput_dot = {
+        shadow_clone!(dot_content);
+        move |ev: InputEvent| {
+            let target: HtmlTextAreaElement = ev.target().unwrap().dyn_into().unwrap();
+            dot_content.set(target.value());
+        }
+    };
+
+    let do_load_dot = {
+        shadow_clone!(fsm_output, dot_content, fsm_to_load);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            match StateMachine::from_dot(&dot_content) {
+                Ok(new_fsm) => {
+                    fsm_to_load.set(Some(new_fsm));
+                    fsm_output.set(None);
+                }
+                Err(why) => {
+                    gloo::dialogs::alert(&format!("Error in DOT: {why}"));
+                }
+            }
+        }
+    };
+
+    let do_export_dot = {
+        shadow_clone!(fsm, dot_content);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            dot_content.set(fsm.to_dot());
+        }
+    };
+
+    let formal_content = use_state(String::new);
+    let oninput_formal = {
+        shadow_clone!(formal_content);
+        move |ev: InputEvent| {
+            let target: HtmlTextAreaElement = ev.target().unwrap().dyn_into().unwrap();
+            formal_content.set(target.value());
+        }
+    };
+
+    let do_load_formal = {
+        shadow_clone!(fsm_output, formal_content, fsm_to_load);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            match StateMachine::from_formal(&formal_content) {
+                Ok(new_fsm) => {
+                    fsm_to_load.set(Some(new_fsm));
+                    fsm_output.set(None);
+                }
+                Err(why) => {
+                    gloo::dialogs::alert(&format!("Error in 5-tuple: {why}"));
+                }
+            }
+        }
+    };
+
+    let do_export_formal = {
+        shadow_clone!(fsm, formal_content);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            formal_content.set(fsm.to_formal());
+        }
+    };
+
+    let tikz_content = use_state(|| None);
+    let do_export_tikz = {
+        shadow_clone!(fsm, tikz_content);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            tikz_content.set(Some(fsm.to_tikz()));
+        }
+    };
+    let tikz_html = match &*tikz_content {
+        Some(tikz) => html!(
+            <textarea class="form-control font-monospace mb-3" rows="6" readonly={true} value={tikz.clone()}></textarea>
+        ),
+        None => html!(),
+    };
+
+    // The reader and the download link must stay alive until they are done.
+    let jff_reader = use_mut_ref(|| None::<gloo::file::callbacks::FileReader>);
+    let jff_download = use_mut_ref(|| None::<gloo::file::ObjectUrl>);
+
+    let on_jff_file = {
+        shadow_clone!(fsm_output, fsm_to_load, jff_reader);
+        move |ev: Event| {
+            let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            let file = gloo::file::File::from(file);
+            shadow_clone!(fsm_output, fsm_to_load);
+            let reader = gloo::file::callbacks::read_as_text(&file, move |text| {
+                let parsed = text
+                    .map_err(|why| why.to_string())
+                    .and_then(|text| StateMachine::from_jff(&text).map_err(|why| why.to_string()));
+                match parsed {
+                    Ok(new_fsm) => {
+                        fsm_to_load.set(Some(new_fsm));
+                        fsm_output.set(None);
+                    }
+                    Err(why) => {
+                        gloo::dialogs::alert(&format!("Cannot read JFLAP file: {why}"));
+                    }
+                }
+            });
+            *jff_reader.borrow_mut() = Some(reader);
+            input.set_value("");
+        }
+    };
+
+    let do_export_jff = {
+        shadow_clone!(fsm, jff_download);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            match fsm.to_jff() {
+                Ok(jff) => {
+                    let url = gloo::file::ObjectUrl::from(gloo::file::Blob::new_with_options(
+                        jff.as_str(),
+                        Some("application/xml"),
+                    ));
+                    let link = gloo::utils::document().create_element("a").unwrap();
+                    link.set_attribute("href", &url).unwrap();
+                    link.set_attribute("download", "machine.jff").unwrap();
+                    link.dyn_into::<web_sys::HtmlElement>().unwrap().click();
+                    *jff_download.borrow_mut() = Some(url);
+                }
+                Err(why) => {
+                    gloo::dialogs::alert(&format!("Cannot export to JFLAP: {why}"));
+                }
+            }
+        }
+    };
+
+    let regex_content = use_state(String::new);
+    let oninput_regex = {
+        shadow_clone!(regex_content);
+        move |ev: InputEvent| {
+            let target: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+            regex_content.set(target.value());
+        }
+    };
+
+    let do_build_regex = {
+        shadow_clone!(fsm_output, regex_content, fsm_to_load);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            match Regex::parse(&regex_content) {
+                Ok(regex) => {
+                    fsm_to_load.set(Some(regex.to_state_machine()));
+                    fsm_output.set(None);
+                }
+                Err(why) => {
+                    gloo::dialogs::alert(&format!("Error in regex: {why}"));
+                }
+            }
+        }
+    };
+
     if fsm_to_load.as_ref() == Some(&*fsm) {
         log::debug!("FSM successfully set, clearing fsm_to_load");
         fsm_to_load.set(None);
@@ -76,6 +369,19 @@ pub fn editor() -> Html {
         // log::debug!("FSM not propagated yet");
     }
 
+    let lints = fsm.lint();
+    let (node_highlights, link_highlights) = lint_highlights(&lints);
+    let lints_html = if lints.is_empty() {
+        html!()
+    } else {
+        html!(
+            <div class="alert alert-warning">
+                <p>{"Warnings:"}</p>
+                <ul class="mb-0">{lints.iter().map(|lint| html!(<li>{lint.to_string()}</li>)).collect::<Html>()}</ul>
+            </div>
+        )
+    };
+
     let fsm_output_html = match *fsm_output {
         None => html!(),
         Some(ref v) => match v {
@@ -94,6 +400,13 @@ pub fn editor() -> Html {
                         fsm::fsm::FSMError::InfiniteLoop => html!("FSM contains infinite loop"),
                         fsm::fsm::FSMError::NoEntryLinks => html!("FSM contains no entry links"),
                         fsm::fsm::FSMError::DisjointedLink(_) => html!("FSM contains disconnected links"),
+                        fsm::fsm::FSMError::InvalidLabel(link, why) => html!(format!("Link {link} has an invalid label: {why}")),
+                        fsm::fsm::FSMError::InvalidPushdownLabel(link, why) => html!(format!("Link {link} has an invalid pushdown label: {why}")),
+                        fsm::fsm::FSMError::InvalidTuringLabel(link, why) => html!(format!("Link {link} has an invalid Turing machine label: {why}")),
+                        fsm::fsm::FSMError::WildcardNotExpressible => html!("FSM needs a wildcard that leaves out some characters"),
+                        fsm::fsm::FSMError::TooManyStates(limit) => html!(format!("Deterministic FSM would have more than {limit} states")),
+                        fsm::fsm::FSMError::StackTooDeep(limit) => html!(format!("The stack grew deeper than {limit} symbols before the word was accepted")),
+                        fsm::fsm::FSMError::TooManyConfigurations(limit) => html!(format!("FSM got into more than {limit} configurations before the word was decided")),
                     }
                 }
                 </p>
@@ -101,6 +414,41 @@ pub fn editor() -> Html {
         },
     };
 
+    let transduce_output_html = match &*transduce_output {
+        None => html!(),
+        Some(Ok(transduction)) => html!(
+            <p class="fs-5">{"Transducer ("}{format!("{:?}", fsm.transducer_kind())}{"): "}{
+                match transduction {
+                    Transduction::Output(output) => html!(<code>{format!("{output:?}")}</code>),
+                    Transduction::Rejected => html!(<span class="text-danger">{"REJECT"}</span>),
+                    Transduction::Ambiguous(a, b) => html!(<span class="text-warning">{format!("AMBIGUOUS: {a:?} or {b:?}")}</span>),
+                }
+            }</p>
+        ),
+        Some(Err(err)) => html!(<p class="fs-5 text-warning">{"ERROR: "}{err.to_string()}</p>),
+    };
+
+    let turing_output_html = match &*turing_output {
+        None => html!(),
+        Some(Ok((output, steps, tape))) => html!(
+            <p class="fs-5">{"Turing machine: "}{
+                match output {
+                    fsm::fsm::FSMOutput::Accept => html!(<span class="text-success">{"ACCEPT"}</span>),
+                    fsm::fsm::FSMOutput::Reject => html!(<span class="text-danger">{"REJECT"}</span>),
+                }
+            }{format!(" after {steps} steps")}{
+                match tape {
+                    Some(tape) => html!(<>{", tape: "}<code>{format!("{tape:?}")}</code></>),
+                    None => html!(),
+                }
+            }</p>
+        ),
+        Some(Err(fsm::fsm::FSMError::InfiniteLoop)) => html!(
+            <p class="fs-5 text-warning">{format!("ERROR: the Turing machine did not halt within {DEFAULT_STEP_BUDGET} steps")}</p>
+        ),
+        Some(Err(err)) => html!(<p class="fs-5 text-warning">{"ERROR: "}{err.to_string()}</p>),
+    };
+
     html! {
         <>
             <div class="alert alert-warning attention">{"Эта страница используется для создания и отладки заданий и тестирующей системы, и не требуется для решения задач."}</div>
@@ -108,14 +456,62 @@ pub fn editor() -> Html {
             <p>{"You can edit, save, load and test state machines here."}</p>
             <Row>
                 <Column>
-                    <Canvas onchange={save_fsm} init={(&*fsm_to_load).clone()} />
+                    <Canvas onchange={save_fsm} init={(&*fsm_to_load).clone()} {node_highlights} {link_highlights} />
+                    if turing_output.is_some() {
+                        <CanvasPlayer word={(*word_to_check_state).clone()} fsm={(*fsm).clone()} turing={true}
+                            auto_play={true} pause_on_restart={true} show_steps_indicator={true} />
+                    }
                 </Column>
                 <Column>
                     <form class="input-group mb-3">
                         <input class="form-control" type="text" placeholder="Word to test" oninput={word_input} />
                         <button type="submit" class="btn btn-outline-primary" onclick={do_check_word}>{"Check word"}</button>
+                        <button type="button" class="btn btn-outline-primary" onclick={do_transduce}>{"Transduce"}</button>
+                        <button type="button" class="btn btn-outline-primary" onclick={do_run_turing}>{"Run Turing machine"}</button>
                     </form>
                     {fsm_output_html}
+                    {transduce_output_html}
+                    {turing_output_html}
+                    {lints_html}
+                    <div class="btn-group mb-3" role="group">
+                        <button type="button" class="btn btn-outline-primary" onclick={do_determinize}>{"Determinize"}</button>
+                        <button type="button" class="btn btn-outline-primary" onclick={do_minimize}>{"Minimize"}</button>
+                    </div>
+                    <div class="btn-group mb-3 ms-2" role="group">
+                        <button type="button" class="btn btn-outline-primary" onclick={do_star}>{"Star"}</button>
+                        <button type="button" class="btn btn-outline-primary" onclick={do_plus}>{"Plus"}</button>
+                        <button type="button" class="btn btn-outline-primary" onclick={do_reverse}>{"Reverse"}</button>
+                    </div>
+                    <div class="btn-group mb-3 ms-2" role="group">
+                        <button type="button" class="btn btn-outline-secondary" onclick={do_tidy_layered} title="Arrange the nodes in columns, following the links">{"Tidy up"}</button>
+                        <button type="button" class="btn btn-outline-secondary" onclick={do_tidy_force} title="Arrange the nodes by pulling linked nodes together">{"Tidy up (spring)"}</button>
+                    </div>
+                    {(*minimize_report).clone()}
+                    <form class="input-group mb-3">
+                        <input type="text" class="form-control" placeholder="Regular expression, like a(b|c)*" oninput={oninput_regex}/>
+                        <button type="submit" class="btn btn-outline-primary" onclick={do_build_regex}>{"Build from regex"}</button>
+                    </form>
+                    <form class="mb-3">
+                        <textarea class="form-control font-monospace mb-1" rows="6" placeholder="Graphviz DOT, like digraph { a -> b [label=\"x\"] }" value={(&*dot_content).clone()} oninput={oninput_dot}></textarea>
+                        <div class="btn-group" role="group">
+                            <button type="submit" class="btn btn-outline-primary" onclick={do_load_dot}>{"Load DOT"}</button>
+                            <button type="button" class="btn btn-outline-secondary" onclick={do_export_dot}>{"Export DOT"}</button>
+                            <button type="button" class="btn btn-outline-secondary" onclick={do_export_tikz}>{"Export TikZ"}</button>
+                        </div>
+                    </form>
+                    {tikz_html}
+                    <form class="mb-3">
+                        <textarea class="form-control font-monospace mb-1" rows="6" placeholder={"states: q0, q1\nstart: q0\naccept: q1\ntransitions:\n    q0 -a-> q1"} value={(&*formal_content).clone()} oninput={oninput_formal}></textarea>
+                        <div class="btn-group" role="group">
+                            <button type="submit" class="btn btn-outline-primary" onclick={do_load_formal}>{"Load 5-tuple"}</button>
+                            <button type="button" class="btn btn-outline-secondary" onclick={do_export_formal}>{"Export 5-tuple"}</button>
+                        </div>
+                    </form>
+                    <div class="input-group mb-3">
+                        <label class="input-group-text" for="jff-file">{"JFLAP file"}</label>
+                        <input class="form-control" type="file" id="jff-file" accept=".jff,.xml" onchange={on_jff_file} />
+                        <button type="button" class="btn btn-outline-secondary" onclick={do_export_jff}>{"Export .jff"}</button>
+                    </div>
                     <form class="input-group mb-3">
                         <input type="text" class="form-control" value={(&*fsm_json_content).clone()} oninput={oninput_json}/>
                         <button type="submit" class="btn btn-outline-danger" onclick={do_load_json}>{"Load"}</button>
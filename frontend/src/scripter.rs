@@ -44,6 +44,26 @@ fn check_word(word) {
     }
     return true;
 }
+
+// Optionally, for tasks where the FSM is a transducer:
+// return what the FSM should write for a word that check_word accepts.
+// Mealy machines write the outputs of their links (labelled "input/output"),
+// and Moore machines write the text of every node they enter.
+// fn transduce(word) {
+//     return word;
+// }
+
+// Optionally, for tasks where the FSM is a pushdown automaton with links labelled "input, pop → push":
+// return "final state" if it should accept by ending in an accepting node, or "empty stack" if by emptying its stack.
+// fn pushdown_acceptance() {
+//     return "final state";
+// }
+
+// Optionally, for tasks where the FSM is a Turing machine with links labelled "read → write, L/R/S":
+// return how many steps it may take on a word. Accepting nodes accept, and nodes named "reject" reject.
+// fn turing_machine() {
+//     return 10000;
+// }
 "#;
 
 #[function_component(Scripter)]
@@ -108,7 +128,14 @@ pub fn scripter() -> Html {
             let mut my_tester: FSMTester = tester.as_ref().unwrap().semiclone();
             let word = (&*word_input_state).clone();
             let res = my_tester.check_word(word.clone());
-            output.set(format!("Question: check_word {word:?}\nAnswer: {res:?}"));
+            if my_tester.is_transducer() {
+                let transduction = my_tester.transduce_word(word.clone());
+                output.set(format!(
+                    "Question: check_word {word:?}\nAnswer: {res:?}\nTransduction: {transduction:?}"
+                ));
+            } else {
+                output.set(format!("Question: check_word {word:?}\nAnswer: {res:?}"));
+            }
         }
     };
 
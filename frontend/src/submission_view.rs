@@ -1,6 +1,11 @@
-use crate::{canvas_player::CanvasPlayer, user_page::TaskLink, Route};
-use api::OthersSubmissionInfo;
-use fsm::fsm::StateMachine;
+use crate::{
+    canvas_player::CanvasPlayer,
+    task::{ExplanationDisplay, TransductionDisplay},
+    user_page::TaskLink,
+    Route,
+};
+use api::{OthersSubmissionInfo, TaskInfo};
+use fsm::{fsm::StateMachine, tester::FSMTester};
 use gloo::storage::Storage;
 use shadow_clone::shadow_clone;
 use wasm_bindgen::JsCast;
@@ -68,7 +73,35 @@ pub fn view_submission_inner(props: &ViewSubmissionProps) -> HtmlResult {
                         </div>)
                     }
                     api::OthersSubmissionDetails::Ok(fsm) => {
-                        html!(<ViewFSM state_machine={fsm.clone()} />)
+                        let explanation_line = match &info.verdict {
+                            api::SubmissionVerdict::WrongAnswer {
+                                first_failure_explanation: Some(explanation),
+                                ..
+                            } => html!(
+                                <p>{"На первом неудачном тесте: "}<ExplanationDisplay explanation={explanation.clone()} fsm={fsm.clone()} /></p>
+                            ),
+                            _ => html!(),
+                        };
+                        let transduction_line = match &info.verdict {
+                            api::SubmissionVerdict::WrongAnswer {
+                                first_failure_transductions: Some((expected, actual)),
+                                ..
+                            } => html!(
+                                <p>{"Ожидался результат "}<TransductionDisplay transduction={expected.clone()} />{", а автомат выдал "}<TransductionDisplay transduction={actual.clone()} /></p>
+                            ),
+                            _ => html!(),
+                        };
+                        let regex_line = if info.verdict.is_ok() {
+                            html!(<RegexLine task_id={info.task_id} fsm={fsm.clone()} />)
+                        } else {
+                            html!()
+                        };
+                        html!(<>
+                            {explanation_line}
+                            {transduction_line}
+                            {regex_line}
+                            <ViewFSM state_machine={fsm.clone()} />
+                        </>)
                     }
                 };
 
@@ -93,10 +126,42 @@ pub fn view_submission_inner(props: &ViewSubmissionProps) -> HtmlResult {
                         fsm::fsm::FSMError::DisjointedLink(_) => {
                             html!(<span class="text-danger">{"Автомат нельзя тестировать, потому что есть несвязность между кружочками и стрелочками"}</span>)
                         }
+                        fsm::fsm::FSMError::InvalidLabel(_, why) => {
+                            html!(<span class="text-danger">{"Автомат нельзя тестировать, потому что текст на одной из стрелочек непонятен: "}{why.to_string()}</span>)
+                        }
+                        fsm::fsm::FSMError::InvalidPushdownLabel(_, why) => {
+                            html!(<span class="text-danger">{"Автомат нельзя тестировать, потому что текст на одной из стрелочек магазинного автомата непонятен: "}{why.to_string()}</span>)
+                        }
+                        fsm::fsm::FSMError::InvalidTuringLabel(_, why) => {
+                            html!(<span class="text-danger">{"Автомат нельзя тестировать, потому что текст на одной из стрелочек машины Тьюринга непонятен: "}{why.to_string()}</span>)
+                        }
+                        fsm::fsm::FSMError::WildcardNotExpressible => {
+                            html!(<span class="text-danger">{"Автомат нельзя тестировать, потому что точку на одной из стрелочек нельзя записать без исключений"}</span>)
+                        }
+                        fsm::fsm::FSMError::TooManyStates(limit) => {
+                            html!(<span class="text-danger">{"Автомат нельзя тестировать, потому что детерминированный автомат получился бы больше "}{limit}{" состояний"}</span>)
+                        }
+                        fsm::fsm::FSMError::StackTooDeep(limit) => {
+                            html!(<span class="text-danger">{"Автомат нельзя тестировать, потому что на одном из тестов магазин стал глубже "}{limit}{" символов"}</span>)
+                        }
+                        fsm::fsm::FSMError::TooManyConfigurations(limit) => {
+                            html!(<span class="text-danger">{"Автомат нельзя тестировать, потому что на одном из тестов он побывал больше чем в "}{limit}{" конфигурациях"}</span>)
+                        }
                     },
                     api::SubmissionVerdict::TaskInternalError(why) => {
                         html!(<span class="text-danger">{"Внутренняя ошибка задания: "}{why}</span>)
                     }
+                    api::SubmissionVerdict::Equivalent => {
+                        html!(<span class="text-success">{"Автомат эквивалентен эталонному решению"}</span>)
+                    }
+                    api::SubmissionVerdict::Counterexample {
+                        word,
+                        expected_count,
+                        actual_count,
+                        ..
+                    } => {
+                        html!(<span class="text-warning">{"НЕВЕРНО: автомат ошибается на слове "}{format!("{word:?}")}{"; слов этой длины автомат принимает "}{actual_count}{", а эталонное решение "}{expected_count}</span>)
+                    }
                 };
 
                 html!(<>
@@ -116,6 +181,49 @@ pub fn view_submission_inner(props: &ViewSubmissionProps) -> HtmlResult {
     })
 }
 
+#[derive(PartialEq, Clone, Properties)]
+struct RegexLineProps {
+    task_id: i64,
+    fsm: StateMachine,
+}
+
+#[function_component(RegexLine)]
+fn regex_line(props: &RegexLineProps) -> Html {
+    html!(
+        <Suspense fallback={html!()}>
+            <RegexLineInner task_id={props.task_id} fsm={props.fsm.clone()} />
+        </Suspense>
+    )
+}
+
+/// The regular expression of an accepted submission, shown only if the task asks for a plain acceptor:
+/// the labels of transducers, pushdown automata and Turing machines do not mean the same thing.
+#[function_component(RegexLineInner)]
+fn regex_line_inner(props: &RegexLineProps) -> HtmlResult {
+    let resp = use_future_with(props.task_id, |task_id| async move {
+        reqwest::get(format!("https://fsm-api.rudn-lab.ru/task-by-id/{task_id}",))
+            .await?
+            .error_for_status()?
+            .json::<TaskInfo>()
+            .await
+    })?;
+    let script = resp.as_ref().ok().map(|info| info.script.clone());
+    let regex = use_memo((props.fsm.clone(), script), |(fsm, script)| {
+        let script = script.as_ref()?;
+        if FSMTester::script_is_transducer(script)
+            || FSMTester::script_pushdown_acceptance(script).is_some()
+            || FSMTester::script_turing_budget(script).is_some()
+        {
+            return None;
+        }
+        fsm.to_regex().ok().map(|regex| regex.to_string())
+    });
+    Ok(match &*regex {
+        Some(regex) => html!(<p>{"Регулярное выражение: "}<code>{regex}</code></p>),
+        None => html!(),
+    })
+}
+
 #[autoprops_component(ViewFSM)]
 fn view_fsm(state_machine: &StateMachine) -> Html {
     let current_fsm: UseStateHandle<StateMachine> = use_state_eq(Default::default);
@@ -1,13 +1,18 @@
 use api::{SubmissionVerdict, TaskInfo, UserTaskSubmission, UserTaskSubmissions};
 use fsm::{
+    diff::Change,
+    explain::Explanation,
     fsm::{FSMOutput, StateMachine},
+    lint::Lint,
+    pushdown::PushdownAcceptance,
     tester::FSMTester,
+    transducer::Transduction,
 };
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use shadow_clone::shadow_clone;
 use wasm_bindgen::JsCast;
-use web_sys::HtmlInputElement;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
 use yew::{prelude::*, suspense::use_future};
 use yew_autoprops::autoprops_component;
 use yew_bootstrap::{
@@ -40,6 +45,88 @@ pub fn task_page(props: &TaskPageProps) -> Html {
     )
 }
 
+/// List what changed in the machine since the last submission.
+#[autoprops_component(ChangeList)]
+pub fn change_list(previous: &Option<StateMachine>, current: &StateMachine) -> Html {
+    let Some(previous) = previous else {
+        return html!();
+    };
+    let diff = previous.diff(current);
+    if diff.is_empty() {
+        return html!(<p class="text-muted">{"С последней отправки автомат не изменился."}</p>);
+    }
+
+    let node_name =
+        |fsm: &StateMachine, node: usize| match fsm.nodes.get(node).map(|n| n.text.as_str()) {
+            Some("") | None => format!("№{node}"),
+            Some(text) => text.to_string(),
+        };
+    let link_text =
+        |fsm: &StateMachine, link: usize| match fsm.links.get(link).map(|l| l.get_text()) {
+            Some("") | None => String::from("ε"),
+            Some(text) => format!("«{text}»"),
+        };
+    let link_place =
+        |fsm: &StateMachine, link: usize| match fsm.links.get(link).map(|l| l.get_nodes()) {
+            Some((Some(a), b)) if a == b => format!("у кружочка {}", node_name(fsm, b)),
+            Some((Some(a), b)) => format!("из {} в {}", node_name(fsm, a), node_name(fsm, b)),
+            Some((None, b)) => format!("входная, в {}", node_name(fsm, b)),
+            None => String::new(),
+        };
+    let items = diff
+        .changes
+        .iter()
+        .map(|change| {
+            let text = match change {
+                Change::AddedNode(node) => {
+                    format!("Добавлен кружочек {}", node_name(current, *node))
+                }
+                Change::RemovedNode(node) => {
+                    format!("Удален кружочек {}", node_name(previous, *node))
+                }
+                Change::RelabeledNode { old, new } => format!(
+                    "Кружочек {} переименован в {}",
+                    node_name(previous, *old),
+                    node_name(current, *new)
+                ),
+                Change::AcceptStateChanged { new, .. } => {
+                    if current.nodes[*new].accept_state {
+                        format!("Кружочек {} стал принимающим", node_name(current, *new))
+                    } else {
+                        format!(
+                            "Кружочек {} больше не принимающий",
+                            node_name(current, *new)
+                        )
+                    }
+                }
+                Change::AddedLink(link) => format!(
+                    "Добавлена стрелочка {} ({})",
+                    link_text(current, *link),
+                    link_place(current, *link)
+                ),
+                Change::RemovedLink(link) => format!(
+                    "Удалена стрелочка {} ({})",
+                    link_text(previous, *link),
+                    link_place(previous, *link)
+                ),
+                Change::RelabeledLink { old, new } => format!(
+                    "Текст стрелочки {} изменен: {} вместо {}",
+                    link_place(current, *new),
+                    link_text(current, *new),
+                    link_text(previous, *old)
+                ),
+            };
+            html!(<li>{text}</li>)
+        })
+        .collect::<Html>();
+    html!(
+        <div class="my-2">
+            <p class="mb-1">{"Что изменилось с последней отправки:"}</p>
+            <ul>{items}</ul>
+        </div>
+    )
+}
+
 #[wasm_bindgen::prelude::wasm_bindgen]
 extern "C" {
     pub fn randfloat() -> f64;
@@ -112,6 +199,75 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
 
     let examples = use_state(|| html!());
 
+    let show_tikz = use_state(|| false);
+    let toggle_tikz = {
+        shadow_clone!(show_tikz);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            show_tikz.set(!*show_tikz);
+        }
+    };
+    let tikz_html = if *show_tikz {
+        html!(
+            <div class="my-2">
+                <p class="mb-1">{"Код TikZ для отчета (нужен пакет "}<code>{"tikz"}</code>{"):"}</p>
+                <textarea class="form-control font-monospace" rows="6" readonly={true} value={current_fsm.to_tikz()}></textarea>
+            </div>
+        )
+    } else {
+        html!()
+    };
+
+    let formal_content = use_state(|| None);
+    let formal_error = use_state(|| None);
+    let toggle_formal = {
+        shadow_clone!(current_fsm, formal_content, formal_error);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            formal_error.set(None);
+            match *formal_content {
+                Some(_) => formal_content.set(None),
+                None => formal_content.set(Some(current_fsm.to_formal())),
+            }
+        }
+    };
+    let oninput_formal = {
+        shadow_clone!(formal_content);
+        move |ev: InputEvent| {
+            let target: HtmlTextAreaElement = ev.target().unwrap().dyn_into().unwrap();
+            formal_content.set(Some(target.value()));
+        }
+    };
+    let load_formal = {
+        shadow_clone!(formal_content, formal_error, fsm_to_load);
+        move |ev: MouseEvent| {
+            ev.prevent_default();
+            let Some(text) = &*formal_content else {
+                return;
+            };
+            match StateMachine::from_formal(text) {
+                Ok(fsm) => {
+                    fsm_to_load.set(Some(fsm));
+                    formal_error.set(None);
+                }
+                Err(why) => formal_error.set(Some(why.to_string())),
+            }
+        }
+    };
+    let formal_html = match &*formal_content {
+        Some(text) => html!(
+            <form class="my-2">
+                <label class="form-label mb-1" for="formal-text">{"Автомат текстом: состояния, алфавит, начальное и допускающие состояния и переходы вида "}<code>{"q0 -a-> q1"}</code></label>
+                <textarea class="form-control font-monospace mb-1" id="formal-text" rows="8" value={text.clone()} oninput={oninput_formal}></textarea>
+                if let Some(why) = &*formal_error {
+                    <div class="text-danger mb-1" role="alert">{"Ошибка: "}{why}</div>
+                }
+                <button type="submit" class="btn btn-outline-primary" onclick={load_formal}>{"Загрузить"}</button>
+            </form>
+        ),
+        None => html!(),
+    };
+
     if fsm_to_load.as_ref() == Some(&*current_fsm) {
         log::debug!("FSM successfully set, clearing fsm_to_set");
         fsm_to_load.set(None);
@@ -164,6 +320,21 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                     let fsm = (&*current_fsm).clone();
                     fsm_to_load.set(Some(fsm.clone()));
 
+                    // The shortest words the student's own machine accepts, if it can be run at all.
+                    // A pushdown automaton's words depend on its stack, and a Turing machine's on its tape,
+                    // so they cannot be listed this way.
+                    let own_words: Option<Vec<String>> =
+                        if FSMTester::script_pushdown_acceptance(&script).is_some()
+                            || FSMTester::script_turing_budget(&script).is_some()
+                        {
+                            None
+                        } else {
+                            fsm.input_machine()
+                                .accepted_words(12, 10)
+                                .ok()
+                                .map(|words| words.collect())
+                        };
+
                     log::debug!("Instantiating tester");
                     let tester = FSMTester::new(fsm, &script);
                     let mut tester = match tester {
@@ -209,10 +380,27 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                         }
                     }
                     log::debug!("Generated tests: ACC={tests_acc:?}, REJ={tests_rej:?}");
+                    // For transducer tasks, show what should be written for the accepted words.
+                    let mut outputs = Vec::with_capacity(tests_acc.len());
+                    for word in tests_acc.iter() {
+                        if !tester.is_transducer() {
+                            outputs.push(html!());
+                            continue;
+                        }
+                        match tester.transduce_word(word.clone()) {
+                            Ok(transduction) => outputs
+                                .push(html!(<>{" → "}<TransductionDisplay {transduction} /></>)),
+                            Err(why) => {
+                                local_test_outcome.set(html!(<span class="text-danger">{"ОШИБКА ЗАДАНИЯ (пожалуйста сообщите об этом!): "}{why}</span>));
+                                return;
+                            }
+                        }
+                    }
                     let rows = tests_acc
                         .iter()
                         .zip(tests_rej.iter())
-                        .map(|(a, b)| {
+                        .zip(outputs)
+                        .map(|((a, b), output)| {
                             let init_word_out = init_word.clone();
                             let out_a = a.clone();
                             let load_a = move |ev: MouseEvent| {
@@ -229,6 +417,7 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                                 <tr>
                                     <td>
                                         <WordDisplay word={a.clone()} response={FSMOutput::Accept} />
+                                        {output}
                                         <button class="btn btn-outline-primary btn-sm" onclick={load_a} >{BI::ARROW_UP_LEFT_SQUARE}</button>
                                     </td>
                                     <td>
@@ -240,7 +429,41 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                         })
                         .collect::<Html>();
 
+                    let own_words = match own_words {
+                        None => html!(),
+                        Some(words) if words.is_empty() => html!(
+                            <p>{"Ваш автомат не принимает ни одного слова длиной до 12 символов"}</p>
+                        ),
+                        Some(words) => {
+                            let words = words
+                                .into_iter()
+                                .map(|word| {
+                                    let init_word_out = init_word.clone();
+                                    let out = word.clone();
+                                    let load = move |ev: MouseEvent| {
+                                        ev.prevent_default();
+                                        init_word_out.set(Some(out.clone()));
+                                    };
+                                    html! {
+                                        <li class="list-inline-item">
+                                            <WordDisplay word={word} response={FSMOutput::Accept} />
+                                            <button class="btn btn-outline-primary btn-sm ms-1" onclick={load} >{BI::ARROW_UP_LEFT_SQUARE}</button>
+                                        </li>
+                                    }
+                                })
+                                .collect::<Html>();
+                            html!(
+                                <>
+                                    <p>{"Самые короткие слова, которые принимает ваш автомат:"}</p>
+                                    <ul class="list-inline">{words}</ul>
+                                </>
+                            )
+                        }
+                    };
+
                     examples.set(html!(
+                        <>
+                        {own_words}
                         <table class="table overflow-scroll">
                             <thead>
                                 <tr>
@@ -252,6 +475,7 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                                 {rows}
                             </tbody>
                         </table>
+                        </>
                     ))
                 }
             };
@@ -301,6 +525,8 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                                     total_tests,
                                     first_failure_seed,
                                     first_failure_expected_result,
+                                    first_failure_explanation,
+                                    first_failure_transductions,
                                 } => {
                                     local_test_outcome.set(html!(<span class="text-warning">{"НЕВЕРНО: только "}{successes}{"/"}{total_tests}{" тестов прошло"}</span>));
                                     let word_to_test = match tester.make_test_case(first_failure_seed, first_failure_expected_result.into()) {
@@ -310,9 +536,18 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                                             return;
                                         }
                                     };
-                                    let response = match word_to_test.1 {
-                                        FSMOutput::Accept => " (слово следует принимать, а ваш автомат отвергает)",
-                                        FSMOutput::Reject => " (слово следует отвергать, а ваш автомат принимает)",
+                                    let response = match (&first_failure_transductions, word_to_test.1) {
+                                        (Some(_), _) => " (ваш автомат выдает не тот результат)",
+                                        (None, FSMOutput::Accept) => " (слово следует принимать, а ваш автомат отвергает)",
+                                        (None, FSMOutput::Reject) => " (слово следует отвергать, а ваш автомат принимает)",
+                                    };
+                                    let transductions = match first_failure_transductions {
+                                        Some((expected, actual)) => html!(<>
+                                            <br/>
+                                            {"Ожидается: "}<TransductionDisplay transduction={expected} />
+                                            {", ваш автомат: "}<TransductionDisplay transduction={actual} />
+                                        </>),
+                                        None => html!(),
                                     };
                                     let failed_word = word_to_test.0.clone();
                                     let load_failed = {
@@ -327,10 +562,34 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                                         <p>{"Ваше решение не работает для слова: "}<WordDisplay word={word_to_test.0} response={word_to_test.1} />
                                         <button class="btn btn-sm btn-outline-primary mx-2" onclick={load_failed}>{BI::ARROW_UP_LEFT_SQUARE}</button>
                                         {response}
+                                        {transductions}
+                                        <br/>
+                                        if let Some(explanation) = first_failure_explanation {
+                                            <ExplanationDisplay {explanation} fsm={tester.fsm.clone()} />
+                                        }
                                         </p>
                                         ));
                             },
                                 fsm::tester::FSMTestingOutput::FSMInvalid(why) => local_test_outcome.set(html!(<span class="text-warning">{"НЕВЕРНЫЙ ФОРМАТ: "}{why}</span>)),
+                                fsm::tester::FSMTestingOutput::Equivalent => local_test_outcome.set(html!(<span class="text-success">{"OK: автомат эквивалентен эталонному решению"}</span>)),
+                                fsm::tester::FSMTestingOutput::Counterexample { word, expected_result, expected_count, actual_count } => {
+                                    local_test_outcome.set(html!(<span class="text-warning">{"НЕВЕРНО: автомат не эквивалентен эталонному решению"}</span>));
+                                    let load_failed = {
+                                        shadow_clone!(init_word, word);
+                                        move |ev: MouseEvent| {
+                                            ev.prevent_default();
+                                            init_word.set(Some(word.clone()))
+                                        }
+                                    };
+                                    examples.set(
+                                        html!(
+                                        <p>{"Ваше решение не работает для слова: "}<WordDisplay word={word.clone()} response={expected_result} />
+                                        <button class="btn btn-sm btn-outline-primary mx-2" onclick={load_failed}>{BI::ARROW_UP_LEFT_SQUARE}</button>
+                                        <br/>
+                                        {"Слов длины "}{word.chars().count()}{": ваш автомат принимает "}{actual_count}{", эталонное решение принимает "}{expected_count}
+                                        </p>
+                                        ));
+                                },
                             }
                         }
                     }
@@ -344,12 +603,19 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                         <div class="btn-group" role="group">
                             <button type="button" class="btn btn-outline-primary" onclick={run_local_test}>{"Тестировать локально"}</button>
                             {send_to_server_button}
+                            <button type="button" class="btn btn-outline-secondary" onclick={toggle_tikz}>{"LaTeX (TikZ)"}</button>
+                            <button type="button" class="btn btn-outline-secondary" onclick={toggle_formal}>{"Текстом"}</button>
                         </div>
+                        {tikz_html}
+                        {formal_html}
 
                         </div>
                         <Row>
                             <Column>
                                 <TestableFSM onchange={set_fsm}
+                                transducer={FSMTester::script_is_transducer(&task.script)}
+                                pushdown={FSMTester::script_pushdown_acceptance(&task.script)}
+                                turing={FSMTester::script_turing_budget(&task.script).is_some()}
                                 init={(&*fsm_to_load).clone()}
                                 init_word={(&*init_word).clone()}/>
                             </Column>
@@ -376,6 +642,7 @@ fn task_page_inner(props: &TaskPageProps) -> HtmlResult {
                             <div>
                                 {(&*local_test_outcome).clone()}
                             </div>
+                            <ChangeList previous={submissions.latest_submission.as_ref().map(|v| v.solution.clone())} current={(&*current_fsm).clone()} />
                             <SubmissionList {submissions} {onselect} />
                         </div>
                 </>
@@ -394,6 +661,9 @@ fn TestableFSM(
     onchange: Callback<StateMachine>,
     init: Option<StateMachine>,
     init_word: Option<AttrValue>,
+    transducer: bool,
+    pushdown: Option<PushdownAcceptance>,
+    turing: bool,
 ) -> Html {
     let word = use_state_eq(|| String::from(""));
     let is_running = use_state_eq(|| false);
@@ -462,7 +732,14 @@ fn TestableFSM(
             pause_on_restart={true} {on_fsm_apply}
             speed={860} auto_play={false} show_steps_indicator={true}
             play_pulse={*play_pulse} {on_validity}
-            {on_is_running}/>
+            {on_is_running} show_lints={!*is_running && !turing} {transducer} {pushdown} {turing}/>
+            if !turing {
+                <LintList fsm={match (transducer, pushdown) {
+                    (true, _) => fsm.input_machine(),
+                    (false, Some(_)) => fsm.pushdown_input_machine(),
+                    (false, None) => (&*fsm).clone(),
+                }} />
+            }
             <form class="input-group my-2" style="width: 800px; margin: 0 auto;">
                 <span class="input-group-text">{"Введите слово для проверки: "}</span>
                 <input class="form-control" disabled={*is_running || !*validity} value={(&*word).clone()} {oninput} />
@@ -492,6 +769,127 @@ fn word_display(word: &AttrValue, response: &FSMOutput) -> Html {
     }
 }
 
+/// Show what a transducer writes for a word.
+#[autoprops_component(TransductionDisplay)]
+pub fn transduction_display(transduction: &Transduction) -> Html {
+    match transduction {
+        Transduction::Output(output) if output.is_empty() => {
+            html!(<span class="badge text-bg-secondary">{"ε"}</span>)
+        }
+        Transduction::Output(output) => html!(<code>{output}</code>),
+        Transduction::Rejected => html!(<span class="text-danger">{"слово отвергается"}</span>),
+        Transduction::Ambiguous(a, b) => html!(
+            <span class="text-warning">{"неоднозначно: "}<code>{a}</code>{" или "}<code>{b}</code></span>
+        ),
+    }
+}
+
+/// Explain in words why the machine accepted or rejected a word.
+#[autoprops_component(ExplanationDisplay)]
+pub fn explanation_display(explanation: &Explanation, fsm: &StateMachine) -> Html {
+    let node_name = |node: usize| match fsm.nodes.get(node).map(|n| n.text.as_str()) {
+        Some("") | None => format!("№{node}"),
+        Some(text) => text.to_string(),
+    };
+    let quoted = |text: &str| {
+        if text.is_empty() {
+            String::from("ε")
+        } else {
+            format!("«{text}»")
+        }
+    };
+    match explanation {
+        Explanation::Accepted { path } => {
+            let steps: Vec<String> = path
+                .iter()
+                .map(|(link, text)| {
+                    let target = fsm.links.get(*link).map_or(0, |l| l.get_nodes().1);
+                    format!("{} → {}", quoted(text), node_name(target))
+                })
+                .collect();
+            html!(<span>{"Автомат принимает слово по пути: "}{steps.join(", ")}</span>)
+        }
+        Explanation::Rejected {
+            longest_prefix,
+            read_whole_word,
+            stuck_nodes,
+        } => {
+            let nodes: Vec<String> = stuck_nodes.iter().map(|n| node_name(*n)).collect();
+            if stuck_nodes.is_empty() {
+                html!(<span>{"Ни одна входная стрелочка не подходит к началу слова"}</span>)
+            } else if *read_whole_word {
+                html!(<span>{"Слово прочитано полностью, но автомат оказался только в непринимающих кружочках: "}{nodes.join(", ")}</span>)
+            } else {
+                html!(<span>{"Автомат смог прочитать только "}{quoted(longest_prefix)}{" и застрял в кружочках: "}{nodes.join(", ")}</span>)
+            }
+        }
+    }
+}
+
+/// List the likely mistakes in the machine, which the canvas highlights in orange.
+#[autoprops_component(LintList)]
+pub fn lint_list(fsm: &StateMachine) -> Html {
+    let lints = fsm.lint();
+    if lints.is_empty() {
+        return html!();
+    }
+    let node_name = |node: &usize| match fsm.nodes.get(*node).map(|n| n.text.as_str()) {
+        Some("") | None => format!("№{node}"),
+        Some(text) => text.to_string(),
+    };
+    let link_names = |links: &Vec<usize>| {
+        let names: Vec<String> = links
+            .iter()
+            .map(|link| match fsm.links.get(*link).map(|l| l.get_text()) {
+                Some("") | None => String::from("ε"),
+                Some(text) => format!("«{text}»"),
+            })
+            .collect();
+        names.join(", ")
+    };
+    let from = |node: &Option<usize>| match node {
+        Some(node) => format!("из кружочка {}", node_name(node)),
+        None => String::from("у входных стрелочек"),
+    };
+    let items = lints
+        .iter()
+        .map(|lint| {
+            let text = match lint {
+                Lint::UnreachableNode(node) => {
+                    format!("В кружочек {} нельзя попасть", node_name(node))
+                }
+                Lint::DeadNode(node) => format!(
+                    "Из кружочка {} нельзя попасть в принимающий кружочек",
+                    node_name(node)
+                ),
+                Lint::UnreachableAcceptState(node) => {
+                    format!("В принимающий кружочек {} нельзя попасть", node_name(node))
+                }
+                Lint::Nondeterminism { node, links } => format!(
+                    "Стрелочки {} {} могут подойти к одному и тому же слову",
+                    link_names(links),
+                    from(node)
+                ),
+                Lint::SameLabel { node, links } => format!(
+                    "Стрелочки {} {} с одинаковым текстом ведут в разные кружочки",
+                    link_names(links),
+                    from(node)
+                ),
+                Lint::DuplicateLinks(links) => {
+                    format!("Стрелочки {} повторяют друг друга", link_names(links))
+                }
+            };
+            html!(<li>{text}</li>)
+        })
+        .collect::<Html>();
+    html!(
+        <div class="alert alert-warning" style="width: 800px; margin: 0 auto;">
+            <p>{"Возможные ошибки в автомате:"}</p>
+            <ul class="mb-0">{items}</ul>
+        </div>
+    )
+}
+
 #[wasm_bindgen::prelude::wasm_bindgen]
 extern "C" {
     pub fn unix_time_to_locale_string(time: f64) -> String;
@@ -524,6 +922,23 @@ pub fn verdict_display(verdict: &SubmissionVerdict) -> Html {
                 fsm::fsm::FSMError::DisjointedLink(_) => {
                     "Есть стрелочка, которая связана с несуществующим кружочком"
                 }
+                fsm::fsm::FSMError::InvalidLabel(..) => "Есть стрелочка с непонятным текстом",
+                fsm::fsm::FSMError::InvalidPushdownLabel(..) => {
+                    "Есть стрелочка с непонятным текстом для магазинного автомата"
+                }
+                fsm::fsm::FSMError::InvalidTuringLabel(..) => {
+                    "Есть стрелочка с непонятным текстом для машины Тьюринга"
+                }
+                fsm::fsm::FSMError::WildcardNotExpressible => {
+                    "Точку на стрелочке нельзя записать без исключений"
+                }
+                fsm::fsm::FSMError::TooManyStates(..) => {
+                    "Детерминированный автомат получился бы слишком большим"
+                }
+                fsm::fsm::FSMError::StackTooDeep(..) => "Магазин автомата стал слишком глубоким",
+                fsm::fsm::FSMError::TooManyConfigurations(..) => {
+                    "Автомат побывал в слишком многих конфигурациях"
+                }
             };
             html!(
                 <span class="d-inline-block text-danger fs-2" tabindex="0" data-bs-toggle="popover" data-bs-trigger="hover focus" data-bs-content={format!("НЕВЕРНЫЙ ФОРМАТ: {why}")}>
@@ -536,6 +951,21 @@ pub fn verdict_display(verdict: &SubmissionVerdict) -> Html {
                 {BI::BUG_FILL}
             </span>
         ),
+        api::SubmissionVerdict::Equivalent => html!(
+            <span class="d-inline-block text-success fs-2" tabindex="0" data-bs-toggle="popover" data-bs-trigger="hover focus" data-bs-content="OK: автомат эквивалентен эталонному решению">
+                {BI::PATCH_CHECK_FILL}
+            </span>
+        ),
+        api::SubmissionVerdict::Counterexample {
+            word,
+            expected_count,
+            actual_count,
+            ..
+        } => html!(
+            <span class="d-inline-block text-warning fs-2" tabindex="0" data-bs-toggle="popover" data-bs-trigger="hover focus" data-bs-content={format!("НЕВЕРНО: автомат ошибается на слове {word:?}; слов этой длины автомат принимает {actual_count}, а эталонное решение {expected_count}")}>
+                {BI::EXCLAMATION_TRIANGLE_FILL}
+            </span>
+        ),
     }
 }
 
@@ -209,7 +209,7 @@ fn EmailValidatorFSM() -> Html {
     let is_running = use_state(|| true);
     let fsm: StateMachine = serde_json::from_str(
         r#"
-{"nodes":[{"x":74,"y":91,"text":"","isAcceptState":false},{"x":208,"y":91,"text":"user","isAcceptState":false},{"x":416,"y":91,"text":"","isAcceptState":false},{"x":539,"y":91,"text":"site","isAcceptState":false},{"x":135,"y":248,"text":"","isAcceptState":false},{"x":539,"y":248,"text":"dot","isAcceptState":false},{"x":135,"y":407,"text":"","isAcceptState":false},{"x":244,"y":314,"text":"","isAcceptState":true},{"x":305,"y":407,"text":"","isAcceptState":false},{"x":230,"y":491,"text":"","isAcceptState":true},{"x":87,"y":524,"text":"","isAcceptState":true},{"x":446,"y":331,"text":"","isAcceptState":true},{"x":435,"y":491,"text":"","isAcceptState":true}],"links":[{"type":"StartLink","node":0,"text":"","deltaX":-59,"deltaY":0},{"type":"Link","nodeA":0,"nodeB":1,"text":"a","lineAngleAdjust":3.141592653589793,"parallelPart":0.7647058823529411,"perpendicularPart":-56.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"b","lineAngleAdjust":3.141592653589793,"parallelPart":0.5588235294117647,"perpendicularPart":-37.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"c","lineAngleAdjust":3.141592653589793,"parallelPart":0.5588235294117647,"perpendicularPart":-14.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"d","lineAngleAdjust":0.0,"parallelPart":0.5147058823529411,"perpendicularPart":19.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"e","lineAngleAdjust":0.0,"parallelPart":0.6764705882352942,"perpendicularPart":40.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"f","lineAngleAdjust":0.0,"parallelPart":0.6617647058823529,"perpendicularPart":67.0},{"type":"SelfLink","node":1,"text":"a","anchorAngle":1.740163983875466},{"type":"SelfLink","node":1,"text":"b","anchorAngle":1.3352513460740334},{"type":"SelfLink","node":1,"text":"c","anchorAngle":1.0360703319417248},{"type":"SelfLink","node":1,"text":"d","anchorAngle":-1.7539071440573808},{"type":"SelfLink","node":1,"text":"e","anchorAngle":-1.1839206090638683},{"type":"SelfLink","node":1,"text":"f","anchorAngle":-0.65788860518221},{"type":"Link","nodeA":1,"nodeB":2,"text":"@","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"a","lineAngleAdjust":3.141592653589793,"parallelPart":0.6754385964912281,"perpendicularPart":-58.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"b","lineAngleAdjust":3.141592653589793,"parallelPart":0.5877192982456141,"perpendicularPart":-34.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"c","lineAngleAdjust":3.141592653589793,"parallelPart":0.49122807017543857,"perpendicularPart":-14.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"d","lineAngleAdjust":0.0,"parallelPart":0.7017543859649122,"perpendicularPart":18.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"e","lineAngleAdjust":0.0,"parallelPart":0.8421052631578947,"perpendicularPart":35.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"f","lineAngleAdjust":0.0,"parallelPart":0.9385964912280702,"perpendicularPart":54.0},{"type":"Link","nodeA":3,"nodeB":5,"text":".","lineAngleAdjust":3.141592653589793,"parallelPart":0.7261146496815286,"perpendicularPart":-128.0},{"type":"Link","nodeA":5,"nodeB":4,"text":"","lineAngleAdjust":0.0,"parallelPart":0.836603886696308,"perpendicularPart":0.0},{"type":"Link","nodeA":4,"nodeB":6,"text":"","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"SelfLink","node":3,"text":"b","anchorAngle":-1.2419654938113762},{"type":"SelfLink","node":3,"text":"a","anchorAngle":-1.797069688822532},{"type":"SelfLink","node":3,"text":"c","anchorAngle":-0.5763752205911837},{"type":"SelfLink","node":3,"text":"d","anchorAngle":0.0},{"type":"SelfLink","node":3,"text":"e","anchorAngle":0.6078019961139605},{"type":"SelfLink","node":3,"text":"f","anchorAngle":0.9025069079643124},{"type":"Link","nodeA":6,"nodeB":7,"text":"ru","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":6,"nodeB":8,"text":"s","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":6,"nodeB":9,"text":"com","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":6,"nodeB":10,"text":"net","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":8,"nodeB":11,"text":"u","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":8,"nodeB":12,"text":"pace","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0}]}
+{"nodes":[{"x":74,"y":91,"text":"","isAcceptState":false},{"x":208,"y":91,"text":"user","isAcceptState":false},{"x":416,"y":91,"text":"","isAcceptState":false},{"x":539,"y":91,"text":"site","isAcceptState":false},{"x":135,"y":248,"text":"","isAcceptState":false},{"x":539,"y":248,"text":"dot","isAcceptState":false},{"x":135,"y":407,"text":"","isAcceptState":false},{"x":244,"y":314,"text":"","isAcceptState":true},{"x":305,"y":407,"text":"","isAcceptState":false},{"x":230,"y":491,"text":"","isAcceptState":true},{"x":87,"y":524,"text":"","isAcceptState":true},{"x":446,"y":331,"text":"","isAcceptState":true},{"x":435,"y":491,"text":"","isAcceptState":true}],"links":[{"type":"StartLink","node":0,"text":"","deltaX":-59,"deltaY":0},{"type":"Link","nodeA":0,"nodeB":1,"text":"a","lineAngleAdjust":3.141592653589793,"parallelPart":0.7647058823529411,"perpendicularPart":-56.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"b","lineAngleAdjust":3.141592653589793,"parallelPart":0.5588235294117647,"perpendicularPart":-37.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"c","lineAngleAdjust":3.141592653589793,"parallelPart":0.5588235294117647,"perpendicularPart":-14.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"d","lineAngleAdjust":0.0,"parallelPart":0.5147058823529411,"perpendicularPart":19.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"e","lineAngleAdjust":0.0,"parallelPart":0.6764705882352942,"perpendicularPart":40.0},{"type":"Link","nodeA":0,"nodeB":1,"text":"f","lineAngleAdjust":0.0,"parallelPart":0.6617647058823529,"perpendicularPart":67.0},{"type":"SelfLink","node":1,"text":"a","anchorAngle":1.740163983875466},{"type":"SelfLink","node":1,"text":"b","anchorAngle":1.3352513460740334},{"type":"SelfLink","node":1,"text":"c","anchorAngle":1.0360703319417248},{"type":"SelfLink","node":1,"text":"d","anchorAngle":-1.7539071440573808},{"type":"SelfLink","node":1,"text":"e","anchorAngle":-1.1839206090638683},{"type":"SelfLink","node":1,"text":"f","anchorAngle":-0.65788860518221},{"type":"Link","nodeA":1,"nodeB":2,"text":"@","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"a","lineAngleAdjust":3.141592653589793,"parallelPart":0.6754385964912281,"perpendicularPart":-58.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"b","lineAngleAdjust":3.141592653589793,"parallelPart":0.5877192982456141,"perpendicularPart":-34.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"c","lineAngleAdjust":3.141592653589793,"parallelPart":0.49122807017543857,"perpendicularPart":-14.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"d","lineAngleAdjust":0.0,"parallelPart":0.7017543859649122,"perpendicularPart":18.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"e","lineAngleAdjust":0.0,"parallelPart":0.8421052631578947,"perpendicularPart":35.0},{"type":"Link","nodeA":2,"nodeB":3,"text":"f","lineAngleAdjust":0.0,"parallelPart":0.9385964912280702,"perpendicularPart":54.0},{"type":"Link","nodeA":3,"nodeB":5,"text":"\\.","lineAngleAdjust":3.141592653589793,"parallelPart":0.7261146496815286,"perpendicularPart":-128.0},{"type":"Link","nodeA":5,"nodeB":4,"text":"","lineAngleAdjust":0.0,"parallelPart":0.836603886696308,"perpendicularPart":0.0},{"type":"Link","nodeA":4,"nodeB":6,"text":"","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"SelfLink","node":3,"text":"b","anchorAngle":-1.2419654938113762},{"type":"SelfLink","node":3,"text":"a","anchorAngle":-1.797069688822532},{"type":"SelfLink","node":3,"text":"c","anchorAngle":-0.5763752205911837},{"type":"SelfLink","node":3,"text":"d","anchorAngle":0.0},{"type":"SelfLink","node":3,"text":"e","anchorAngle":0.6078019961139605},{"type":"SelfLink","node":3,"text":"f","anchorAngle":0.9025069079643124},{"type":"Link","nodeA":6,"nodeB":7,"text":"ru","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":6,"nodeB":8,"text":"s","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":6,"nodeB":9,"text":"com","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":6,"nodeB":10,"text":"net","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":8,"nodeB":11,"text":"u","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0},{"type":"Link","nodeA":8,"nodeB":12,"text":"pace","lineAngleAdjust":0.0,"parallelPart":0.5,"perpendicularPart":0.0}]}
         "#,
     )
     .unwrap();
@@ -499,8 +499,10 @@ pub fn tutorial_ru() -> Html {
 
                 <p>{"Конечные автоматы задают регулярные языки, которые также задаются "}<em>{"регулярными выражениями"}</em>
                 {" — они часто полезны в программировании. Например, они используются в валидации текстовых полей; например, email-адресов."}</p>
-                <p>{"К сожалению, эта среда работы с конечными автоматами не позволяет сделать стрелочку, которая принимает \"любую одну букву\". "}
-                {"Поэтому мы ограничим набор допустимых букв: пусть имя пользователя и домен могут состоять из букв от "}<WordDisplay word="a" />
+                <p>{"На стрелочке можно написать не только буквы: "}<code>{"a,b"}</code>{" означает «a или b», "}<code>{"[a-f]"}</code>{" — любую букву от a до f, "}
+                {"точка — любую одну букву, а "}<code>{"\\e"}</code>{" — пустой переход. "}
+                {"Чтобы написать саму точку, запятую или скобку, перед ней ставится обратная косая черта: "}<code>{"\\."}</code>{". "}
+                {"Пусть имя пользователя и домен могут состоять из букв от "}<WordDisplay word="a" />
                 {" до "}<WordDisplay word="f" />{", и домен может заканчиваться только на .com, .ru, .su, .net или .space."}</p>
                 <p>{"Регулярное выражение для такого правила валидации будет выглядеть вот так: "}<code>{"[a-f]+@[a-f]+\\.(com|ru|net|(s(u|pace)))"}</code>{"; "}
                 {"это полностью соответствует следующему конечному автомату. "}
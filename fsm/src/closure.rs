@@ -0,0 +1,269 @@
+use crate::{
+    drawing::{assemble, epsilon_loop_representatives, fit_to_canvas, loop_text},
+    fsm::{FSMError, StateMachine},
+    label::Label,
+};
+
+/// How far apart the two machines are placed when concatenating, and how far new nodes are from old ones.
+const GAP: i32 = 150;
+
+/// A machine taken apart into plain lists, so that nodes and links can be added while building a new machine.
+#[derive(Clone, Debug, Default)]
+struct Parts {
+    positions: Vec<(i32, i32)>,
+    names: Vec<String>,
+    accept: Vec<bool>,
+    /// (node, text) for every start link.
+    starts: Vec<(usize, String)>,
+    /// (from, to, text) for every other link.
+    edges: Vec<(usize, usize, String)>,
+}
+
+impl Parts {
+    /// Take the machine apart. It must have passed [StateMachine::check_error].
+    fn new(fsm: &StateMachine) -> Self {
+        let mut parts = Parts {
+            positions: fsm.nodes.iter().map(|n| (n.x, n.y)).collect(),
+            names: fsm.nodes.iter().map(|n| n.text.clone()).collect(),
+            accept: fsm.nodes.iter().map(|n| n.accept_state).collect(),
+            ..Default::default()
+        };
+        for link in fsm.links.iter() {
+            let text = link.get_text().to_string();
+            match link.get_nodes() {
+                (None, node) => parts.starts.push((node, text)),
+                (Some(from), to) => parts.edges.push((from, to, text)),
+            }
+        }
+        parts
+    }
+
+    fn add_node(&mut self, position: (i32, i32), accept: bool) -> usize {
+        self.positions.push(position);
+        self.names.push(String::new());
+        self.accept.push(accept);
+        self.positions.len() - 1
+    }
+
+    fn accepting(&self) -> Vec<usize> {
+        (0..self.accept.len()).filter(|n| self.accept[*n]).collect()
+    }
+
+    /// Where to put a new node, so that it is to the left of the start links.
+    fn left_of_starts(&self) -> (i32, i32) {
+        let nodes = self.starts.iter().map(|(n, _)| self.positions[*n]);
+        let x = nodes.clone().map(|p| p.0).min().unwrap_or(0);
+        let count = self.starts.len().max(1) as i32;
+        let y = nodes.map(|p| p.1).sum::<i32>() / count;
+        (x - GAP, y)
+    }
+
+    /// Put the machine back together, merging any loops of epsilon links that the construction made.
+    fn build(self) -> StateMachine {
+        let Parts {
+            mut positions,
+            names,
+            accept,
+            starts,
+            edges,
+        } = self;
+        let representative = epsilon_loop_representatives(positions.len(), &edges);
+        let mut number = vec![None; positions.len()];
+        let mut kept = vec![];
+        for (node, rep) in representative.iter().enumerate() {
+            if *rep == node {
+                number[node] = Some(kept.len());
+                kept.push(node);
+            }
+        }
+        let new_number = |node: usize| number[representative[node]].unwrap();
+
+        let mut merged_accept = vec![false; kept.len()];
+        for (node, accept) in accept.iter().enumerate() {
+            merged_accept[new_number(node)] |= *accept;
+        }
+        let mut merged_names = vec![String::new(); kept.len()];
+        for (node, name) in names.into_iter().enumerate() {
+            let merged = &mut merged_names[new_number(node)];
+            if merged.is_empty() {
+                *merged = name;
+            }
+        }
+        positions = kept.iter().map(|n| positions[*n]).collect();
+        fit_to_canvas(&mut positions);
+
+        let mut merged_edges: Vec<(usize, usize, String)> = vec![];
+        for (from, to, text) in edges {
+            let (from, to) = (new_number(from), new_number(to));
+            let text = if from == to {
+                match loop_text(&text) {
+                    Some(text) => text,
+                    None => continue,
+                }
+            } else {
+                text
+            };
+            let edge = (from, to, text);
+            if !merged_edges.contains(&edge) {
+                merged_edges.push(edge);
+            }
+        }
+        let mut merged_starts: Vec<(usize, String)> = vec![];
+        for (node, text) in starts {
+            let start = (new_number(node), text);
+            if !merged_starts.contains(&start) {
+                merged_starts.push(start);
+            }
+        }
+
+        assemble(
+            &positions,
+            merged_names,
+            &merged_accept,
+            merged_starts,
+            merged_edges,
+        )
+    }
+}
+
+/// The text of the label reading every word of this one backwards.
+fn reversed_label(text: &str) -> String {
+    Label::parse_or_literal(text).reversed().to_string()
+}
+
+impl StateMachine {
+    fn checked_parts(&self) -> Result<Parts, FSMError> {
+        match self.check_error() {
+            Some(err) => Err(err),
+            None => Ok(Parts::new(self)),
+        }
+    }
+
+    /// Build a machine accepting a word of this machine followed by a word of the other one.
+    ///
+    /// The other machine is placed to the right, and every accepting node of this machine
+    /// gets a copy of the other machine's start links.
+    pub fn concatenate(&self, other: &StateMachine) -> Result<StateMachine, FSMError> {
+        let mut parts = self.checked_parts()?;
+        let second = other.checked_parts()?;
+
+        let right_edge = parts.positions.iter().map(|p| p.0).max().unwrap_or(0);
+        let left_edge = second.positions.iter().map(|p| p.0).min().unwrap_or(0);
+        let shift = right_edge - left_edge + GAP;
+        let offset = parts.positions.len();
+        let finals = parts.accepting();
+        parts.accept.iter_mut().for_each(|a| *a = false);
+
+        parts
+            .positions
+            .extend(second.positions.iter().map(|(x, y)| (x + shift, *y)));
+        parts.names.extend(second.names);
+        parts.accept.extend(second.accept);
+        parts.edges.extend(
+            second
+                .edges
+                .into_iter()
+                .map(|(a, b, text)| (a + offset, b + offset, text)),
+        );
+        for node in finals {
+            for (start, text) in second.starts.iter() {
+                parts.edges.push((node, start + offset, text.clone()));
+            }
+        }
+        Ok(parts.build())
+    }
+
+    /// Link every accepting node back along the start links, so that words can be repeated.
+    fn repeat_parts(&self) -> Result<Parts, FSMError> {
+        let mut parts = self.checked_parts()?;
+        for node in parts.accepting() {
+            for (start, text) in parts.starts.clone() {
+                parts.edges.push((node, start, text));
+            }
+        }
+        Ok(parts)
+    }
+
+    /// Build a machine accepting any number of this machine's words in a row, including none.
+    ///
+    /// A new accepting start node is added in front of the old start links.
+    pub fn star(&self) -> Result<StateMachine, FSMError> {
+        let mut parts = self.repeat_parts()?;
+        let start = parts.add_node(parts.left_of_starts(), true);
+        let old_starts = std::mem::replace(&mut parts.starts, vec![(start, String::new())]);
+        for (node, text) in old_starts {
+            parts.edges.push((start, node, text));
+        }
+        Ok(parts.build())
+    }
+
+    /// Build a machine accepting one or more of this machine's words in a row.
+    pub fn plus(&self) -> Result<StateMachine, FSMError> {
+        Ok(self.repeat_parts()?.build())
+    }
+
+    /// Build a machine accepting the reversed words of this machine.
+    ///
+    /// Every link is turned around and its text reversed; accepting nodes get the start links.
+    pub fn reverse(&self) -> Result<StateMachine, FSMError> {
+        let original = self.checked_parts()?;
+        let mut parts = Parts {
+            positions: original.positions.clone(),
+            names: original.names.clone(),
+            accept: vec![false; original.accept.len()],
+            starts: original
+                .accepting()
+                .into_iter()
+                .map(|n| (n, String::new()))
+                .collect(),
+            edges: original
+                .edges
+                .iter()
+                .map(|(a, b, text)| (*b, *a, reversed_label(text)))
+                .collect(),
+        };
+
+        // Start links that read text need a node to finish reading it in.
+        let mut finish = None;
+        for (node, text) in original.starts.iter() {
+            if text.is_empty() {
+                parts.accept[*node] = true;
+            } else {
+                let (x, y) = original.positions[*node];
+                let end = *finish.get_or_insert_with(|| parts.add_node((x - GAP, y), true));
+                parts.edges.push((*node, end, reversed_label(text)));
+            }
+        }
+
+        // With no accepting nodes the language is empty, but the machine still needs a start link.
+        if parts.starts.is_empty() {
+            let position = original.left_of_starts();
+            let start = parts.add_node(position, false);
+            parts.starts.push((start, String::new()));
+        }
+        Ok(parts.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{drawing::test_machine, fsm::FSMOutput};
+
+    #[test]
+    fn repeating_explicit_epsilon_links_leaves_no_empty_loop() {
+        for text in ["\\e", "ε", "a,\\e"] {
+            let optional = test_machine(&[false, true], &[(0, 1, text), (0, 1, "b")]);
+            for repeated in [optional.plus().unwrap(), optional.star().unwrap()] {
+                assert_eq!(repeated.check_error(), None, "{text:?}");
+                for word in ["", "b", "bb"] {
+                    assert_eq!(
+                        repeated.evaluate(word),
+                        Ok(FSMOutput::Accept),
+                        "{text:?} {word:?}"
+                    );
+                }
+                assert_eq!(repeated.evaluate("c"), Ok(FSMOutput::Reject), "{text:?}");
+            }
+        }
+    }
+}
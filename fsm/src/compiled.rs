@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    fsm::{FSMOutput, StateMachine},
+    label::Label,
+};
+
+#[derive(Clone, Debug, Default)]
+struct TrieNode {
+    children: BTreeMap<char, usize>,
+    /// Links whose whole text has been read when this trie node is reached.
+    links: Vec<usize>,
+}
+
+/// The texts of a group of links, merged into a trie, so that all links matching a word can be found in one pass.
+#[derive(Clone, Debug)]
+struct LabelTrie {
+    nodes: Vec<TrieNode>,
+}
+
+impl Default for LabelTrie {
+    fn default() -> Self {
+        LabelTrie {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+}
+
+impl LabelTrie {
+    fn insert(&mut self, text: &str, link: usize) {
+        let mut current = 0;
+        for c in text.chars() {
+            current = match self.nodes[current].children.get(&c) {
+                Some(next) => *next,
+                None => {
+                    self.nodes.push(TrieNode::default());
+                    let next = self.nodes.len() - 1;
+                    self.nodes[current].children.insert(c, next);
+                    next
+                }
+            };
+        }
+        if !self.nodes[current].links.contains(&link) {
+            self.nodes[current].links.push(link);
+        }
+    }
+
+    /// Find the links whose text is a prefix of `rest`, along with the length of that text in bytes.
+    fn matches(&self, rest: &str, out: &mut Vec<(usize, usize)>) {
+        let mut current = 0;
+        let mut chars = rest.char_indices();
+        loop {
+            let read = chars.clone().next().map_or(rest.len(), |(i, _)| i);
+            out.extend(self.nodes[current].links.iter().map(|link| (*link, read)));
+            current = match chars.next() {
+                Some((_, c)) => match self.nodes[current].children.get(&c) {
+                    Some(next) => *next,
+                    None => return,
+                },
+                None => return,
+            };
+        }
+    }
+}
+
+/// A [StateMachine] prepared for running many words through it quickly.
+///
+/// The links leaving every node are indexed in a trie by their text;
+/// labels with classes or wildcards, which a trie cannot hold, are kept aside and tried one by one.
+/// Evaluation tracks the set of (node, position in the word) pairs instead of separate cursors,
+/// so every pair is visited at most once.
+#[derive(Clone, Debug)]
+pub struct CompiledMachine {
+    accept: Vec<bool>,
+    /// The node every link leads to.
+    targets: Vec<usize>,
+    /// The links leaving every node, and finally the start links.
+    tries: Vec<LabelTrie>,
+    /// The same, for the links whose labels are not plain texts.
+    patterns: Vec<Vec<(usize, Label)>>,
+    has_start_links: bool,
+}
+
+impl CompiledMachine {
+    /// Index the machine's links. Links pointing at nodes that do not exist are left out,
+    /// and texts that are not valid labels are read literally.
+    pub fn new(fsm: &StateMachine) -> Self {
+        let count = fsm.nodes.len();
+        let mut tries = vec![LabelTrie::default(); count + 1];
+        let mut patterns = vec![vec![]; count + 1];
+        let mut has_start_links = false;
+        for (id, link) in fsm.links.iter().enumerate() {
+            let (a, b) = link.get_nodes();
+            if b >= count || a.is_some_and(|a| a >= count) {
+                continue;
+            }
+            has_start_links |= a.is_none();
+            let from = a.unwrap_or(count);
+            let label = Label::parse_or_literal(link.get_text());
+            match label.as_literals() {
+                Some(texts) => texts.iter().for_each(|text| tries[from].insert(text, id)),
+                None => patterns[from].push((id, label)),
+            }
+        }
+        CompiledMachine {
+            accept: fsm.nodes.iter().map(|n| n.accept_state).collect(),
+            targets: fsm.links.iter().map(|l| l.get_nodes().1).collect(),
+            tries,
+            patterns,
+            has_start_links,
+        }
+    }
+
+    /// Add the links leaving the node with this index whose label reads a prefix of `rest` to `out`.
+    fn matches(&self, index: usize, rest: &str, out: &mut Vec<(usize, usize)>) {
+        self.tries[index].matches(rest, out);
+        for (link, label) in self.patterns[index].iter() {
+            out.extend(label.prefix_lengths(rest).into_iter().map(|l| (*link, l)));
+        }
+    }
+
+    pub fn has_start_links(&self) -> bool {
+        self.has_start_links
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.accept.len()
+    }
+
+    pub fn is_accepting(&self, node: usize) -> bool {
+        self.accept[node]
+    }
+
+    /// The node this link leads to.
+    pub fn target(&self, link: usize) -> usize {
+        self.targets[link]
+    }
+
+    /// Find the links leaving this node (or the start links, for None) whose label reads a prefix of `rest`.
+    ///
+    /// Returns the link indices, with the length of the prefix in bytes.
+    /// A link whose label reads several prefixes appears once for each of them.
+    pub fn links_matching(&self, node: Option<usize>, rest: &str) -> Vec<(usize, usize)> {
+        let mut out = vec![];
+        self.matches(node.unwrap_or(self.accept.len()), rest, &mut out);
+        out
+    }
+
+    /// Run the word through the machine.
+    ///
+    /// This always finishes, even if the machine has loops of empty links,
+    /// because every (node, position) pair is only visited once.
+    pub fn evaluate(&self, word: &str) -> FSMOutput {
+        let positions = word.len() + 1;
+        let mut visited = vec![false; self.accept.len() * positions];
+        let mut stack: Vec<(usize, usize)> = vec![];
+        let mut matches = vec![];
+
+        let mut visit = |node: usize, position: usize, stack: &mut Vec<(usize, usize)>| {
+            let seen = &mut visited[node * positions + position];
+            if !*seen {
+                *seen = true;
+                stack.push((node, position));
+            }
+        };
+
+        self.matches(self.accept.len(), word, &mut matches);
+        for (link, length) in matches.drain(..) {
+            visit(self.targets[link], length, &mut stack);
+        }
+
+        while let Some((node, position)) = stack.pop() {
+            if position == word.len() && self.accept[node] {
+                return FSMOutput::Accept;
+            }
+            self.matches(node, &word[position..], &mut matches);
+            for (link, length) in matches.drain(..) {
+                visit(self.targets[link], position + length, &mut stack);
+            }
+        }
+        FSMOutput::Reject
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        drawing::test_machine,
+        fsm::{FSMOutput, StateMachine},
+        label::Label,
+    };
+
+    use super::CompiledMachine;
+
+    /// Run the word by trying every link's label on its own, without the trie.
+    fn evaluate_by_labels(fsm: &StateMachine, word: &str) -> FSMOutput {
+        let mut seen = BTreeSet::new();
+        let mut stack: Vec<(Option<usize>, usize)> = vec![(None, 0)];
+        while let Some((node, position)) = stack.pop() {
+            if let Some(node) = node {
+                if position == word.len() && fsm.nodes[node].accept_state {
+                    return FSMOutput::Accept;
+                }
+            }
+            for link in fsm.links.iter() {
+                let (from, to) = link.get_nodes();
+                if from != node {
+                    continue;
+                }
+                let label = Label::parse_or_literal(link.get_text());
+                for length in label.prefix_lengths(&word[position..]) {
+                    if seen.insert((to, position + length)) {
+                        stack.push((Some(to), position + length));
+                    }
+                }
+            }
+        }
+        FSMOutput::Reject
+    }
+
+    fn all_words(alphabet: &[char], max_length: usize) -> Vec<String> {
+        let mut words = vec![String::new()];
+        let mut last = words.clone();
+        for _ in 0..max_length {
+            last = last
+                .iter()
+                .flat_map(|word| alphabet.iter().map(move |c| format!("{word}{c}")))
+                .collect();
+            words.extend(last.iter().cloned());
+        }
+        words
+    }
+
+    fn assert_same_as_labels(fsm: &StateMachine, alphabet: &[char]) {
+        let compiled = CompiledMachine::new(fsm);
+        for word in all_words(alphabet, 4) {
+            assert_eq!(
+                compiled.evaluate(&word),
+                evaluate_by_labels(fsm, &word),
+                "on {word:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn agrees_with_labels_on_unicode_words() {
+        let fsm = test_machine(
+            &[false, false, true],
+            &[
+                (0, 1, "жa"),
+                (0, 1, "ж"),
+                (1, 2, "\\ε"),
+                (2, 0, "aж,b"),
+                (1, 1, "яя"),
+            ],
+        );
+        assert_same_as_labels(&fsm, &['a', 'b', 'ж', 'я', 'ε']);
+        let compiled = CompiledMachine::new(&fsm);
+        assert_eq!(compiled.evaluate("жaε"), FSMOutput::Accept);
+        assert_eq!(compiled.evaluate("жяяεbжε"), FSMOutput::Accept);
+        assert_eq!(compiled.evaluate("жa"), FSMOutput::Reject);
+    }
+
+    #[test]
+    fn agrees_with_labels_on_patterns() {
+        let fsm = test_machine(
+            &[false, true, false, true],
+            &[
+                (0, 1, "."),
+                (1, 2, "a,bb"),
+                (2, 1, "[a-c]"),
+                (1, 3, "\\e"),
+                (3, 3, "[x-zж]"),
+            ],
+        );
+        assert_same_as_labels(&fsm, &['a', 'b', 'z', 'ж', 'ε']);
+    }
+
+    #[test]
+    fn epsilon_cycles_finish() {
+        let fsm = test_machine(
+            &[false, false, true],
+            &[
+                (0, 1, "\\e"),
+                (1, 0, ""),
+                (1, 1, "ε,a"),
+                (0, 2, "b"),
+                (2, 2, "\\e"),
+            ],
+        );
+        assert_same_as_labels(&fsm, &['a', 'b']);
+        let compiled = CompiledMachine::new(&fsm);
+        assert_eq!(compiled.evaluate("aab"), FSMOutput::Accept);
+        assert_eq!(compiled.evaluate("aaba"), FSMOutput::Reject);
+    }
+
+    #[test]
+    fn matches_report_lengths_in_bytes() {
+        let fsm = test_machine(&[true], &[(0, 0, "ж"), (0, 0, "жж,a"), (0, 0, "[а-я]")]);
+        let compiled = CompiledMachine::new(&fsm);
+        let mut matches = compiled.links_matching(Some(0), "жжa");
+        matches.sort();
+        assert_eq!(matches, [(1, 2), (2, 4), (3, 2)]);
+        assert_eq!(compiled.links_matching(Some(0), "aж"), [(2, 1)]);
+        assert_eq!(compiled.links_matching(None, "ж"), [(0, 0)]);
+    }
+
+    #[test]
+    fn every_node_and_position_is_visited_once() {
+        // Every word of n letters has 2ⁿ paths here, which would be too many to follow one by one.
+        let mut links = vec![];
+        for node in 0..40 {
+            links.push((node, node + 1, "a"));
+            links.push((node, node + 1, "a,\\e"));
+        }
+        let mut accept = vec![false; 41];
+        accept[40] = true;
+        let fsm = test_machine(&accept, &links);
+        let compiled = CompiledMachine::new(&fsm);
+        assert_eq!(compiled.evaluate(&"a".repeat(30)), FSMOutput::Accept);
+        assert_eq!(compiled.evaluate(&"a".repeat(41)), FSMOutput::Reject);
+    }
+}
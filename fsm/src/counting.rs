@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+
+use petgraph::Graph;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dfa::Dfa,
+    fsm::{FSMError, StateMachine},
+};
+
+/// How many words a language contains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LanguageSize {
+    /// No words are accepted at all.
+    Empty,
+
+    /// Finitely many words are accepted. Counts that do not fit saturate at `u64::MAX`.
+    Finite { words: u64, longest: usize },
+
+    /// Arbitrarily long words are accepted.
+    Infinite,
+}
+
+impl Dfa {
+    /// Count the accepted words of every length from 0 to `max_length`.
+    ///
+    /// Counts that do not fit saturate at `u64::MAX`.
+    pub fn count_words(&self, max_length: usize) -> Vec<u64> {
+        // How many words of the current length lead from the start to every state.
+        let mut paths = vec![0u64; self.len()];
+        paths[self.start] = 1;
+        let mut counts = Vec::with_capacity(max_length + 1);
+        for length in 0..=max_length {
+            counts.push(
+                paths
+                    .iter()
+                    .zip(self.accept.iter())
+                    .filter(|(_, accept)| **accept)
+                    .fold(0u64, |sum, (p, _)| sum.saturating_add(*p)),
+            );
+            if length == max_length {
+                break;
+            }
+            let mut next = vec![0u64; self.len()];
+            for (state, row) in self.transitions.iter().enumerate() {
+                for target in row.iter().flatten() {
+                    next[*target] = next[*target].saturating_add(paths[state]);
+                }
+            }
+            paths = next;
+        }
+        counts
+    }
+
+    /// Find the states that are reachable from the start and from which some word is accepted.
+    fn useful_states(&self) -> Vec<bool> {
+        let live = self.live_states();
+        let mut reachable = vec![false; self.len()];
+        reachable[self.start] = true;
+        let mut queue = VecDeque::from([self.start]);
+        while let Some(state) = queue.pop_front() {
+            for target in self.transitions[state].iter().flatten() {
+                if !reachable[*target] {
+                    reachable[*target] = true;
+                    queue.push_back(*target);
+                }
+            }
+        }
+        live.iter().zip(reachable).map(|(l, r)| *l && r).collect()
+    }
+
+    /// Decide whether the language is empty, finite or infinite.
+    pub fn language_size(&self) -> LanguageSize {
+        let useful = self.useful_states();
+        if !useful[self.start] {
+            return LanguageSize::Empty;
+        }
+
+        // The language is infinite exactly when a loop can be taken on the way to an accepting state.
+        let mut graph = Graph::new();
+        let nodes: Vec<_> = (0..self.len()).map(|_| graph.add_node(())).collect();
+        for (state, row) in self.transitions.iter().enumerate() {
+            for target in row.iter().flatten() {
+                if useful[state] && useful[*target] {
+                    graph.add_edge(nodes[state], nodes[*target], ());
+                }
+            }
+        }
+        if petgraph::algo::is_cyclic_directed(&graph) {
+            return LanguageSize::Infinite;
+        }
+
+        // Without loops, no accepted word is longer than the number of useful states.
+        let useful_count = useful.iter().filter(|u| **u).count();
+        let counts = self.count_words(useful_count);
+        LanguageSize::Finite {
+            words: counts.iter().fold(0u64, |sum, c| sum.saturating_add(*c)),
+            longest: counts.iter().rposition(|c| *c > 0).unwrap_or(0),
+        }
+    }
+}
+
+impl StateMachine {
+    /// Count the accepted words of every length from 0 to `max_length`.
+    pub fn count_words(&self, max_length: usize) -> Result<Vec<u64>, FSMError> {
+        Ok(self.to_dfa()?.count_words(max_length))
+    }
+
+    /// Decide whether this machine accepts no words, finitely many, or infinitely many.
+    pub fn language_size(&self) -> Result<LanguageSize, FSMError> {
+        Ok(self.to_dfa()?.language_size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fsm::StateMachine, regex::Regex};
+
+    use super::LanguageSize;
+
+    fn compile(regex: &str) -> StateMachine {
+        Regex::parse(regex).unwrap().to_state_machine()
+    }
+
+    #[test]
+    fn counts_words_of_every_length() {
+        assert_eq!(compile("(a|b)*").count_words(4), Ok(vec![1, 2, 4, 8, 16]));
+        assert_eq!(
+            compile("(a|b)*abb").count_words(5),
+            Ok(vec![0, 0, 0, 1, 2, 4])
+        );
+        // Words read along different paths of a nondeterministic machine are counted once.
+        assert_eq!(compile("a*|a*").count_words(2), Ok(vec![1, 1, 1]));
+    }
+
+    #[test]
+    fn tells_the_size_of_the_language() {
+        assert_eq!(compile("∅").language_size(), Ok(LanguageSize::Empty));
+        assert_eq!(
+            compile("a|bc|[0-9]").language_size(),
+            Ok(LanguageSize::Finite {
+                words: 12,
+                longest: 2
+            })
+        );
+        assert_eq!(compile("ab*").language_size(), Ok(LanguageSize::Infinite));
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::{
+    drawing::{assemble, circle_positions},
+    fsm::{FSMError, FSMOutput, StateMachine},
+    label::Label,
+};
+
+/// A nondeterministic automaton where every edge consumes at most one character.
+///
+/// It is built from a [StateMachine] by splitting every alternative of every label into a chain of edges:
+/// longer alternatives get intermediate states, classes become one edge per character,
+/// and empty alternatives become epsilon edges.
+/// All start links leave from one extra start state.
+///
+/// A `.` becomes one edge for every known character, and one for the [other](Nfa::other) character,
+/// which stands for all the characters that no label mentions.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Nfa {
+    /// For every state: (character, or None for epsilon; target state).
+    pub edges: Vec<Vec<(Option<char>, usize)>>,
+    pub accept: Vec<bool>,
+    pub start: usize,
+    /// For every state: the node of the original machine it stands for, if any.
+    pub origin: Vec<Option<usize>>,
+    /// The character standing for every unknown character, if the machine has a `.` anywhere.
+    pub other: Option<char>,
+}
+
+impl Nfa {
+    fn add_state(&mut self, origin: Option<usize>, accept: bool) -> usize {
+        self.edges.push(vec![]);
+        self.accept.push(accept);
+        self.origin.push(origin);
+        self.edges.len() - 1
+    }
+
+    fn add_label(&mut self, from: usize, label: &Label, to: usize, wildcard: &[char]) {
+        for alternative in label.alternatives.iter() {
+            if alternative.is_empty() {
+                self.edges[from].push((None, to));
+                continue;
+            }
+            let mut current = from;
+            for (i, set) in alternative.iter().enumerate() {
+                let next = if i == alternative.len() - 1 {
+                    to
+                } else {
+                    self.add_state(None, false)
+                };
+                for c in set.chars(wildcard) {
+                    self.edges[current].push((Some(c), next));
+                }
+                current = next;
+            }
+        }
+    }
+
+    /// Build the automaton. The machine must have passed [StateMachine::check_error].
+    pub fn from_state_machine(fsm: &StateMachine) -> Self {
+        Self::from_state_machine_over(fsm, &[])
+    }
+
+    /// Build the automaton, counting the `extra` characters as known even if no label mentions them.
+    ///
+    /// Automata built over the same known characters pick the same [other](Nfa::other) character,
+    /// so they can be run side by side.
+    pub fn from_state_machine_over(fsm: &StateMachine, extra: &[char]) -> Self {
+        let mut known = fsm.label_alphabet();
+        known.extend_from_slice(extra);
+        let mut nfa = Nfa::default();
+        if fsm.has_wildcard() {
+            let other = other_char(&known);
+            known.push(other);
+            nfa.other = Some(other);
+        }
+        let wildcard = known;
+        for (i, node) in fsm.nodes.iter().enumerate() {
+            nfa.add_state(Some(i), node.accept_state);
+        }
+        nfa.start = nfa.add_state(None, false);
+
+        for link in fsm.links.iter() {
+            let (a, b) = link.get_nodes();
+            let from = a.unwrap_or(nfa.start);
+            nfa.add_label(
+                from,
+                &Label::parse_or_literal(link.get_text()),
+                b,
+                &wildcard,
+            );
+        }
+        nfa
+    }
+
+    /// Extend the set with every state reachable from it by epsilon edges.
+    pub fn epsilon_closure(&self, set: &mut BTreeSet<usize>) {
+        let mut stack: Vec<usize> = set.iter().copied().collect();
+        while let Some(state) = stack.pop() {
+            for (c, target) in self.edges[state].iter() {
+                if c.is_none() && set.insert(*target) {
+                    stack.push(*target);
+                }
+            }
+        }
+    }
+
+    /// All the characters that appear on edges, sorted.
+    pub fn alphabet(&self) -> Vec<char> {
+        let set: BTreeSet<char> = self
+            .edges
+            .iter()
+            .flatten()
+            .filter_map(|(c, _)| *c)
+            .collect();
+        set.into_iter().collect()
+    }
+}
+
+/// The most states a subset construction builds before giving up with [FSMError::TooManyStates].
+///
+/// Subset construction can take exponentially many states, and machines come from anyone who can submit one.
+pub const MAX_DFA_STATES: usize = 10_000;
+
+/// Pick a character that is not among the known ones, to stand for all the characters that are not.
+///
+/// The choice only depends on the set of known characters, and prefers letters and digits,
+/// so that words containing it still read naturally.
+pub(crate) fn other_char(known: &[char]) -> char {
+    ('a'..='z')
+        .chain('0'..='9')
+        .chain('A'..='Z')
+        .chain('\u{a1}'..=char::MAX)
+        .find(|c| !known.contains(c))
+        .expect("labels cannot mention every character")
+}
+
+/// The set of NFA states that a state of the subset construction stands for.
+pub(crate) type NfaStates = BTreeSet<usize>;
+
+/// A deterministic automaton over single characters.
+///
+/// The transition table may be partial: a missing transition means the word is rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dfa {
+    /// The characters this automaton has transitions for, sorted.
+    pub alphabet: Vec<char>,
+    pub start: usize,
+    pub accept: Vec<bool>,
+    /// For every state, for every character of the alphabet: the target state, if any.
+    pub transitions: Vec<Vec<Option<usize>>>,
+    /// A character of the alphabet that stands for every character outside of it, if the machine had a `.`.
+    ///
+    /// Without one, characters outside of the alphabet have no transitions at all.
+    pub other: Option<char>,
+}
+
+impl Dfa {
+    /// Number of states.
+    pub fn len(&self) -> usize {
+        self.accept.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accept.is_empty()
+    }
+
+    /// Index of this character in the alphabet.
+    pub fn char_index(&self, c: char) -> Option<usize> {
+        self.alphabet.binary_search(&c).ok()
+    }
+
+    /// Index of the column that this character follows: its own, or the [other](Dfa::other) character's.
+    pub fn column(&self, c: char) -> Option<usize> {
+        self.char_index(c).or_else(|| self.char_index(self.other?))
+    }
+
+    pub fn step(&self, state: usize, c: char) -> Option<usize> {
+        self.transitions[state][self.column(c)?]
+    }
+
+    pub fn evaluate(&self, word: &str) -> FSMOutput {
+        let mut state = self.start;
+        for c in word.chars() {
+            state = match self.step(state, c) {
+                Some(s) => s,
+                None => return FSMOutput::Reject,
+            };
+        }
+        if self.accept[state] {
+            FSMOutput::Accept
+        } else {
+            FSMOutput::Reject
+        }
+    }
+
+    /// Run the subset construction.
+    ///
+    /// Returns the automaton, and for every one of its states the set of NFA states it stands for.
+    /// The empty set is never a state: those transitions are left missing.
+    /// Fails if there would be more than [MAX_DFA_STATES] states.
+    pub(crate) fn from_nfa(nfa: &Nfa) -> Result<(Self, Vec<NfaStates>), FSMError> {
+        let (dfa, sets, _) = Self::from_nfa_roots(nfa, &[nfa.start])?;
+        Ok((dfa, sets))
+    }
+
+    /// Run the subset construction starting from several NFA states at once.
+    ///
+    /// The first root becomes the start state.
+    /// Also returns which state every root ended up as.
+    pub(crate) fn from_nfa_roots(
+        nfa: &Nfa,
+        roots: &[usize],
+    ) -> Result<(Self, Vec<NfaStates>, Vec<usize>), FSMError> {
+        let alphabet = nfa.alphabet();
+        let mut known: BTreeMap<BTreeSet<usize>, usize> = BTreeMap::new();
+        let mut sets = vec![];
+        let mut root_states = vec![];
+        let mut transitions = vec![];
+        let mut queue = VecDeque::new();
+
+        for root in roots {
+            let mut set = BTreeSet::from([*root]);
+            nfa.epsilon_closure(&mut set);
+            let idx = match known.get(&set) {
+                Some(i) => *i,
+                None => {
+                    sets.push(set.clone());
+                    known.insert(set, sets.len() - 1);
+                    queue.push_back(sets.len() - 1);
+                    sets.len() - 1
+                }
+            };
+            root_states.push(idx);
+        }
+
+        while let Some(idx) = queue.pop_front() {
+            let mut row = vec![None; alphabet.len()];
+            for (ci, c) in alphabet.iter().enumerate() {
+                let mut next = BTreeSet::new();
+                for state in sets[idx].iter() {
+                    for (ec, target) in nfa.edges[*state].iter() {
+                        if *ec == Some(*c) {
+                            next.insert(*target);
+                        }
+                    }
+                }
+                if next.is_empty() {
+                    continue;
+                }
+                nfa.epsilon_closure(&mut next);
+                let next_idx = match known.get(&next) {
+                    Some(i) => *i,
+                    None => {
+                        if sets.len() == MAX_DFA_STATES {
+                            return Err(FSMError::TooManyStates(MAX_DFA_STATES));
+                        }
+                        sets.push(next.clone());
+                        known.insert(next, sets.len() - 1);
+                        queue.push_back(sets.len() - 1);
+                        sets.len() - 1
+                    }
+                };
+                row[ci] = Some(next_idx);
+            }
+            // States are discovered in BFS order, so rows are filled in order too.
+            transitions.push(row);
+        }
+
+        let accept = sets
+            .iter()
+            .map(|set| set.iter().any(|s| nfa.accept[*s]))
+            .collect();
+
+        Ok((
+            Dfa {
+                other: nfa.other.filter(|o| alphabet.contains(o)),
+                alphabet,
+                start: 0,
+                accept,
+                transitions,
+            },
+            sets,
+            root_states,
+        ))
+    }
+
+    /// Convert into a drawable [StateMachine], naming the nodes `q0`, `q1`...
+    pub fn to_state_machine(&self) -> Result<StateMachine, FSMError> {
+        let names = (0..self.len()).map(|i| format!("q{i}")).collect();
+        self.to_state_machine_with_names(names)
+    }
+
+    /// Convert into a drawable [StateMachine] with the given node names.
+    ///
+    /// All transitions between the same two states become one link, labelled with their characters,
+    /// and nodes are placed on a circle.
+    /// The [other](Dfa::other) character becomes a `.`, which labels can only say when every character
+    /// goes to the same state; otherwise this fails with [FSMError::WildcardNotExpressible].
+    pub fn to_state_machine_with_names(
+        &self,
+        names: Vec<String>,
+    ) -> Result<StateMachine, FSMError> {
+        let positions = circle_positions(self.len(), self.start);
+        let mut edges = vec![];
+        for (from, row) in self.transitions.iter().enumerate() {
+            let mut by_target: BTreeMap<usize, Vec<char>> = BTreeMap::new();
+            for (ci, to) in row.iter().enumerate() {
+                if let Some(to) = to {
+                    by_target.entry(*to).or_default().push(self.alphabet[ci]);
+                }
+            }
+            for (to, chars) in by_target {
+                let text = match self.other {
+                    Some(other) if chars.contains(&other) => {
+                        if chars.len() != self.alphabet.len() {
+                            return Err(FSMError::WildcardNotExpressible);
+                        }
+                        ".".to_string()
+                    }
+                    _ => Label::from_chars(&chars).to_string(),
+                };
+                edges.push((from, to, text));
+            }
+        }
+        Ok(assemble(
+            &positions,
+            names,
+            &self.accept,
+            vec![(self.start, String::new())],
+            edges,
+        ))
+    }
+}
+
+impl StateMachine {
+    /// Build the deterministic automaton for this machine's language.
+    ///
+    /// Since an automaton needs a finite alphabet, `.` in labels becomes the characters
+    /// written in this machine's labels, and the [other](Dfa::other) character for all the rest.
+    pub fn to_dfa(&self) -> Result<Dfa, FSMError> {
+        self.to_dfa_over(&[])
+    }
+
+    /// Build the deterministic automaton for this machine's language,
+    /// counting the `extra` characters as written in its labels.
+    ///
+    /// When comparing machines, pass the other machine's [StateMachine::label_alphabet] here,
+    /// so that both automata agree on which character stands for the unknown ones.
+    pub fn to_dfa_over(&self, extra: &[char]) -> Result<Dfa, FSMError> {
+        if let Some(err) = self.check_error() {
+            return Err(err);
+        }
+        let nfa = Nfa::from_state_machine_over(self, extra);
+        Ok(Dfa::from_nfa(&nfa)?.0)
+    }
+
+    /// Convert this machine into an equivalent deterministic one, where every link has a single character.
+    ///
+    /// A `.` can only be kept where it is followed the same way as every written character;
+    /// otherwise this fails with [FSMError::WildcardNotExpressible].
+    ///
+    /// Every node of the result is named after the set of original nodes it stands for.
+    pub fn determinize(&self) -> Result<StateMachine, FSMError> {
+        if let Some(err) = self.check_error() {
+            return Err(err);
+        }
+        let nfa = Nfa::from_state_machine(self);
+        let (dfa, sets) = Dfa::from_nfa(&nfa)?;
+        // Sets that differ only in intermediate states would get the same name, so mark repeats with primes.
+        let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+        let names = sets
+            .iter()
+            .map(|set| self.node_set_name(set.iter().filter_map(|s| nfa.origin[*s])))
+            .map(|name| {
+                let repeats = seen.entry(name.clone()).or_default();
+                *repeats += 1;
+                format!("{name}{}", "'".repeat(*repeats - 1))
+            })
+            .collect();
+        dfa.to_state_machine_with_names(names)
+    }
+
+    /// Name a set of nodes like `{q0,q1}`, using node indices for nodes without text.
+    ///
+    /// The empty set gets an empty name.
+    pub(crate) fn node_set_name(&self, nodes: impl Iterator<Item = usize>) -> String {
+        let nodes: BTreeSet<usize> = nodes.collect();
+        if nodes.is_empty() {
+            return String::new();
+        }
+        let parts: Vec<String> = nodes
+            .iter()
+            .map(|i| match self.nodes[*i].text.as_str() {
+                "" => i.to_string(),
+                text => text.to_string(),
+            })
+            .collect();
+        format!("{{{}}}", parts.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        drawing::test_machine,
+        fsm::{FSMError, FSMOutput},
+    };
+
+    #[test]
+    fn wildcard_matches_unmentioned_characters() {
+        let any = test_machine(&[false, true], &[(0, 1, ".")]);
+        let dfa = any.to_dfa().unwrap();
+        assert_eq!(dfa.evaluate("x"), FSMOutput::Accept);
+        assert_eq!(dfa.evaluate("xy"), FSMOutput::Reject);
+
+        let determinized = any.determinize().unwrap();
+        for word in ["x", "0", ".", "", "xy"] {
+            assert_eq!(determinized.evaluate(word), any.evaluate(word), "{word:?}");
+        }
+        assert_eq!(any.to_regex().unwrap().to_string(), ".");
+    }
+
+    #[test]
+    fn wildcard_is_not_equivalent_to_one_character() {
+        let any = test_machine(&[false, true], &[(0, 1, ".")]);
+        let one = test_machine(&[false, true], &[(0, 1, "1")]);
+        let (word, model_output) = any.distinguishing_word(&one).unwrap().unwrap();
+        assert_eq!(word.chars().count(), 1);
+        assert_eq!(model_output, FSMOutput::Reject);
+        assert_eq!(any.evaluate(&word).unwrap(), FSMOutput::Accept);
+
+        let redundant = test_machine(&[false, true], &[(0, 1, "a,.")]);
+        assert_eq!(redundant.distinguishing_word(&any).unwrap(), None);
+    }
+
+    #[test]
+    fn subset_construction_stops_at_the_state_limit() {
+        // Remembering the last 15 letters takes 2^15 states.
+        let regex: crate::regex::Regex = format!("[ab]*a{}", "[ab]".repeat(14)).parse().unwrap();
+        let fsm = regex.to_state_machine();
+        assert_eq!(
+            fsm.to_dfa(),
+            Err(FSMError::TooManyStates(super::MAX_DFA_STATES))
+        );
+    }
+
+    #[test]
+    fn wildcard_with_exceptions_cannot_be_determinized() {
+        let fsm = test_machine(&[false, true, false], &[(0, 1, "."), (0, 2, "a")]);
+        assert_eq!(fsm.determinize(), Err(FSMError::WildcardNotExpressible));
+        assert_eq!(fsm.to_dfa().unwrap().evaluate("a"), FSMOutput::Accept);
+    }
+}
@@ -0,0 +1,456 @@
+//! Finding what changed between two versions of a machine.
+
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fsm::StateMachine;
+
+/// How far a node may move and still be taken for the same node when its text changed too, in pixels.
+const MAX_MOVE: i32 = 60;
+
+/// How many partial matchings the search for an isomorphism may try before giving up.
+const MAX_ISOMORPHISM_STEPS: usize = 100_000;
+
+/// One difference between an old machine and a new one.
+///
+/// Nodes and links that exist in both are given by their index in each: `old` and `new`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Change {
+    /// This node of the new machine is not in the old one.
+    AddedNode(usize),
+
+    /// This node of the old machine is not in the new one.
+    RemovedNode(usize),
+
+    /// The node's text changed.
+    RelabeledNode { old: usize, new: usize },
+
+    /// The node became accepting, or stopped being accepting.
+    AcceptStateChanged { old: usize, new: usize },
+
+    /// This link of the new machine is not in the old one.
+    AddedLink(usize),
+
+    /// This link of the old machine is not in the new one.
+    RemovedLink(usize),
+
+    /// The link joins the same nodes, but its text changed.
+    RelabeledLink { old: usize, new: usize },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let now = |old: &usize, new: &usize, what: &str| match old == new {
+            true => String::new(),
+            false => format!(", and is now {what} {new}"),
+        };
+        match self {
+            Change::AddedNode(n) => write!(f, "node {n} was added"),
+            Change::RemovedNode(n) => write!(f, "node {n} was removed"),
+            Change::RelabeledNode { old, new } => {
+                write!(f, "node {old} was renamed{}", now(old, new, "node"))
+            }
+            Change::AcceptStateChanged { old, new } => write!(
+                f,
+                "node {old} changed whether it accepts{}",
+                now(old, new, "node")
+            ),
+            Change::AddedLink(l) => write!(f, "link {l} was added"),
+            Change::RemovedLink(l) => write!(f, "link {l} was removed"),
+            Change::RelabeledLink { old, new } => {
+                write!(f, "the text of link {old} changed{}", now(old, new, "link"))
+            }
+        }
+    }
+}
+
+/// How the nodes and links of an old machine correspond to a new one's, and what changed between them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diff {
+    /// For every node of the old machine, the node of the new machine it became, if any.
+    pub nodes: Vec<Option<usize>>,
+
+    /// For every link of the old machine, the link of the new machine it became, if any.
+    pub links: Vec<Option<usize>>,
+
+    /// Whether the machines have the same shape and the same accepting nodes, and the nodes were matched along it:
+    /// then only texts can have changed.
+    pub isomorphic: bool,
+
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    /// Whether nothing changed, apart from where things are drawn.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// How many links go between each pair of nodes, with None as the source of start links;
+/// None if a link points at a node that does not exist.
+fn link_counts(fsm: &StateMachine) -> Option<HashMap<(Option<usize>, usize), usize>> {
+    let mut counts = HashMap::new();
+    for link in fsm.links.iter() {
+        let (a, b) = link.get_nodes();
+        if a.is_some_and(|a| a >= fsm.nodes.len()) || b >= fsm.nodes.len() {
+            return None;
+        }
+        *counts.entry((a, b)).or_default() += 1;
+    }
+    Some(counts)
+}
+
+fn distance_squared(old: &StateMachine, new: &StateMachine, (i, j): (usize, usize)) -> i64 {
+    let (dx, dy) = (
+        (old.nodes[i].x - new.nodes[j].x) as i64,
+        (old.nodes[i].y - new.nodes[j].y) as i64,
+    );
+    dx * dx + dy * dy
+}
+
+/// The backtracking search for a matching of nodes that keeps every link.
+struct Isomorphism<'a> {
+    old_counts: &'a HashMap<(Option<usize>, usize), usize>,
+    new_counts: &'a HashMap<(Option<usize>, usize), usize>,
+    /// The old nodes in the order they are matched in.
+    order: Vec<usize>,
+    /// For every old node, the new nodes it may become, the most likely first.
+    candidates: Vec<Vec<usize>>,
+    mapping: Vec<Option<usize>>,
+    used: Vec<bool>,
+    steps: usize,
+}
+
+impl Isomorphism<'_> {
+    fn count(counts: &HashMap<(Option<usize>, usize), usize>, a: Option<usize>, b: usize) -> usize {
+        counts.get(&(a, b)).copied().unwrap_or(0)
+    }
+
+    /// Whether the old node can become the new one, given the nodes already matched.
+    fn fits(&self, old: usize, new: usize) -> bool {
+        if Self::count(self.old_counts, None, old) != Self::count(self.new_counts, None, new)
+            || Self::count(self.old_counts, Some(old), old)
+                != Self::count(self.new_counts, Some(new), new)
+        {
+            return false;
+        }
+        self.mapping.iter().enumerate().all(|(other, image)| {
+            let Some(image) = image else {
+                return true;
+            };
+            Self::count(self.old_counts, Some(old), other)
+                == Self::count(self.new_counts, Some(new), *image)
+                && Self::count(self.old_counts, Some(other), old)
+                    == Self::count(self.new_counts, Some(*image), new)
+        })
+    }
+
+    /// Match the old nodes from this place in the order on; false if there is no way, or the search took too long.
+    fn extend(&mut self, depth: usize) -> bool {
+        let Some(&old) = self.order.get(depth) else {
+            return true;
+        };
+        for i in 0..self.candidates[old].len() {
+            let new = self.candidates[old][i];
+            self.steps += 1;
+            if self.steps > MAX_ISOMORPHISM_STEPS {
+                return false;
+            }
+            if self.used[new] || !self.fits(old, new) {
+                continue;
+            }
+            self.mapping[old] = Some(new);
+            self.used[new] = true;
+            if self.extend(depth + 1) {
+                return true;
+            }
+            self.mapping[old] = None;
+            self.used[new] = false;
+        }
+        false
+    }
+}
+
+/// Match the nodes so that every link is kept, preferring nodes with the same text and place.
+fn match_by_isomorphism(old: &StateMachine, new: &StateMachine) -> Option<Vec<Option<usize>>> {
+    if old.nodes.len() != new.nodes.len() || old.links.len() != new.links.len() {
+        return None;
+    }
+    let (old_counts, new_counts) = (link_counts(old)?, link_counts(new)?);
+    let degrees = |counts: &HashMap<(Option<usize>, usize), usize>, count: usize| {
+        let mut degrees = vec![(0, 0); count];
+        for ((a, b), n) in counts.iter() {
+            if let Some(a) = a {
+                degrees[*a].0 += n;
+            }
+            degrees[*b].1 += n;
+        }
+        degrees
+    };
+    let old_degrees = degrees(&old_counts, old.nodes.len());
+    let new_degrees = degrees(&new_counts, new.nodes.len());
+
+    let candidates = (0..old.nodes.len())
+        .map(|i| {
+            let mut candidates: Vec<usize> = (0..new.nodes.len())
+                .filter(|j| {
+                    old.nodes[i].accept_state == new.nodes[*j].accept_state
+                        && old_degrees[i] == new_degrees[*j]
+                })
+                .collect();
+            candidates.sort_by_key(|j| {
+                (
+                    old.nodes[i].text != new.nodes[*j].text,
+                    distance_squared(old, new, (i, *j)),
+                )
+            });
+            candidates
+        })
+        .collect::<Vec<_>>();
+    // The nodes with the fewest choices go first, so that dead ends show up early.
+    let mut order: Vec<usize> = (0..old.nodes.len()).collect();
+    order.sort_by_key(|i| candidates[*i].len());
+
+    let mut search = Isomorphism {
+        old_counts: &old_counts,
+        new_counts: &new_counts,
+        order,
+        candidates,
+        mapping: vec![None; old.nodes.len()],
+        used: vec![false; new.nodes.len()],
+        steps: 0,
+    };
+    search.extend(0).then_some(search.mapping)
+}
+
+/// Match the nodes with the same text, nearest first, and then the nodes that stayed close to where they were.
+fn match_by_label_and_position(old: &StateMachine, new: &StateMachine) -> Vec<Option<usize>> {
+    let mut pairs = vec![];
+    for (i, a) in old.nodes.iter().enumerate() {
+        for (j, b) in new.nodes.iter().enumerate() {
+            let distance = distance_squared(old, new, (i, j));
+            if !a.text.is_empty() && a.text == b.text {
+                pairs.push((false, distance, i, j));
+            } else if distance <= (MAX_MOVE as i64).pow(2) {
+                pairs.push((true, distance, i, j));
+            }
+        }
+    }
+    pairs.sort();
+
+    let mut mapping = vec![None; old.nodes.len()];
+    let mut used = vec![false; new.nodes.len()];
+    for (_, _, i, j) in pairs {
+        if mapping[i].is_none() && !used[j] {
+            mapping[i] = Some(j);
+            used[j] = true;
+        }
+    }
+    mapping
+}
+
+impl StateMachine {
+    /// Find what changed between this machine and a newer version of it.
+    ///
+    /// Nodes are matched along the links if the two machines have the same shape; otherwise,
+    /// by their text, and then by where they are.
+    /// Links are matched if they join matched nodes, with the same text if possible.
+    /// Moving nodes and bending links are not changes.
+    pub fn diff(&self, new: &StateMachine) -> Diff {
+        let (nodes, isomorphic) = match match_by_isomorphism(self, new) {
+            Some(nodes) => (nodes, true),
+            None => (match_by_label_and_position(self, new), false),
+        };
+
+        let mut changes = vec![];
+        let mut node_used = vec![false; new.nodes.len()];
+        for (old, image) in nodes.iter().enumerate() {
+            match image {
+                None => changes.push(Change::RemovedNode(old)),
+                Some(new_index) => {
+                    node_used[*new_index] = true;
+                    let (a, b) = (&self.nodes[old], &new.nodes[*new_index]);
+                    if a.text != b.text {
+                        changes.push(Change::RelabeledNode {
+                            old,
+                            new: *new_index,
+                        });
+                    }
+                    if a.accept_state != b.accept_state {
+                        changes.push(Change::AcceptStateChanged {
+                            old,
+                            new: *new_index,
+                        });
+                    }
+                }
+            }
+        }
+        for (new_index, used) in node_used.iter().enumerate() {
+            if !used {
+                changes.push(Change::AddedNode(new_index));
+            }
+        }
+
+        // The links of each machine by the nodes they join, in the new machine's numbering.
+        let mut old_links: HashMap<(Option<usize>, usize), Vec<usize>> = HashMap::new();
+        let mut links = vec![None; self.links.len()];
+        for (i, link) in self.links.iter().enumerate() {
+            let (a, b) = link.get_nodes();
+            let a = match a {
+                Some(a) => nodes.get(a).copied().flatten().map(Some),
+                None => Some(None),
+            };
+            if let (Some(a), Some(Some(b))) = (a, nodes.get(b)) {
+                old_links.entry((a, *b)).or_default().push(i);
+            }
+        }
+        let mut new_links: HashMap<(Option<usize>, usize), Vec<usize>> = HashMap::new();
+        for (i, link) in new.links.iter().enumerate() {
+            new_links.entry(link.get_nodes()).or_default().push(i);
+        }
+        for (ends, new_group) in new_links.iter() {
+            let Some(old_group) = old_links.get_mut(ends) else {
+                continue;
+            };
+            let mut unmatched = vec![];
+            for j in new_group {
+                let text = new.links[*j].get_text();
+                match old_group
+                    .iter()
+                    .position(|i| self.links[*i].get_text() == text)
+                {
+                    Some(k) => links[old_group.remove(k)] = Some(*j),
+                    None => unmatched.push(*j),
+                }
+            }
+            for (i, j) in old_group.iter().zip(unmatched) {
+                links[*i] = Some(j);
+            }
+        }
+
+        let mut link_used = vec![false; new.links.len()];
+        for (old, image) in links.iter().enumerate() {
+            match image {
+                None => changes.push(Change::RemovedLink(old)),
+                Some(new_index) => {
+                    link_used[*new_index] = true;
+                    if self.links[old].get_text() != new.links[*new_index].get_text() {
+                        changes.push(Change::RelabeledLink {
+                            old,
+                            new: *new_index,
+                        });
+                    }
+                }
+            }
+        }
+        for (new_index, used) in link_used.iter().enumerate() {
+            if !used {
+                changes.push(Change::AddedLink(new_index));
+            }
+        }
+
+        Diff {
+            nodes,
+            links,
+            isomorphic,
+            changes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{drawing::test_machine, fsm::StateMachine};
+
+    use super::Change;
+
+    fn line(names: &str, start: &str, accept: &str, transitions: &str) -> StateMachine {
+        StateMachine::from_formal(&format!(
+            "states: {names}\nstart: {start}\naccept: {accept}\ntransitions:\n{transitions}"
+        ))
+        .unwrap()
+    }
+
+    fn link_named(fsm: &StateMachine, text: &str) -> usize {
+        fsm.links
+            .iter()
+            .position(|link| link.get_text() == text)
+            .unwrap()
+    }
+
+    #[test]
+    fn renamed_and_renumbered_nodes_are_matched_along_the_links() {
+        let old = line("p, q, r", "p", "r", " p -a-> q\n q -b-> r\n r -c-> r");
+        let new = line("z, y, x", "x", "z", " x -a-> y\n y -b-> z\n z -d-> z");
+        let diff = old.diff(&new);
+        assert!(diff.isomorphic);
+        assert_eq!(diff.nodes, [Some(2), Some(1), Some(0)]);
+        let (c, d) = (link_named(&old, "c"), link_named(&new, "d"));
+        assert_eq!(
+            diff.changes,
+            [
+                Change::RelabeledNode { old: 0, new: 2 },
+                Change::RelabeledNode { old: 1, new: 1 },
+                Change::RelabeledNode { old: 2, new: 0 },
+                Change::RelabeledLink { old: c, new: d },
+            ]
+        );
+        assert_eq!(
+            diff.changes[0].to_string(),
+            "node 0 was renamed, and is now node 2"
+        );
+
+        let moved = old.layout(crate::layout::LayoutAlgorithm::ForceDirected);
+        assert!(old.diff(&moved).is_empty());
+    }
+
+    #[test]
+    fn added_nodes_and_links_fall_back_to_names() {
+        let old = line("p, q, r", "p", "r", " p -a-> q\n q -b-> r");
+        let new = line("p, q, r, s", "p", "s", " p -a-> q\n q -b-> r\n r -c-> s");
+        let diff = old.diff(&new);
+        assert!(!diff.isomorphic);
+        assert_eq!(diff.nodes, [Some(0), Some(1), Some(2)]);
+        assert_eq!(
+            diff.changes,
+            [
+                Change::AcceptStateChanged { old: 2, new: 2 },
+                Change::AddedNode(3),
+                Change::AddedLink(link_named(&new, "c")),
+            ]
+        );
+        let back = new.diff(&old);
+        assert!(back.changes.contains(&Change::RemovedNode(3)));
+        assert!(back
+            .changes
+            .contains(&Change::RemovedLink(link_named(&new, "c"))));
+    }
+
+    #[test]
+    fn symmetric_machines_that_differ_fall_back_in_time() {
+        // A cycle through 24 nodes against two cycles through 12, numbered so that every other node of the big
+        // cycle comes first: nothing rules out a matching until half the nodes are placed.
+        let n = 12;
+        let index = |k: usize| match k % 2 {
+            0 => k / 2,
+            _ => n + k / 2,
+        };
+        let cycle: Vec<(usize, usize, &str)> = (0..2 * n)
+            .map(|k| (index(k), index((k + 1) % (2 * n)), "a"))
+            .collect();
+        let cycles: Vec<(usize, usize, &str)> = (0..2 * n)
+            .map(|k| (k, k / n * n + (k + 1) % n, "a"))
+            .collect();
+        let (old, new) = (
+            test_machine(&[false; 24], &cycle),
+            test_machine(&[false; 24], &cycles),
+        );
+        let diff = old.diff(&new);
+        assert!(!diff.isomorphic);
+        assert_eq!(diff.nodes, (0..2 * n).map(Some).collect::<Vec<_>>());
+        assert!(!diff.is_empty());
+        assert!(old.diff(&old).isomorphic);
+    }
+}
@@ -0,0 +1,661 @@
+//! Reading and writing machines in Graphviz's DOT language.
+
+use std::collections::{BTreeMap, HashMap};
+
+use thiserror::Error;
+
+use crate::{
+    drawing::{fit_to_canvas, route_links, CENTER},
+    fsm::{Link, Node, StateMachine},
+    layout::LayoutAlgorithm,
+    stored::{ValidationError, MAX_COORDINATE},
+};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum DotError {
+    #[error("line {line}: the {what} is never closed")]
+    Unclosed { line: usize, what: &'static str },
+
+    #[error("line {line}: expected {expected}, but found `{found}`")]
+    Unexpected {
+        line: usize,
+        expected: &'static str,
+        found: String,
+    },
+
+    #[error("the text ends where {expected} was expected")]
+    UnexpectedEnd { expected: &'static str },
+
+    #[error("node {node} has the position ({x}, {y}), which is too far from the canvas")]
+    PositionTooFar { node: String, x: f64, y: f64 },
+
+    #[error("the machine is not valid: {0}")]
+    Invalid(#[from] ValidationError),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    /// A name, number or string; keywords are only recognized when not quoted.
+    Id {
+        text: String,
+        quoted: bool,
+    },
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    Equals,
+    Semicolon,
+    Comma,
+    Colon,
+    Plus,
+    /// `->` or `--`: both are read as going from left to right.
+    EdgeOp,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Id { text, .. } => text.clone(),
+            Token::OpenBrace => "{".into(),
+            Token::CloseBrace => "}".into(),
+            Token::OpenBracket => "[".into(),
+            Token::CloseBracket => "]".into(),
+            Token::Equals => "=".into(),
+            Token::Semicolon => ";".into(),
+            Token::Comma => ",".into(),
+            Token::Colon => ":".into(),
+            Token::Plus => "+".into(),
+            Token::EdgeOp => "->".into(),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Id { text, quoted: false } if text.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || !c.is_ascii()
+}
+
+/// Split the text into tokens, each with the line it starts on, dropping comments.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, DotError> {
+    let mut tokens: Vec<(Token, usize)> = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut at_line_start = true;
+    while let Some(c) = chars.next() {
+        let start_line = line;
+        let starts_line = at_line_start;
+        at_line_start = c == '\n' || (at_line_start && c.is_whitespace());
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            // Lines starting with `#` are preprocessor output, and lines starting with `//` are comments.
+            '#' if starts_line => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(DotError::Unclosed {
+                                line: start_line,
+                                what: "comment",
+                            })
+                        }
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                    }
+                }
+                continue;
+            }
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '=' => Token::Equals,
+            ';' => Token::Semicolon,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '+' => Token::Plus,
+            '-' if matches!(chars.peek(), Some('>') | Some('-')) => {
+                chars.next();
+                Token::EdgeOp
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(DotError::Unclosed {
+                                line: start_line,
+                                what: "string",
+                            })
+                        }
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('"') => text.push('"'),
+                            Some('\\') => text.push('\\'),
+                            // A backslash before a line break continues the string on the next line.
+                            Some('\n') => line += 1,
+                            // Other escapes, like `\n` or `\N`, mean something in labels, so they are kept.
+                            Some(c) => {
+                                text.push('\\');
+                                text.push(c);
+                            }
+                            None => {}
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            text.push(c);
+                        }
+                    }
+                }
+                Token::Id { text, quoted: true }
+            }
+            '<' => {
+                // An HTML-like string: its text is kept as is.
+                let mut text = String::new();
+                let mut depth = 1;
+                loop {
+                    let c = chars.next().ok_or(DotError::Unclosed {
+                        line: start_line,
+                        what: "HTML string",
+                    })?;
+                    match c {
+                        '<' => depth += 1,
+                        '>' if depth == 1 => break,
+                        '>' => depth -= 1,
+                        '\n' => line += 1,
+                        _ => {}
+                    }
+                    text.push(c);
+                }
+                Token::Id { text, quoted: true }
+            }
+            c if is_id_char(c) || c == '-' => {
+                let mut text = String::from(c);
+                while let Some(c) = chars.next_if(|c| is_id_char(*c)) {
+                    text.push(c);
+                }
+                Token::Id {
+                    text,
+                    quoted: false,
+                }
+            }
+            other => {
+                return Err(DotError::Unexpected {
+                    line: start_line,
+                    expected: "a name, a string or punctuation",
+                    found: other.to_string(),
+                })
+            }
+        };
+        tokens.push((token, start_line));
+    }
+
+    // Strings joined with `+` are one string.
+    let mut joined: Vec<(Token, usize)> = vec![];
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some((token, line)) = tokens.next() {
+        if token != Token::Plus {
+            joined.push((token, line));
+            continue;
+        }
+        let previous = joined.last_mut().map(|(t, _)| t);
+        let next = tokens.next_if(|(t, _)| matches!(t, Token::Id { quoted: true, .. }));
+        match (previous, next) {
+            (
+                Some(Token::Id {
+                    text: left,
+                    quoted: true,
+                }),
+                Some((Token::Id { text: right, .. }, _)),
+            ) => left.push_str(&right),
+            _ => {
+                return Err(DotError::Unexpected {
+                    line,
+                    expected: "`+` to be between two strings",
+                    found: "+".into(),
+                })
+            }
+        }
+    }
+    Ok(joined)
+}
+
+type Attributes = BTreeMap<String, String>;
+
+/// The default attributes for nodes and edges, which `node [...]` and `edge [...]` change
+/// until the end of the graph or subgraph they are in.
+#[derive(Clone, Default)]
+struct Scope {
+    node: Attributes,
+    edge: Attributes,
+}
+
+#[derive(Default)]
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    at: usize,
+    /// Every node with its name and attributes, in the order they first appear.
+    nodes: Vec<(String, Attributes)>,
+    node_indices: HashMap<String, usize>,
+    edges: Vec<(usize, usize, Attributes)>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at).map(|(t, _)| t)
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<(Token, usize), DotError> {
+        let token = self
+            .tokens
+            .get(self.at)
+            .cloned()
+            .ok_or(DotError::UnexpectedEnd { expected })?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, token: Token, expected: &'static str) -> Result<(), DotError> {
+        match self.next(expected)? {
+            (t, _) if t == token => Ok(()),
+            (t, line) => Err(DotError::Unexpected {
+                line,
+                expected,
+                found: t.describe(),
+            }),
+        }
+    }
+
+    fn id(&mut self, expected: &'static str) -> Result<String, DotError> {
+        match self.next(expected)? {
+            (Token::Id { text, .. }, _) => Ok(text),
+            (t, line) => Err(DotError::Unexpected {
+                line,
+                expected,
+                found: t.describe(),
+            }),
+        }
+    }
+
+    /// `[strict] (graph | digraph) [name] { statements }`
+    fn graph(&mut self) -> Result<(), DotError> {
+        if self.peek().is_some_and(|t| t.is_keyword("strict")) {
+            self.at += 1;
+        }
+        match self.next("`digraph` or `graph`")? {
+            (t, _) if t.is_keyword("digraph") || t.is_keyword("graph") => {}
+            (t, line) => {
+                return Err(DotError::Unexpected {
+                    line,
+                    expected: "`digraph` or `graph`",
+                    found: t.describe(),
+                })
+            }
+        }
+        if let Some(Token::Id { .. }) = self.peek() {
+            self.at += 1;
+        }
+        self.expect(Token::OpenBrace, "`{`")?;
+        self.statements(&mut Scope::default())?;
+        self.expect(Token::CloseBrace, "`}`")?;
+        match self.tokens.get(self.at) {
+            None => Ok(()),
+            Some((t, line)) => Err(DotError::Unexpected {
+                line: *line,
+                expected: "nothing after the graph",
+                found: t.describe(),
+            }),
+        }
+    }
+
+    /// Statements up to, but not including, the closing brace.
+    fn statements(&mut self, scope: &mut Scope) -> Result<(), DotError> {
+        loop {
+            let Some(token) = self.peek().cloned() else {
+                return Err(DotError::UnexpectedEnd { expected: "`}`" });
+            };
+            match token {
+                Token::CloseBrace => return Ok(()),
+                Token::Semicolon => self.at += 1,
+                t if t.is_keyword("node") || t.is_keyword("edge") || t.is_keyword("graph") => {
+                    self.at += 1;
+                    let attributes = self.attribute_lists()?;
+                    if t.is_keyword("node") {
+                        scope.node.extend(attributes);
+                    } else if t.is_keyword("edge") {
+                        scope.edge.extend(attributes);
+                    }
+                }
+                t if t.is_keyword("subgraph") || t == Token::OpenBrace => {
+                    self.subgraph(scope)?;
+                }
+                Token::Id { .. } => self.node_or_edge(scope)?,
+                t => {
+                    return Err(DotError::Unexpected {
+                        line: self.tokens[self.at].1,
+                        expected: "a statement",
+                        found: t.describe(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// `[subgraph [name]] { statements }`, with its own copy of the defaults.
+    fn subgraph(&mut self, scope: &Scope) -> Result<(), DotError> {
+        if self.peek().is_some_and(|t| t.is_keyword("subgraph")) {
+            self.at += 1;
+            if let Some(Token::Id { .. }) = self.peek() {
+                self.at += 1;
+            }
+        }
+        self.expect(Token::OpenBrace, "`{`")?;
+        self.statements(&mut scope.clone())?;
+        self.expect(Token::CloseBrace, "`}`")
+    }
+
+    /// A node's name, without the port that may follow it.
+    fn node_id(&mut self) -> Result<String, DotError> {
+        let id = self.id("a node name")?;
+        for _ in 0..2 {
+            if self.peek() == Some(&Token::Colon) {
+                self.at += 1;
+                self.id("a port")?;
+            }
+        }
+        Ok(id)
+    }
+
+    fn node(&mut self, id: String, scope: &Scope) -> usize {
+        if let Some(index) = self.node_indices.get(&id) {
+            return *index;
+        }
+        self.nodes.push((id.clone(), scope.node.clone()));
+        self.node_indices.insert(id, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// `name = value`, `name [attributes]` or `name -> name -> ... [attributes]`.
+    fn node_or_edge(&mut self, scope: &Scope) -> Result<(), DotError> {
+        let first = self.node_id()?;
+        if self.peek() == Some(&Token::Equals) {
+            // An attribute of the whole graph.
+            self.at += 1;
+            self.id("a value")?;
+            return Ok(());
+        }
+        let mut chain = vec![self.node(first, scope)];
+        while self.peek() == Some(&Token::EdgeOp) {
+            self.at += 1;
+            let id = self.node_id()?;
+            chain.push(self.node(id, scope));
+        }
+        let attributes = self.attribute_lists()?;
+        if chain.len() == 1 {
+            self.nodes[chain[0]].1.extend(attributes);
+        } else {
+            let mut edge_attributes = scope.edge.clone();
+            edge_attributes.extend(attributes);
+            for pair in chain.windows(2) {
+                self.edges.push((pair[0], pair[1], edge_attributes.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Any number of `[name = value, ...]`.
+    fn attribute_lists(&mut self) -> Result<Attributes, DotError> {
+        let mut attributes = Attributes::new();
+        while self.peek() == Some(&Token::OpenBracket) {
+            self.at += 1;
+            loop {
+                match self.peek() {
+                    Some(Token::CloseBracket) => {
+                        self.at += 1;
+                        break;
+                    }
+                    Some(Token::Comma) | Some(Token::Semicolon) => self.at += 1,
+                    _ => {
+                        let name = self.id("an attribute name or `]`")?;
+                        let value = if self.peek() == Some(&Token::Equals) {
+                            self.at += 1;
+                            self.id("an attribute value")?
+                        } else {
+                            String::from("true")
+                        };
+                        attributes.insert(name.to_lowercase(), value);
+                    }
+                }
+            }
+        }
+        Ok(attributes)
+    }
+}
+
+/// Whether a node only marks where start links come from:
+/// one that is invisible, a point, or drawn as nothing with no label.
+fn is_start_marker(id: &str, attributes: &Attributes) -> bool {
+    let shape = attributes.get("shape").map(|s| s.to_lowercase());
+    let label_empty = attributes.get("label").is_some_and(|l| l.is_empty());
+    id.is_empty()
+        || attributes
+            .get("style")
+            .is_some_and(|s| s.to_lowercase().contains("invis"))
+        || shape.as_deref() == Some("point")
+        || (matches!(shape.as_deref(), Some("none") | Some("plaintext")) && label_empty)
+}
+
+/// A `pos` attribute, `x,y` in points, possibly followed by `!`.
+fn parse_pos(pos: &str) -> Option<(f64, f64)> {
+    let (x, y) = pos.trim().trim_end_matches('!').split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+/// Quote a string for DOT, so that it reads back the same.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl StateMachine {
+    /// Write this machine as a Graphviz digraph.
+    ///
+    /// Accepting nodes are double circles, and every start link comes from an invisible node of its own.
+    /// Every node's `pos` is its place on the canvas, in points, with the y axis pointing up as in Graphviz,
+    /// so `neato -n` draws the machine as it is on the canvas.
+    pub fn to_dot(&self) -> String {
+        let height = 2 * CENTER.1;
+        let mut dot = String::from("digraph fsm {\n    rankdir=LR;\n    node [shape=circle];\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let shape = if node.accept_state {
+                ", shape=doublecircle"
+            } else {
+                ""
+            };
+            dot += &format!(
+                "    q{i} [label={}{shape}, pos=\"{},{}!\"];\n",
+                quote(&node.text),
+                node.x,
+                height - node.y
+            );
+        }
+        for (i, link) in self.links.iter().enumerate() {
+            let text = quote(link.get_text());
+            match link {
+                Link::StartLink {
+                    node,
+                    delta_x,
+                    delta_y,
+                    ..
+                } => {
+                    let (x, y) = self
+                        .nodes
+                        .get(*node)
+                        .map_or((0, 0), |n| (n.x + delta_x, height - n.y - delta_y));
+                    dot += &format!(
+                        "    start{i} [shape=point, style=invis, label=\"\", pos=\"{x},{y}!\"];\n"
+                    );
+                    dot += &format!("    start{i} -> q{node} [label={text}];\n");
+                }
+                Link::NormalLink {
+                    start_node,
+                    end_node,
+                    ..
+                } => dot += &format!("    q{start_node} -> q{end_node} [label={text}];\n"),
+                Link::SelfLink { node, .. } => {
+                    dot += &format!("    q{node} -> q{node} [label={text}];\n")
+                }
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+
+    /// Read a machine from a Graphviz graph.
+    ///
+    /// Nodes with `shape=doublecircle` (or `peripheries=2`) accept, and edges from invisible nodes,
+    /// points, or nodes drawn as nothing become start links. Edge labels become link text.
+    /// If every node has a `pos`, the machine is placed accordingly; otherwise it is laid out automatically.
+    /// Positions further than [MAX_COORDINATE] points from the origin, or that are not numbers, are an error.
+    /// Subgraphs are read as part of the graph, and the rest of the attributes are ignored.
+    pub fn from_dot(text: &str) -> Result<StateMachine, DotError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            ..Default::default()
+        };
+        parser.graph()?;
+
+        let mut indices = vec![None; parser.nodes.len()];
+        let mut fsm = StateMachine::default();
+        let mut positions = vec![];
+        for (i, (id, attributes)) in parser.nodes.iter().enumerate() {
+            if is_start_marker(id, attributes) {
+                continue;
+            }
+            indices[i] = Some(fsm.nodes.len());
+            let text = match attributes.get("label") {
+                Some(label) if label != "\\N" => label.clone(),
+                _ => id.clone(),
+            };
+            let accept_state = attributes
+                .get("shape")
+                .is_some_and(|s| s.eq_ignore_ascii_case("doublecircle"))
+                || attributes
+                    .get("peripheries")
+                    .and_then(|p| p.parse::<usize>().ok())
+                    .is_some_and(|p| p >= 2);
+            fsm.nodes.push(Node {
+                x: 0,
+                y: 0,
+                text,
+                accept_state,
+            });
+            let pos = attributes.get("pos").and_then(|p| parse_pos(p));
+            if let Some((x, y)) = pos {
+                let in_range = |c: f64| c.is_finite() && c.abs() <= MAX_COORDINATE as f64;
+                if !in_range(x) || !in_range(y) {
+                    return Err(DotError::PositionTooFar {
+                        node: id.clone(),
+                        x,
+                        y,
+                    });
+                }
+            }
+            positions.push(pos);
+        }
+        for (from, to, attributes) in parser.edges.iter() {
+            let text = attributes.get("label").cloned().unwrap_or_default();
+            match (indices[*from], indices[*to]) {
+                (None, Some(node)) => fsm.links.push(Link::StartLink {
+                    node,
+                    text,
+                    delta_x: 0,
+                    delta_y: 0,
+                }),
+                (Some(a), Some(b)) if a == b => fsm.links.push(Link::SelfLink {
+                    node: a,
+                    text,
+                    anchor_angle: 0.0,
+                }),
+                (Some(a), Some(b)) => fsm.links.push(Link::NormalLink {
+                    start_node: a,
+                    end_node: b,
+                    text,
+                    angle_adjust: 0.0,
+                    parallel_part: 0.5,
+                    perpendicular_part: 0.0,
+                }),
+                _ => log::debug!("Ignoring an edge leading to a start marker in DOT"),
+            }
+        }
+
+        let positions: Option<Vec<(f64, f64)>> = positions.into_iter().collect();
+        match positions {
+            Some(positions) => {
+                let height = (2 * CENTER.1) as f64;
+                let mut positions: Vec<(i32, i32)> = positions
+                    .into_iter()
+                    .map(|(x, y)| (x.round() as i32, (height - y).round() as i32))
+                    .collect();
+                fit_to_canvas(&mut positions);
+                for (node, (x, y)) in fsm.nodes.iter_mut().zip(positions) {
+                    node.x = x;
+                    node.y = y;
+                }
+                route_links(&mut fsm);
+            }
+            None => fsm = fsm.layout(LayoutAlgorithm::Layered),
+        }
+        fsm.validate()?;
+        Ok(fsm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsm::StateMachine;
+
+    use super::DotError;
+
+    #[test]
+    fn far_positions_are_an_error() {
+        let far =
+            StateMachine::from_dot(r#"digraph { a [pos="3e9,1!"]; b [pos="-3e9,1!"]; a -> b }"#);
+        assert!(
+            matches!(far, Err(DotError::PositionTooFar { .. })),
+            "{far:?}"
+        );
+        let nan = StateMachine::from_dot(r#"digraph { a [pos="NaN,1"]; }"#);
+        assert!(
+            matches!(nan, Err(DotError::PositionTooFar { .. })),
+            "{nan:?}"
+        );
+
+        let near =
+            StateMachine::from_dot(r#"digraph { a [pos="-9000,1!"]; b [pos="9000,1!"]; a -> b }"#)
+                .unwrap();
+        assert_eq!(near.validate(), Ok(()));
+    }
+}
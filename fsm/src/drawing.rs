@@ -0,0 +1,357 @@
+//! Helpers for turning machines built in code into something that looks reasonable on the canvas.
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use petgraph::{algo::tarjan_scc, Graph};
+
+use crate::{
+    fsm::{Link, Node, StateMachine},
+    label::Label,
+};
+
+pub(crate) const CENTER: (i32, i32) = (400, 300);
+
+/// Place nodes evenly on a circle in the middle of the canvas, with the given node on the left.
+pub(crate) fn circle_positions(count: usize, first: usize) -> Vec<(i32, i32)> {
+    if count == 1 {
+        return vec![CENTER];
+    }
+    let n = count as f64;
+    let radius = (45.0 / (PI / n).sin()).clamp(100.0, 220.0);
+    (0..count)
+        .map(|i| {
+            let place = (i + count - first) % count;
+            let angle = PI + 2.0 * PI * place as f64 / n;
+            (
+                CENTER.0 + (radius * angle.cos()).round() as i32,
+                CENTER.1 + (radius * angle.sin()).round() as i32,
+            )
+        })
+        .collect()
+}
+
+/// Place nodes in columns by their distance from the start node, left to right.
+///
+/// Nodes that cannot be reached from the start are put in the last column.
+pub(crate) fn column_positions(
+    count: usize,
+    start: usize,
+    edges: &[(usize, usize)],
+) -> Vec<(i32, i32)> {
+    let mut column = vec![usize::MAX; count];
+    column[start] = 0;
+    let mut queue = std::collections::VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for (a, b) in edges.iter() {
+            if *a == node && column[*b] == usize::MAX {
+                column[*b] = column[node] + 1;
+                queue.push_back(*b);
+            }
+        }
+    }
+    let last = column.iter().filter(|c| **c != usize::MAX).max().copied();
+    let unreachable_column = last.map_or(0, |c| c + 1);
+    for c in column.iter_mut() {
+        if *c == usize::MAX {
+            *c = unreachable_column;
+        }
+    }
+
+    let columns = column.iter().max().map_or(1, |c| c + 1);
+    let mut rows = vec![0usize; columns];
+    for c in column.iter() {
+        rows[*c] += 1;
+    }
+    let dx = (680.0 / columns.max(2) as f64).min(140.0);
+    let mut placed = vec![0usize; columns];
+    column
+        .iter()
+        .map(|c| {
+            let row = placed[*c];
+            placed[*c] += 1;
+            let dy = (500.0 / rows[*c] as f64).min(120.0);
+            let y = CENTER.1 as f64 + (row as f64 - (rows[*c] as f64 - 1.0) / 2.0) * dy;
+            ((60.0 + *c as f64 * dx).round() as i32, y.round() as i32)
+        })
+        .collect()
+}
+
+/// Scale and move the positions so that they all fit on the canvas, keeping their shape.
+///
+/// Positions that already fit are left alone.
+pub(crate) fn fit_to_canvas(positions: &mut [(i32, i32)]) {
+    const MARGIN: i32 = 50;
+    let (Some(min_x), Some(max_x)) = (
+        positions.iter().map(|p| p.0).min(),
+        positions.iter().map(|p| p.0).max(),
+    ) else {
+        return;
+    };
+    let min_y = positions.iter().map(|p| p.1).min().unwrap();
+    let max_y = positions.iter().map(|p| p.1).max().unwrap();
+    if min_x >= MARGIN
+        && min_y >= MARGIN
+        && max_x <= 2 * CENTER.0 - MARGIN
+        && max_y <= 2 * CENTER.1 - MARGIN
+    {
+        return;
+    }
+
+    // Positions may come from imported files, so the spans are taken in `i64` to avoid overflowing.
+    let width = (max_x as i64 - min_x as i64).max(1) as f64;
+    let height = (max_y as i64 - min_y as i64).max(1) as f64;
+    let scale = ((2 * (CENTER.0 - MARGIN)) as f64 / width)
+        .min((2 * (CENTER.1 - MARGIN)) as f64 / height)
+        .min(1.0);
+    let middle = (
+        (min_x as f64 + max_x as f64) / 2.0,
+        (min_y as f64 + max_y as f64) / 2.0,
+    );
+    for p in positions.iter_mut() {
+        *p = (
+            CENTER.0 + ((p.0 as f64 - middle.0) * scale).round() as i32,
+            CENTER.1 + ((p.1 as f64 - middle.1) * scale).round() as i32,
+        );
+    }
+}
+
+/// For every node, the node it should be merged into so that no loop of epsilon edges remains.
+///
+/// An edge counts as epsilon if its label can read the empty word, like `\e` or `a,\e`.
+/// Nodes on a loop of such edges accept the same words, so merging them keeps the language.
+/// Every loop is merged into its lowest-numbered node; see [loop_text] for the edges that become self-loops.
+pub(crate) fn epsilon_loop_representatives(
+    count: usize,
+    edges: &[(usize, usize, String)],
+) -> Vec<usize> {
+    let mut graph = Graph::<(), ()>::new();
+    let nodes: Vec<_> = (0..count).map(|_| graph.add_node(())).collect();
+    for (a, b, text) in edges.iter() {
+        if Label::parse_or_literal(text).is_nullable() {
+            graph.add_edge(nodes[*a], nodes[*b], ());
+        }
+    }
+    let mut representative: Vec<usize> = (0..count).collect();
+    for component in tarjan_scc(&graph) {
+        let min = component.iter().map(|n| n.index()).min().unwrap();
+        for n in component {
+            representative[n.index()] = min;
+        }
+    }
+    representative
+}
+
+/// The text an edge keeps when merging turns it into a self-loop, or `None` if it should be dropped.
+///
+/// Reading the empty word in a loop does nothing, so those alternatives are removed.
+pub(crate) fn loop_text(text: &str) -> Option<String> {
+    let mut label = Label::parse_or_literal(text);
+    if !label.is_nullable() {
+        return Some(text.to_string());
+    }
+    label.alternatives.retain(|a| !a.is_empty());
+    if label.alternatives.is_empty() {
+        None
+    } else {
+        Some(label.to_string())
+    }
+}
+
+/// Distance from a point to a segment.
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    if length_sq == 0.0 {
+        return ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt();
+    }
+    let t = (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0);
+    let (x, y) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - x).powi(2) + (p.1 - y).powi(2)).sqrt()
+}
+
+/// Build a [StateMachine] out of nodes at the given positions and a list of labeled edges.
+///
+/// The links are laid out with [route_links].
+pub(crate) fn assemble(
+    positions: &[(i32, i32)],
+    names: Vec<String>,
+    accept: &[bool],
+    starts: Vec<(usize, String)>,
+    edges: Vec<(usize, usize, String)>,
+) -> StateMachine {
+    let nodes = names
+        .into_iter()
+        .zip(positions.iter())
+        .zip(accept.iter())
+        .map(|((text, (x, y)), accept)| Node {
+            x: *x,
+            y: *y,
+            text,
+            accept_state: *accept,
+        })
+        .collect();
+    let mut fsm = StateMachine {
+        nodes,
+        links: vec![],
+    };
+
+    let mut start_groups: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (node, text) in starts {
+        start_groups.entry(node).or_default().push(text);
+    }
+    for (node, texts) in start_groups {
+        for text in texts {
+            fsm.links.push(Link::StartLink {
+                node,
+                text,
+                delta_x: 0,
+                delta_y: 0,
+            });
+        }
+    }
+
+    let mut groups: BTreeMap<(usize, usize), Vec<String>> = BTreeMap::new();
+    for (a, b, text) in edges.into_iter() {
+        groups.entry((a, b)).or_default().push(text);
+    }
+    for ((from, to), texts) in groups {
+        for text in texts {
+            fsm.links.push(if from == to {
+                Link::SelfLink {
+                    node: from,
+                    text,
+                    anchor_angle: 0.0,
+                }
+            } else {
+                Link::NormalLink {
+                    start_node: from,
+                    end_node: to,
+                    text,
+                    angle_adjust: 0.0,
+                    parallel_part: 0.5,
+                    perpendicular_part: 0.0,
+                }
+            });
+        }
+    }
+
+    route_links(&mut fsm);
+    fsm
+}
+
+/// A machine for tests: nodes on a circle with no names, a start link to node 0, and these accepting nodes and links.
+#[cfg(test)]
+pub(crate) fn test_machine(accept: &[bool], links: &[(usize, usize, &str)]) -> StateMachine {
+    assemble(
+        &circle_positions(accept.len(), 0),
+        vec![String::new(); accept.len()],
+        accept,
+        vec![(0, String::new())],
+        links
+            .iter()
+            .map(|(a, b, text)| (*a, *b, text.to_string()))
+            .collect(),
+    )
+}
+
+/// Lay out the links of a machine whose nodes are already placed, keeping their order and text.
+///
+/// Parallel links are fanned out, links in both directions are curved apart,
+/// straight links that would cross another node are bent around it,
+/// and self links and start links point away from the node's neighbours.
+/// Links pointing at nodes that do not exist are left alone.
+pub(crate) fn route_links(fsm: &mut StateMachine) {
+    let positions: Vec<(f64, f64)> = fsm.nodes.iter().map(|n| (n.x as f64, n.y as f64)).collect();
+    let pos = |i: usize| positions[i];
+    let count = positions.len();
+    let edges: Vec<(usize, usize)> = fsm
+        .links
+        .iter()
+        .filter_map(|l| match l.get_nodes() {
+            (Some(a), b) if a < count && b < count => Some((a, b)),
+            _ => None,
+        })
+        .collect();
+
+    // The direction pointing away from the node's neighbours.
+    let away = |node: usize| {
+        let (x, y) = pos(node);
+        let (mut sx, mut sy) = (0.0, 0.0);
+        for (a, b) in edges.iter() {
+            let other = if *a == node && *b != node {
+                *b
+            } else if *b == node && *a != node {
+                *a
+            } else {
+                continue;
+            };
+            let (ox, oy) = pos(other);
+            let length = ((ox - x).powi(2) + (oy - y).powi(2)).sqrt().max(1.0);
+            sx += (ox - x) / length;
+            sy += (oy - y) / length;
+        }
+        if sx.abs() < 1e-6 && sy.abs() < 1e-6 {
+            None
+        } else {
+            Some(f64::atan2(-sy, -sx))
+        }
+    };
+
+    // Every link's place among the links with the same ends, and how many of those there are.
+    let mut groups: BTreeMap<(Option<usize>, usize), Vec<usize>> = BTreeMap::new();
+    for (i, link) in fsm.links.iter().enumerate() {
+        groups.entry(link.get_nodes()).or_default().push(i);
+    }
+    let mut place = vec![(0usize, 1usize); fsm.links.len()];
+    for members in groups.values() {
+        for (i, link) in members.iter().enumerate() {
+            place[*link] = (i, members.len());
+        }
+    }
+
+    for (link, (i, group_size)) in fsm.links.iter_mut().zip(place) {
+        let (i, group_size) = (i as f64, group_size as f64);
+        let fan = (i - (group_size - 1.0) / 2.0) * 0.7;
+        match link {
+            Link::StartLink {
+                node,
+                delta_x,
+                delta_y,
+                ..
+            } if *node < count => {
+                let angle = away(*node).unwrap_or(PI) + fan;
+                *delta_x = (70.0 * angle.cos()).round() as i32;
+                *delta_y = (70.0 * angle.sin()).round() as i32;
+            }
+            Link::SelfLink {
+                node, anchor_angle, ..
+            } if *node < count => {
+                *anchor_angle = away(*node).unwrap_or(-PI / 2.0) + fan;
+            }
+            Link::NormalLink {
+                start_node,
+                end_node,
+                angle_adjust,
+                parallel_part,
+                perpendicular_part,
+                ..
+            } if *start_node < count && *end_node < count => {
+                let (from, to) = (*start_node, *end_node);
+                let has_reverse = groups.contains_key(&(Some(to), from));
+                let blocked = (0..count)
+                    .filter(|n| *n != from && *n != to)
+                    .any(|n| distance_to_segment(pos(n), pos(from), pos(to)) < 40.0);
+                *perpendicular_part = if has_reverse || blocked {
+                    let base = if blocked { 60.0 } else { 25.0 };
+                    base + i * 25.0
+                } else {
+                    (i - (group_size - 1.0) / 2.0) * 25.0
+                };
+                *angle_adjust = 0.0;
+                *parallel_part = 0.5;
+            }
+            _ => {}
+        }
+    }
+}
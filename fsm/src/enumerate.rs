@@ -0,0 +1,168 @@
+use crate::{
+    dfa::Dfa,
+    fsm::{FSMError, StateMachine},
+};
+
+/// Iterator over the words an automaton accepts, in shortlex order:
+/// shorter words first, and words of the same length alphabetically.
+///
+/// Created by [StateMachine::accepted_words] or [Dfa::accepted_words].
+#[derive(Clone, Debug)]
+pub struct AcceptedWords {
+    dfa: Dfa,
+    max_length: usize,
+    max_count: usize,
+    count: usize,
+    /// For every `k` computed so far, for every state: whether some word of exactly length `k` is accepted from it.
+    reach: Vec<Vec<bool>>,
+    /// The length of the words currently being listed.
+    length: usize,
+    /// Whether the search for words of the current length has begun.
+    started: bool,
+    /// The depth-first search: (state, the next alphabet index to try from it).
+    stack: Vec<(usize, usize)>,
+    word: Vec<char>,
+}
+
+impl AcceptedWords {
+    fn new(dfa: Dfa, max_length: usize, max_count: usize) -> Self {
+        let reach = vec![dfa.accept.clone()];
+        AcceptedWords {
+            dfa,
+            max_length,
+            max_count,
+            count: 0,
+            reach,
+            length: 0,
+            started: false,
+            stack: vec![],
+            word: vec![],
+        }
+    }
+
+    /// Make sure `reach` is known up to `length`.
+    fn extend_reach(&mut self, length: usize) {
+        while self.reach.len() <= length {
+            let previous = self.reach.last().unwrap();
+            let next = self
+                .dfa
+                .transitions
+                .iter()
+                .map(|row| row.iter().flatten().any(|t| previous[*t]))
+                .collect();
+            self.reach.push(next);
+        }
+    }
+}
+
+impl Iterator for AcceptedWords {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if self.count >= self.max_count || self.length > self.max_length {
+                return None;
+            }
+
+            if !self.started {
+                self.extend_reach(self.length);
+                let reach = &self.reach[self.length];
+                // Once no state accepts a word of some length, no longer words are accepted either.
+                if !reach.iter().any(|r| *r) {
+                    // Make the bounds check above end the iteration from now on.
+                    self.max_length = 0;
+                    self.length = 1;
+                    return None;
+                }
+                if !reach[self.dfa.start] {
+                    self.length += 1;
+                    continue;
+                }
+                self.started = true;
+                self.stack.push((self.dfa.start, 0));
+            }
+
+            while let Some((state, next)) = self.stack.last().copied() {
+                let remaining = self.length - self.word.len();
+                if remaining == 0 {
+                    self.stack.pop();
+                    let word = self.word.iter().collect();
+                    self.word.pop();
+                    self.count += 1;
+                    return Some(word);
+                }
+
+                let reach = &self.reach[remaining - 1];
+                let row = &self.dfa.transitions[state];
+                let found = (next..row.len()).find(|ci| row[*ci].is_some_and(|t| reach[t]));
+                match found {
+                    Some(ci) => {
+                        self.stack.last_mut().unwrap().1 = ci + 1;
+                        self.word.push(self.dfa.alphabet[ci]);
+                        self.stack.push((row[ci].unwrap(), 0));
+                    }
+                    None => {
+                        self.stack.pop();
+                        self.word.pop();
+                    }
+                }
+            }
+
+            self.started = false;
+            self.length += 1;
+        }
+    }
+}
+
+impl Dfa {
+    /// List the accepted words in shortlex order,
+    /// stopping after `max_count` words or once words get longer than `max_length`.
+    pub fn accepted_words(&self, max_length: usize, max_count: usize) -> AcceptedWords {
+        AcceptedWords::new(self.clone(), max_length, max_count)
+    }
+}
+
+impl StateMachine {
+    /// List the accepted words in shortlex order,
+    /// stopping after `max_count` words or once words get longer than `max_length`.
+    ///
+    /// Pass `usize::MAX` for no limit; the iterator still ends when the language is finite.
+    pub fn accepted_words(
+        &self,
+        max_length: usize,
+        max_count: usize,
+    ) -> Result<AcceptedWords, FSMError> {
+        Ok(self.to_dfa()?.accepted_words(max_length, max_count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::regex::Regex;
+
+    fn words(regex: &str, max_length: usize, max_count: usize) -> Vec<String> {
+        Regex::parse(regex)
+            .unwrap()
+            .to_state_machine()
+            .accepted_words(max_length, max_count)
+            .unwrap()
+            .collect()
+    }
+
+    #[test]
+    fn lists_words_in_shortlex_order() {
+        assert_eq!(
+            words("(a|b)*abb", 5, 6),
+            ["abb", "aabb", "babb", "aaabb", "ababb", "baabb"]
+        );
+        assert_eq!(words("b|ab*", 3, usize::MAX), ["a", "b", "ab", "abb"]);
+    }
+
+    #[test]
+    fn stops_at_the_bounds_or_the_last_word() {
+        assert_eq!(words("a*", 3, usize::MAX), ["", "a", "aa", "aaa"]);
+        assert_eq!(words("a*", usize::MAX, 2), ["", "a"]);
+        assert_eq!(words("ab|c", usize::MAX, usize::MAX), ["c", "ab"]);
+        assert!(words("∅", usize::MAX, usize::MAX).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::{
+    dfa::Dfa,
+    fsm::{FSMError, FSMOutput, StateMachine},
+};
+
+impl Dfa {
+    /// Find the shortest word on which the two automata disagree, by searching their product automaton.
+    ///
+    /// Among words of the same length, the alphabetically first one is returned,
+    /// together with this automaton's output for it.
+    /// If there is no such word, the automata accept the same language.
+    pub fn distinguishing_word(&self, other: &Dfa) -> Option<(String, FSMOutput)> {
+        let alphabet: Vec<char> = self
+            .alphabet
+            .iter()
+            .chain(other.alphabet.iter())
+            .copied()
+            .collect::<BTreeSet<char>>()
+            .into_iter()
+            .collect();
+        self.distinguishing_word_over(other, &alphabet)
+    }
+
+    /// Like [Dfa::distinguishing_word], but only for words made of these characters, which must be sorted.
+    pub fn distinguishing_word_over(
+        &self,
+        other: &Dfa,
+        alphabet: &[char],
+    ) -> Option<(String, FSMOutput)> {
+        let accepts = |dfa: &Dfa, state: Option<usize>| state.is_some_and(|s| dfa.accept[s]);
+
+        // For every visited pair: the pair it was reached from, and the character used.
+        type Pair = (Option<usize>, Option<usize>);
+        let start: Pair = (Some(self.start), Some(other.start));
+        let mut parents: BTreeMap<Pair, Option<(Pair, char)>> = BTreeMap::from([(start, None)]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(pair) = queue.pop_front() {
+            let mine = accepts(self, pair.0);
+            if mine != accepts(other, pair.1) {
+                let mut word = vec![];
+                let mut current = pair;
+                while let Some(Some((parent, c))) = parents.get(&current) {
+                    word.push(*c);
+                    current = *parent;
+                }
+                let output = if mine {
+                    FSMOutput::Accept
+                } else {
+                    FSMOutput::Reject
+                };
+                return Some((word.into_iter().rev().collect(), output));
+            }
+
+            for c in alphabet.iter() {
+                let next = (
+                    pair.0.and_then(|s| self.step(s, *c)),
+                    pair.1.and_then(|s| other.step(s, *c)),
+                );
+                // Once both sides are stuck, they will never disagree.
+                if next == (None, None) || parents.contains_key(&next) {
+                    continue;
+                }
+                parents.insert(next, Some((pair, *c)));
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+}
+
+impl StateMachine {
+    /// Decide exactly whether this machine accepts the same words as the model machine.
+    ///
+    /// If it does not, returns the shortest word where they differ, and the model's output for it.
+    /// Characters that neither machine mentions all behave alike, so one of them stands for the rest.
+    pub fn distinguishing_word(
+        &self,
+        model: &StateMachine,
+    ) -> Result<Option<(String, FSMOutput)>, FSMError> {
+        let mine = self.to_dfa_over(&model.label_alphabet())?;
+        let model = model.to_dfa_over(&self.label_alphabet())?;
+        Ok(model.distinguishing_word(&mine))
+    }
+
+    /// Like [StateMachine::distinguishing_word], but only for words made of these characters, which must be sorted.
+    ///
+    /// This is for comparing machines on the words a task can ask about:
+    /// they may disagree on other characters, like the ones a `.` stands for, without either being wrong.
+    pub fn distinguishing_word_over(
+        &self,
+        model: &StateMachine,
+        alphabet: &[char],
+    ) -> Result<Option<(String, FSMOutput)>, FSMError> {
+        let mine = self.to_dfa_over(alphabet)?;
+        let model = model.to_dfa_over(alphabet)?;
+        Ok(model.distinguishing_word_over(&mine, alphabet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fsm::{FSMOutput, StateMachine},
+        regex::Regex,
+        tester::{FSMTester, FSMTestingOutput},
+    };
+
+    fn compile(regex: &str) -> StateMachine {
+        Regex::parse(regex).unwrap().to_state_machine()
+    }
+
+    /// Words of `a` and `b` that end in `b`.
+    const ENDS_IN_B: &str = r#"
+        fn gen_word(ok) {
+            let word = "";
+            let n = rng.gen_range(0, 6);
+            for i in 0..n { word += if rng.gen_range(0, 2) == 0 { "a" } else { "b" }; }
+            word + if ok { "b" } else { "a" }
+        }
+        fn check_word(word) { word.ends_with("b") }
+    "#;
+
+    #[test]
+    fn only_the_given_characters_are_compared() {
+        let model = compile("(a|b)*b");
+        let wildcard = compile(".*b");
+        assert!(wildcard.distinguishing_word(&model).unwrap().is_some());
+        assert_eq!(
+            wildcard.distinguishing_word_over(&model, &['a', 'b']),
+            Ok(None)
+        );
+        assert_eq!(
+            compile("a*b").distinguishing_word_over(&model, &['a', 'b']),
+            Ok(Some(("bb".to_string(), FSMOutput::Accept)))
+        );
+    }
+
+    #[test]
+    fn tasks_compare_over_the_characters_they_ask_about() {
+        let model = compile("(a|b)*b");
+        let mut tester = FSMTester::new(compile(".*b"), ENDS_IN_B).unwrap();
+        assert!(matches!(
+            tester.run_equivalence(&model, 0).unwrap(),
+            Some(FSMTestingOutput::Equivalent)
+        ));
+
+        let mut tester = FSMTester::new(compile("(a|b)*"), ENDS_IN_B).unwrap();
+        assert!(matches!(
+            tester.run_equivalence(&model, 0).unwrap(),
+            Some(FSMTestingOutput::Counterexample {
+                expected_result: FSMOutput::Reject,
+                expected_count: 0,
+                actual_count: 1,
+                ..
+            })
+        ));
+    }
+}
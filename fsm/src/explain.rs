@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compiled::CompiledMachine,
+    fsm::{FSMError, FSMOutput, StateMachine},
+};
+
+/// Why a machine gave its answer for a word.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Explanation {
+    /// The word is accepted along this path: for every link taken, its index and the text it consumed.
+    ///
+    /// The path uses as few links as possible.
+    Accepted { path: Vec<(usize, String)> },
+
+    /// No path accepts the word.
+    Rejected {
+        /// The longest beginning of the word that any path managed to read.
+        longest_prefix: String,
+
+        /// Whether that is the whole word.
+        read_whole_word: bool,
+
+        /// The nodes where the paths that read that much got stuck.
+        ///
+        /// If the whole word was read, these are the nodes it ended in, none of which accept.
+        stuck_nodes: Vec<usize>,
+    },
+}
+
+impl Explanation {
+    pub fn output(&self) -> FSMOutput {
+        match self {
+            Explanation::Accepted { .. } => FSMOutput::Accept,
+            Explanation::Rejected { .. } => FSMOutput::Reject,
+        }
+    }
+}
+
+impl CompiledMachine {
+    /// Run the word through the machine like [CompiledMachine::evaluate], and explain the answer.
+    pub fn explain(&self, word: &str) -> Explanation {
+        let positions = word.len() + 1;
+        // For every visited (node, position): the pair it was reached from, if any, and the link taken.
+        let mut parents: Vec<Option<(Option<usize>, usize)>> =
+            vec![None; self.node_count() * positions];
+        let mut queue = VecDeque::new();
+        let mut furthest = 0;
+
+        let index = |node: usize, position: usize| node * positions + position;
+        for (link, length) in self.links_matching(None, word) {
+            let i = index(self.target(link), length);
+            if parents[i].is_none() {
+                parents[i] = Some((None, link));
+                queue.push_back((self.target(link), length));
+            }
+        }
+
+        while let Some((node, position)) = queue.pop_front() {
+            furthest = furthest.max(position);
+            if position == word.len() && self.is_accepting(node) {
+                let mut path = vec![];
+                let mut current = Some(index(node, position));
+                while let Some(i) = current {
+                    let (parent, link) = parents[i].unwrap();
+                    let start = parent.map_or(0, |p| p % positions);
+                    path.push((link, word[start..i % positions].to_string()));
+                    current = parent;
+                }
+                path.reverse();
+                return Explanation::Accepted { path };
+            }
+            for (link, length) in self.links_matching(Some(node), &word[position..]) {
+                let target = (self.target(link), position + length);
+                let i = index(target.0, target.1);
+                if parents[i].is_none() {
+                    parents[i] = Some((Some(index(node, position)), link));
+                    queue.push_back(target);
+                }
+            }
+        }
+
+        let stuck_nodes: Vec<usize> = (0..self.node_count())
+            .filter(|node| parents[index(*node, furthest)].is_some())
+            .collect();
+        Explanation::Rejected {
+            longest_prefix: word[..furthest].to_string(),
+            read_whole_word: furthest == word.len() && !stuck_nodes.is_empty(),
+            stuck_nodes,
+        }
+    }
+}
+
+impl StateMachine {
+    /// Run the word through the machine, and explain why it is accepted or rejected.
+    pub fn explain(&self, word: &str) -> Result<Explanation, FSMError> {
+        if let Some(err) = self.check_error() {
+            return Err(err);
+        }
+        Ok(CompiledMachine::new(self).explain(word))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        drawing::test_machine,
+        fsm::{FSMError, StateMachine},
+    };
+
+    use super::Explanation;
+
+    /// Reads `ab` and then a digit, or `ж` and then anything.
+    fn machine() -> StateMachine {
+        test_machine(
+            &[false, false, true],
+            &[(0, 1, "ab"), (1, 2, "[0-9]"), (0, 1, "ж"), (1, 2, ".")],
+        )
+    }
+
+    fn link(fsm: &StateMachine, text: &str) -> usize {
+        fsm.links
+            .iter()
+            .position(|link| link.get_text() == text)
+            .unwrap()
+    }
+
+    #[test]
+    fn accepted_words_show_their_path() {
+        let fsm = machine();
+        assert_eq!(
+            fsm.explain("ab7"),
+            Ok(Explanation::Accepted {
+                path: vec![
+                    (0, String::new()),
+                    (link(&fsm, "ab"), "ab".into()),
+                    (link(&fsm, "[0-9]"), "7".into())
+                ]
+            })
+        );
+        let Ok(Explanation::Accepted { path }) = fsm.explain("жж") else {
+            panic!("жж should be accepted");
+        };
+        let texts: Vec<&str> = path.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(texts, ["", "ж", "ж"]);
+    }
+
+    #[test]
+    fn rejected_words_show_where_they_got_stuck() {
+        let fsm = machine();
+        assert_eq!(
+            fsm.explain("ab"),
+            Ok(Explanation::Rejected {
+                longest_prefix: "ab".into(),
+                read_whole_word: true,
+                stuck_nodes: vec![1],
+            })
+        );
+        assert_eq!(
+            fsm.explain("ж12"),
+            Ok(Explanation::Rejected {
+                longest_prefix: "ж1".into(),
+                read_whole_word: false,
+                stuck_nodes: vec![2],
+            })
+        );
+        assert_eq!(
+            fsm.explain("b"),
+            Ok(Explanation::Rejected {
+                longest_prefix: String::new(),
+                read_whole_word: false,
+                stuck_nodes: vec![0],
+            })
+        );
+    }
+
+    #[test]
+    fn broken_machines_are_not_explained() {
+        assert_eq!(
+            StateMachine::default().explain("a"),
+            Err(FSMError::NoEntryLinks)
+        );
+    }
+}
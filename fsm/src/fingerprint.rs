@@ -0,0 +1,191 @@
+//! Fingerprints that stay the same when a machine is redrawn or renumbered, for spotting copied solutions.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dfa::Dfa,
+    fsm::{FSMError, StateMachine},
+};
+
+/// How many rounds of refinement the drawing hash makes: each node's color covers everything within this many links of it.
+const REFINEMENT_ROUNDS: usize = 8;
+
+/// The 64-bit FNV-1a hash: unlike the standard library's hasher, it is the same in every build,
+/// so fingerprints can be stored and compared later.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Two fingerprints of a machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// The hash of the machine's [canonical form](StateMachine::canonical_form): equal for machines with the same language.
+    /// None if the machine has errors.
+    pub language: Option<u64>,
+
+    /// The hash of the machine's drawing as a graph: equal for machines with the same links and accepting nodes,
+    /// whatever their node names, positions and order.
+    pub drawing: u64,
+}
+
+impl Dfa {
+    /// The hash of this automaton's states, alphabet and transitions, in its own numbering.
+    ///
+    /// Only automata from [Dfa::minimize] are numbered canonically, so only their hashes can be compared.
+    pub fn canonical_hash(&self) -> u64 {
+        // Only whether there is an other character counts, not which one was picked.
+        let columns = self.column_order();
+        let written: Vec<char> = self
+            .alphabet
+            .iter()
+            .copied()
+            .filter(|c| Some(*c) != self.other)
+            .collect();
+        let mut text = format!("{written:?}|{}|{}|", self.other.is_some(), self.start);
+        for (accept, row) in self.accept.iter().zip(self.transitions.iter()) {
+            text += if *accept { "A" } else { "R" };
+            for target in columns.iter().map(|ci| row[*ci]) {
+                match target {
+                    Some(t) => text += &format!("{t},"),
+                    None => text += "-,",
+                }
+            }
+            text += ";";
+        }
+        fnv1a(text.as_bytes())
+    }
+}
+
+impl StateMachine {
+    /// The minimal deterministic automaton for this machine's language, with its states numbered canonically,
+    /// so that machines with the same language have equal canonical forms.
+    pub fn canonical_form(&self) -> Result<Dfa, FSMError> {
+        Ok(self.to_dfa()?.minimize().0)
+    }
+
+    /// A hash of the machine's drawing that does not depend on how its nodes are numbered, named or placed.
+    ///
+    /// Every node starts out colored by whether it accepts, and is then recolored again and again by its own color
+    /// and the texts and colors of the links into and out of it (Weisfeiler–Lehman refinement).
+    /// Machines drawn the same way always get equal hashes, and different ones almost always different hashes.
+    /// Links pointing at nodes that do not exist are left out.
+    pub fn drawing_hash(&self) -> u64 {
+        let count = self.nodes.len();
+        // Every link is (source, target, text), with source None for start links.
+        let links: Vec<(Option<usize>, usize, &str)> = self
+            .links
+            .iter()
+            .map(|link| {
+                let (a, b) = link.get_nodes();
+                (a, b, link.get_text())
+            })
+            .filter(|(a, b, _)| !a.is_some_and(|a| a >= count) && *b < count)
+            .collect();
+
+        let mut colors: Vec<u64> = self
+            .nodes
+            .iter()
+            .map(|node| fnv1a(if node.accept_state { b"A" } else { b"R" }))
+            .collect();
+        for _ in 0..REFINEMENT_ROUNDS {
+            colors = (0..count)
+                .map(|node| {
+                    let mut outgoing = vec![];
+                    let mut incoming = vec![];
+                    for (a, b, text) in links.iter() {
+                        if *a == Some(node) {
+                            outgoing.push(format!("{}:{text}", colors[*b]));
+                        }
+                        if *b == node {
+                            let source = a.map_or(String::from("start"), |a| colors[a].to_string());
+                            incoming.push(format!("{source}:{text}"));
+                        }
+                    }
+                    outgoing.sort();
+                    incoming.sort();
+                    let text = format!(
+                        "{}|{}|{}",
+                        colors[node],
+                        outgoing.join("\u{0}"),
+                        incoming.join("\u{0}")
+                    );
+                    fnv1a(text.as_bytes())
+                })
+                .collect();
+        }
+
+        // The machine's hash is the multiset of its nodes' colors.
+        let mut histogram: BTreeMap<u64, usize> = BTreeMap::new();
+        for color in colors {
+            *histogram.entry(color).or_default() += 1;
+        }
+        let text: Vec<String> = histogram
+            .iter()
+            .map(|(color, n)| format!("{color}x{n}"))
+            .collect();
+        fnv1a(format!("{}|{}", links.len(), text.join(",")).as_bytes())
+    }
+
+    /// Both fingerprints of this machine.
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint {
+            language: self.canonical_form().ok().map(|dfa| dfa.canonical_hash()),
+            drawing: self.drawing_hash(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsm::StateMachine;
+
+    fn formal(text: &str) -> StateMachine {
+        StateMachine::from_formal(text).unwrap()
+    }
+
+    #[test]
+    fn renamed_and_renumbered_machines_match() {
+        let a = formal(
+            "states: p, q\nstart: p\naccept: q\ntransitions:\n p -a-> q\n q -b-> q\n q -c-> p",
+        );
+        let mut b = formal(
+            "states: y, x\nstart: x\naccept: y\ntransitions:\n y -c-> x\n x -a-> y\n y -b-> y",
+        );
+        b.nodes[0].x += 150;
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert!(a.fingerprint().language.is_some());
+    }
+
+    #[test]
+    fn different_machines_differ() {
+        let a = formal("states: p, q\nstart: p\naccept: q\ntransitions:\n p -a-> q\n q -b-> q");
+        let other_language =
+            formal("states: p, q\nstart: p\naccept: q\ntransitions:\n p -a-> q\n q -c-> q");
+        assert_ne!(
+            a.fingerprint().language,
+            other_language.fingerprint().language
+        );
+        assert_ne!(a.drawing_hash(), other_language.drawing_hash());
+
+        // The same language drawn differently shares only the language fingerprint.
+        let one = formal("states: p\nstart: p\naccept: p\ntransitions:\n p -a-> p");
+        let two =
+            formal("states: p, q\nstart: p\naccept: p, q\ntransitions:\n p -a-> q\n q -a-> p");
+        assert_eq!(one.fingerprint().language, two.fingerprint().language);
+        assert_ne!(one.drawing_hash(), two.drawing_hash());
+
+        let accepting =
+            formal("states: p, q\nstart: p\naccept: p\ntransitions:\n p -a-> q\n q -b-> q");
+        assert_ne!(a.drawing_hash(), accepting.drawing_hash());
+    }
+
+    #[test]
+    fn broken_machines_have_no_language_fingerprint() {
+        let broken = formal("states: p\nstart: p\ntransitions:\n p --> p");
+        assert_eq!(broken.fingerprint().language, None);
+    }
+}
@@ -0,0 +1,623 @@
+//! A text format for machines that follows their formal definition as a 5-tuple:
+//!
+//! ```text
+//! states: q0, q1, q2
+//! alphabet: a, b
+//! start: q0
+//! accept: q2
+//! transitions:
+//!     q0 -a-> q1
+//!     q1 -b-> q2
+//!     q2 -a,b-> q2
+//! ```
+//!
+//! Every transition's label is an ordinary link label, and `q0 --> q1` is an empty link.
+//! Spaces around a label are ignored; a label can be put in double quotes, like `q0 -" a"-> q1`,
+//! to keep its spaces or to contain `-`, with `""` standing for a quote inside it.
+//! Lines starting with `#` are comments.
+
+use std::collections::{BTreeSet, HashMap};
+
+use thiserror::Error;
+
+use crate::{
+    fsm::{Link, Node, StateMachine},
+    label::{Label, LabelError},
+    layout::LayoutAlgorithm,
+};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FormalErrorKind {
+    #[error("`{0}` is not a section; use states, alphabet, start, accept or transitions")]
+    UnknownSection(String),
+
+    #[error("the {0} section is given twice")]
+    DuplicateSection(&'static str),
+
+    #[error("the {0} section is missing")]
+    MissingSection(&'static str),
+
+    #[error("expected `:` after the section name")]
+    MissingColon,
+
+    #[error("`{0}` cannot be the name of a state; names cannot be empty or contain spaces, commas or braces")]
+    InvalidName(String),
+
+    #[error("the state `{0}` is listed twice")]
+    DuplicateState(String),
+
+    #[error("there is no state `{0}` in the states section")]
+    UnknownState(String),
+
+    #[error("`{0}` is not a single symbol")]
+    NotASymbol(String),
+
+    #[error("expected a transition like `q0 -a-> q1`")]
+    NotATransition,
+
+    #[error("the quoted label is not closed")]
+    UnclosedQuote,
+
+    #[error("the label cannot be read: {0}")]
+    InvalidLabel(LabelError),
+
+    #[error("the label uses `{0}`, which is not in the alphabet")]
+    NotInAlphabet(char),
+}
+
+/// What is wrong with a machine's text, and where: lines and columns are counted from 1.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("line {line}, column {column}: {kind}")]
+pub struct FormalError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: FormalErrorKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    States,
+    Alphabet,
+    Start,
+    Accept,
+    Transitions,
+}
+
+impl Section {
+    fn parse(name: &str) -> Option<Section> {
+        match name.to_lowercase().as_str() {
+            "states" | "q" => Some(Section::States),
+            "alphabet" | "sigma" | "σ" => Some(Section::Alphabet),
+            "start" | "initial" => Some(Section::Start),
+            "accept" | "final" | "f" => Some(Section::Accept),
+            "transitions" | "delta" | "δ" => Some(Section::Transitions),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Section::States => "states",
+            Section::Alphabet => "alphabet",
+            Section::Start => "start",
+            Section::Accept => "accept",
+            Section::Transitions => "transitions",
+        }
+    }
+}
+
+/// Whether the text can be used as a state's name in this format.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, ',' | '{' | '}'))
+}
+
+/// The comma-separated items of a list, optionally in braces, each with the column it starts at.
+fn list_items(line: &str, offset: usize) -> Vec<(&str, usize)> {
+    let mut text = line;
+    let mut start = offset;
+    let trimmed = text.trim_start();
+    start += text.len() - trimmed.len();
+    text = trimmed.trim_end();
+    if let Some(inner) = text.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        text = inner;
+        start += 1;
+    }
+    if text.trim().is_empty() {
+        return vec![];
+    }
+    let mut items = vec![];
+    for item in text.split(',') {
+        let leading = item.len() - item.trim_start().len();
+        items.push((item.trim(), start + leading));
+        start += item.len() + 1;
+    }
+    items
+}
+
+/// Read a label in double quotes at the start of the text, returning it and the length of the quoted text in bytes.
+fn quoted_label(text: &str) -> Option<(String, usize)> {
+    let mut label = String::new();
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '"' && chars.next_if(|(_, c)| *c == '"').is_none() {
+            return Some((label, i + 1));
+        }
+        label.push(c);
+    }
+    None
+}
+
+/// Write a label for a transition, in quotes if it would not read back the same without them.
+fn write_label(text: &str) -> String {
+    if text != text.trim() || text.contains('-') || text.starts_with('"') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// The column, counted in characters from 1, of a byte offset into the line.
+fn column(line: &str, byte: usize) -> usize {
+    line[..byte].chars().count() + 1
+}
+
+/// The line a section starts on, and its items, each with the column it starts at.
+type SectionItems = (usize, Vec<(String, usize)>);
+
+struct Transition {
+    line: usize,
+    from: (String, usize),
+    label: (String, usize),
+    to: (String, usize),
+}
+
+impl StateMachine {
+    /// Read a machine written in the 5-tuple text format; see [the module documentation](self).
+    ///
+    /// The states and start sections must be there; the alphabet, if given, limits what the labels may use.
+    /// The machine is laid out automatically.
+    pub fn from_formal(text: &str) -> Result<StateMachine, FormalError> {
+        let error =
+            |line: usize, column: usize, kind: FormalErrorKind| FormalError { line, column, kind };
+        let mut sections: HashMap<&'static str, SectionItems> = HashMap::new();
+        let mut transitions = vec![];
+        let mut in_transitions = false;
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - line.trim_start().len();
+
+            // A transition, if it is in the transitions section and looks like one.
+            let header = line.split_once(':').and_then(|(name, _)| {
+                Section::parse(name.trim())
+                    .filter(|_| !name.trim().is_empty() && !name.contains('-'))
+            });
+            if in_transitions && header.is_none() {
+                let rest = &line[indent..];
+                let from_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let after_from = indent + from_end;
+                let arrow_start = line[after_from..]
+                    .find(|c: char| !c.is_whitespace())
+                    .map(|i| after_from + i);
+                let Some(label_start) = arrow_start
+                    .filter(|i| line[*i..].starts_with('-'))
+                    .map(|i| i + 1)
+                else {
+                    return Err(error(
+                        number,
+                        column(line, arrow_start.unwrap_or(line.len())),
+                        FormalErrorKind::NotATransition,
+                    ));
+                };
+                let (label, to_start) = if line[label_start..].starts_with('"') {
+                    let Some((label, length)) = quoted_label(&line[label_start..]) else {
+                        return Err(error(
+                            number,
+                            column(line, label_start),
+                            FormalErrorKind::UnclosedQuote,
+                        ));
+                    };
+                    let after = label_start + length;
+                    let arrow = after + line[after..].len() - line[after..].trim_start().len();
+                    if !line[arrow..].starts_with("->") {
+                        return Err(error(
+                            number,
+                            column(line, arrow),
+                            FormalErrorKind::NotATransition,
+                        ));
+                    }
+                    (label, arrow + 2)
+                } else {
+                    let Some(label_end) = line[label_start..].rfind("->").map(|i| label_start + i)
+                    else {
+                        return Err(error(
+                            number,
+                            column(line, label_start),
+                            FormalErrorKind::NotATransition,
+                        ));
+                    };
+                    (
+                        line[label_start..label_end].trim().to_string(),
+                        label_end + 2,
+                    )
+                };
+                let to = line[to_start..].trim();
+                let to_column = column(
+                    line,
+                    to_start + line[to_start..].len() - line[to_start..].trim_start().len(),
+                );
+                if to.is_empty() {
+                    return Err(error(
+                        number,
+                        column(line, line.len()),
+                        FormalErrorKind::NotATransition,
+                    ));
+                }
+                transitions.push(Transition {
+                    line: number,
+                    from: (rest[..from_end].to_string(), column(line, indent)),
+                    label: (label, column(line, label_start)),
+                    to: (to.to_string(), to_column),
+                });
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once(':') else {
+                return Err(error(
+                    number,
+                    column(line, line.len()),
+                    FormalErrorKind::MissingColon,
+                ));
+            };
+            let Some(section) = Section::parse(name.trim()) else {
+                return Err(error(
+                    number,
+                    column(line, indent),
+                    FormalErrorKind::UnknownSection(name.trim().to_string()),
+                ));
+            };
+            if sections.contains_key(section.name())
+                || (section == Section::Transitions && in_transitions)
+            {
+                return Err(error(
+                    number,
+                    column(line, indent),
+                    FormalErrorKind::DuplicateSection(section.name()),
+                ));
+            }
+            in_transitions = section == Section::Transitions;
+            if in_transitions {
+                if !value.trim().is_empty() {
+                    let at = name.len() + 1 + value.len() - value.trim_start().len();
+                    return Err(error(
+                        number,
+                        column(line, at),
+                        FormalErrorKind::NotATransition,
+                    ));
+                }
+                continue;
+            }
+            let items = list_items(value, name.len() + 1)
+                .into_iter()
+                .map(|(item, at)| (item.to_string(), column(line, at)))
+                .collect();
+            sections.insert(section.name(), (number, items));
+        }
+
+        let last_line = text.lines().count().max(1);
+        let missing =
+            |section: &'static str| error(last_line, 1, FormalErrorKind::MissingSection(section));
+
+        // States, in the order they are listed.
+        let (states_line, state_items) = sections.get("states").ok_or_else(|| missing("states"))?;
+        let mut fsm = StateMachine::default();
+        let mut indices = HashMap::new();
+        for (name, at) in state_items.iter() {
+            if !is_valid_name(name) {
+                return Err(error(
+                    *states_line,
+                    *at,
+                    FormalErrorKind::InvalidName(name.clone()),
+                ));
+            }
+            if indices.insert(name.clone(), fsm.nodes.len()).is_some() {
+                return Err(error(
+                    *states_line,
+                    *at,
+                    FormalErrorKind::DuplicateState(name.clone()),
+                ));
+            }
+            fsm.nodes.push(Node {
+                x: 0,
+                y: 0,
+                text: name.clone(),
+                accept_state: false,
+            });
+        }
+        let state = |line: usize, (name, at): &(String, usize)| {
+            indices
+                .get(name)
+                .copied()
+                .ok_or_else(|| error(line, *at, FormalErrorKind::UnknownState(name.clone())))
+        };
+
+        let alphabet = match sections.get("alphabet") {
+            None => None,
+            Some((line, items)) => {
+                let mut alphabet = BTreeSet::new();
+                for (symbol, at) in items.iter() {
+                    let mut chars = symbol.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => alphabet.insert(c),
+                        _ => {
+                            return Err(error(
+                                *line,
+                                *at,
+                                FormalErrorKind::NotASymbol(symbol.clone()),
+                            ))
+                        }
+                    };
+                }
+                Some(alphabet)
+            }
+        };
+
+        let (start_line, start_items) = sections.get("start").ok_or_else(|| missing("start"))?;
+        for item in start_items.iter() {
+            fsm.links.push(Link::StartLink {
+                node: state(*start_line, item)?,
+                text: String::new(),
+                delta_x: 0,
+                delta_y: 0,
+            });
+        }
+        if let Some((accept_line, accept_items)) = sections.get("accept") {
+            for item in accept_items.iter() {
+                let node = state(*accept_line, item)?;
+                fsm.nodes[node].accept_state = true;
+            }
+        }
+
+        for transition in transitions {
+            let from = state(transition.line, &transition.from)?;
+            let to = state(transition.line, &transition.to)?;
+            let (text, at) = transition.label;
+            let label: Label = text
+                .parse()
+                .map_err(|why| error(transition.line, at, FormalErrorKind::InvalidLabel(why)))?;
+            if let Some(alphabet) = &alphabet {
+                if let Some(c) = label
+                    .mentioned_chars()
+                    .into_iter()
+                    .find(|c| !alphabet.contains(c))
+                {
+                    return Err(error(
+                        transition.line,
+                        at,
+                        FormalErrorKind::NotInAlphabet(c),
+                    ));
+                }
+            }
+            fsm.links.push(if from == to {
+                Link::SelfLink {
+                    node: from,
+                    text,
+                    anchor_angle: 0.0,
+                }
+            } else {
+                Link::NormalLink {
+                    start_node: from,
+                    end_node: to,
+                    text,
+                    angle_adjust: 0.0,
+                    parallel_part: 0.5,
+                    perpendicular_part: 0.0,
+                }
+            });
+        }
+
+        Ok(fsm.layout(LayoutAlgorithm::Layered))
+    }
+
+    /// Write this machine in the 5-tuple text format; see [the module documentation](self).
+    ///
+    /// Nodes whose text cannot be a state's name, or is used by another node, are called `q0`, `q1`, ...
+    /// after their index instead. The alphabet is every character the labels use,
+    /// unless one of them cannot be written in a list.
+    /// The format has no text on start links, so if a start link reads something,
+    /// it is written as a transition from an extra start state.
+    pub fn to_formal(&self) -> String {
+        let mut names: Vec<String> = vec![];
+        for (i, node) in self.nodes.iter().enumerate() {
+            let taken = |name: &str| {
+                names.iter().any(|n| n == name)
+                    || self.nodes[i + 1..].iter().any(|n| n.text == name)
+            };
+            if is_valid_name(&node.text) && !taken(&node.text) {
+                names.push(node.text.clone());
+            } else {
+                let mut name = format!("q{i}");
+                while taken(&name) {
+                    name.push('\'');
+                }
+                names.push(name);
+            }
+        }
+        let name = |node: usize| {
+            names
+                .get(node)
+                .cloned()
+                .unwrap_or_else(|| format!("q{node}"))
+        };
+
+        let mut starts = vec![];
+        let mut reading_starts = vec![];
+        let mut transitions = vec![];
+        for link in self.links.iter() {
+            match link.get_nodes() {
+                (None, node) if link.get_text().is_empty() => starts.push(name(node)),
+                (None, node) => reading_starts.push((node, link.get_text())),
+                (Some(from), to) => transitions.push(format!(
+                    "    {} -{}-> {}",
+                    name(from),
+                    write_label(link.get_text()),
+                    name(to)
+                )),
+            }
+        }
+        let mut states = names.clone();
+        if !reading_starts.is_empty() {
+            let mut start = String::from("start");
+            while states.contains(&start) {
+                start.push('\'');
+            }
+            for (node, text) in reading_starts {
+                transitions.push(format!(
+                    "    {start} -{}-> {}",
+                    write_label(text),
+                    name(node)
+                ));
+            }
+            states.push(start.clone());
+            starts.push(start);
+        }
+
+        let list = |items: &[String]| items.join(", ");
+        let alphabet = self.label_alphabet();
+        let accept: Vec<String> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.accept_state)
+            .map(|(i, _)| name(i))
+            .collect();
+        let mut text = format!("states: {}\n", list(&states));
+        // Symbols that would be taken for part of the list are left for the labels to define.
+        if !alphabet
+            .iter()
+            .any(|c| c.is_whitespace() || matches!(c, ',' | '{' | '}'))
+        {
+            let alphabet: Vec<String> = alphabet.into_iter().map(String::from).collect();
+            text += &format!("alphabet: {}\n", list(&alphabet));
+        }
+        text += &format!(
+            "start: {}\naccept: {}\ntransitions:\n",
+            list(&starts),
+            list(&accept)
+        );
+        for transition in transitions {
+            text += &transition;
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{drawing::test_machine, fsm::StateMachine, label::LabelError};
+
+    use super::{FormalError, FormalErrorKind};
+
+    /// The names, accepting nodes and links of a machine, leaving out where things are drawn.
+    fn shape(fsm: &StateMachine) -> Vec<String> {
+        let nodes = fsm
+            .nodes
+            .iter()
+            .map(|node| format!("{} {}", node.text, node.accept_state));
+        let links = fsm.links.iter().map(|link| {
+            let (from, to) = link.get_nodes();
+            format!("{from:?} -{:?}-> {to}", link.get_text())
+        });
+        let mut links: Vec<String> = links.collect();
+        links.sort();
+        nodes.chain(links).collect()
+    }
+
+    #[test]
+    fn machines_read_back_the_same() {
+        let mut fsm = test_machine(
+            &[false, true, false],
+            &[
+                (0, 1, "a,b"),
+                (1, 2, " "),
+                (2, 2, " a "),
+                (2, 0, "[a-c]"),
+                (0, 2, "\"q\""),
+                (1, 0, ""),
+                (1, 1, "x->y"),
+            ],
+        );
+        for (node, name) in fsm.nodes.iter_mut().zip(["q0", "q1", "q2"]) {
+            node.text = name.to_string();
+        }
+        let text = fsm.to_formal();
+        assert_eq!(
+            shape(&StateMachine::from_formal(&text).unwrap()),
+            shape(&fsm),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn labels_can_be_quoted() {
+        let fsm = StateMachine::from_formal(
+            "states: q0\nstart: q0\ntransitions:\n    q0 -\" -\"\"\"-> q0\n    q0 - a -> q0",
+        )
+        .unwrap();
+        let texts: Vec<&str> = fsm.links.iter().map(|link| link.get_text()).collect();
+        assert_eq!(texts, ["", " -\"", "a"]);
+    }
+
+    #[test]
+    fn errors_point_at_the_line_and_column() {
+        let error = |text: &str| StateMachine::from_formal(text).unwrap_err();
+        let at =
+            |line: usize, column: usize, kind: FormalErrorKind| FormalError { line, column, kind };
+
+        assert_eq!(
+            error("states: q0, q1\nstart: q2\n"),
+            at(2, 8, FormalErrorKind::UnknownState("q2".into()))
+        );
+        assert_eq!(
+            error("states: q0\n# a comment\n  foo: x"),
+            at(3, 3, FormalErrorKind::UnknownSection("foo".into()))
+        );
+        assert_eq!(
+            error("states: q0, q0\nstart: q0"),
+            at(1, 13, FormalErrorKind::DuplicateState("q0".into()))
+        );
+        assert_eq!(
+            error("states: q0\nstart: q0\ntransitions:\n    q0 -a,,b-> q0"),
+            at(
+                4,
+                9,
+                FormalErrorKind::InvalidLabel(LabelError::EmptyAlternative)
+            )
+        );
+        // Columns count characters, not bytes.
+        assert_eq!(
+            error("states: ж0\nalphabet: a\nstart: ж0\ntransitions:\n  ж0 -b-> ж0"),
+            at(5, 7, FormalErrorKind::NotInAlphabet('b'))
+        );
+        assert_eq!(
+            error("states: q0\nstart: q0\ntransitions:\n  q0 -\"a-> q0"),
+            at(4, 7, FormalErrorKind::UnclosedQuote)
+        );
+        assert_eq!(
+            error("states: q0\nstart: q0\ntransitions:\n  q0 q0"),
+            at(4, 6, FormalErrorKind::NotATransition)
+        );
+        assert_eq!(
+            error("start: q0"),
+            at(1, 1, FormalErrorKind::MissingSection("states"))
+        );
+    }
+}
@@ -2,6 +2,13 @@ use petgraph::Graph;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::{
+    compiled::CompiledMachine,
+    label::{Label, LabelError},
+    pushdown::{CompiledPushdown, PushdownAcceptance, PushdownLabelError, MAX_STACK_DEPTH},
+    turing::TuringLabelError,
+};
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
 pub struct StateMachine {
     pub nodes: Vec<Node>,
@@ -99,6 +106,31 @@ pub enum FSMError {
         "a link with this index is pointing at a node with the second index that does not exist"
     )]
     DisjointedLink((usize, usize)),
+
+    #[error("the text of the link with this index cannot be read as a label: {1}")]
+    InvalidLabel(usize, LabelError),
+
+    #[error("the text of the link with this index cannot be read as a pushdown label: {1}")]
+    InvalidPushdownLabel(usize, PushdownLabelError),
+
+    #[error("the text of the link with this index cannot be read as a Turing machine label: {1}")]
+    InvalidTuringLabel(usize, TuringLabelError),
+
+    #[error(
+        "the result would need a `.` that leaves out some characters, which labels cannot say"
+    )]
+    WildcardNotExpressible,
+
+    #[error("the deterministic version of this machine would have more than {0} states")]
+    TooManyStates(usize),
+
+    #[error(
+        "the pushdown automaton's stack grew deeper than {0} symbols before the word was accepted"
+    )]
+    StackTooDeep(usize),
+
+    #[error("the machine got into more than {0} configurations before the word was decided")]
+    TooManyConfigurations(usize),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -116,20 +148,41 @@ impl From<FSMOutput> for bool {
     }
 }
 
+/// The cursors of a [StateMachineEvaluator] after some number of steps.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EvaluatorStep {
+    // (node_id, remaining word, stack)
+    pub node_cursors: Vec<(usize, String, String)>,
+
+    // (link_id, remaining word before, remaining word after, stack after)
+    pub link_cursors: Vec<(usize, String, String, String)>,
+
+    /// Whether some cursor has accepted the word by this step.
+    pub accepted: bool,
+
+    /// Set once a pushdown cursor was dropped by this step, because its stack grew past [MAX_STACK_DEPTH]:
+    /// then the word may not be rejected.
+    pub error: Option<FSMError>,
+}
+
+impl EvaluatorStep {
+    /// Whether the run is over: after the first step, there is nothing left to move.
+    fn is_final(&self, position: usize) -> bool {
+        position > 0 && self.node_cursors.is_empty() && self.link_cursors.is_empty()
+    }
+}
+
 pub struct StateMachineEvaluator {
     fsm: StateMachine,
+    compiled: CompiledMachine,
+    /// Present when the machine is run as a pushdown automaton.
+    pushdown: Option<CompiledPushdown>,
     word: String,
 
-    // (node_id, char index)
-    node_cursors: Vec<(usize, String)>,
-    node_cursors_back: Vec<(usize, String)>,
-
-    // link_id, char index before, char index after)
-    link_cursors: Vec<(usize, String, String)>,
-    link_cursors_back: Vec<(usize, String, String)>,
-
-    accept: bool,
-    started: bool,
+    /// Every step computed so far, starting with the one before anything has moved.
+    history: Vec<EvaluatorStep>,
+    /// Which step of the history the evaluator is showing.
+    position: usize,
 }
 
 impl StateMachineEvaluator {
@@ -138,84 +191,220 @@ impl StateMachineEvaluator {
             return Err(e);
         }
         Ok(Self {
+            compiled: CompiledMachine::new(&fsm),
+            pushdown: None,
             fsm,
             word,
-            started: false,
-            node_cursors: vec![],
-            node_cursors_back: vec![],
-            link_cursors: vec![],
-            link_cursors_back: vec![],
-            accept: false,
+            history: vec![EvaluatorStep::default()],
+            position: 0,
         })
     }
 
-    pub fn node_cursors(&self) -> &Vec<(usize, String)> {
-        &self.node_cursors
+    /// Run the machine as a pushdown automaton: every cursor carries its own stack, which starts empty.
+    ///
+    /// The stacks are written with the top at the end.
+    pub fn new_pushdown(
+        fsm: StateMachine,
+        word: String,
+        acceptance: PushdownAcceptance,
+    ) -> Result<Self, FSMError> {
+        if let Some(e) = fsm.check_pushdown_error() {
+            return Err(e);
+        }
+        Ok(Self {
+            compiled: CompiledMachine::new(&fsm.pushdown_input_machine()),
+            pushdown: Some(CompiledPushdown::new(&fsm, acceptance)?),
+            fsm,
+            word,
+            history: vec![EvaluatorStep::default()],
+            position: 0,
+        })
     }
 
-    pub fn link_cursors(&self) -> &Vec<(usize, String, String)> {
-        &self.link_cursors
+    pub fn node_cursors(&self) -> &Vec<(usize, String, String)> {
+        &self.history[self.position].node_cursors
     }
 
-    pub fn step(&mut self) {
-        self.link_cursors_back.clear();
-        self.node_cursors_back.clear();
+    pub fn link_cursors(&self) -> &Vec<(usize, String, String, String)> {
+        &self.history[self.position].link_cursors
+    }
+
+    /// Whether some cursor has accepted the word so far.
+    pub fn accepted(&self) -> bool {
+        self.history[self.position].accepted
+    }
+
+    /// Why the word cannot be rejected, if some cursor had to be dropped so far.
+    pub fn error(&self) -> Option<&FSMError> {
+        self.history[self.position].error.as_ref()
+    }
 
-        if !self.started {
-            self.started = true;
+    /// Whether a cursor that has read the whole word, at this node and with this stack, accepts it.
+    pub fn accepts(&self, node: usize, stack: &str) -> bool {
+        match &self.pushdown {
+            Some(pushdown) => pushdown.accepts(node, stack),
+            None => self.fsm.nodes[node].accept_state,
+        }
+    }
+
+    /// How many steps have been taken to get to the current cursors.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Whether the run is over at the current step.
+    pub fn is_finished(&self) -> bool {
+        self.history[self.position].is_final(self.position)
+    }
+
+    /// The cursors after this many steps, computing them if needed; None if the run is over before that.
+    ///
+    /// This does not move the evaluator.
+    pub fn step_at(&mut self, position: usize) -> Option<&EvaluatorStep> {
+        self.compute_until(position);
+        self.history.get(position)
+    }
+
+    /// The links that a cursor can take: each with how much of the word it reads, and the stack after it.
+    ///
+    /// For a pushdown automaton, links that would make the stack too deep are left out;
+    /// see [EvaluatorStep::error].
+    pub fn links_matching(
+        &self,
+        node: Option<usize>,
+        rest: &str,
+        stack: &str,
+    ) -> Vec<(usize, usize, String)> {
+        match &self.pushdown {
+            Some(pushdown) => pushdown
+                .links_matching(node, rest, stack)
+                .into_iter()
+                .filter(|(_, _, stack)| stack.chars().count() <= MAX_STACK_DEPTH)
+                .collect(),
+            None => self
+                .compiled
+                .links_matching(node, rest)
+                .into_iter()
+                .map(|(id, length)| (id, length, String::new()))
+                .collect(),
+        }
+    }
+
+    /// Extend the history up to this step, or up to the end of the run, whichever comes first.
+    fn compute_until(&mut self, position: usize) {
+        while self.history.len() <= position {
+            let last = self.history.len() - 1;
+            if self.history[last].is_final(last) {
+                break;
+            }
+            let next = self.next_step(last);
+            self.history.push(next);
+        }
+    }
+
+    /// Compute the step after the one at this position of the history.
+    fn next_step(&self, position: usize) -> EvaluatorStep {
+        let current = &self.history[position];
+        let mut next = EvaluatorStep {
+            accepted: current.accepted,
+            error: current.error.clone(),
+            ..Default::default()
+        };
+        // Links the pushdown automaton could take, but only with too deep a stack.
+        let too_deep = |node: Option<usize>, rest: &str, stack: &str| match &self.pushdown {
+            Some(pushdown) => pushdown
+                .links_matching(node, rest, stack)
+                .iter()
+                .any(|(_, _, stack)| stack.chars().count() > MAX_STACK_DEPTH),
+            None => false,
+        };
+
+        if position == 0 {
+            if too_deep(None, &self.word, "") {
+                next.error = Some(FSMError::StackTooDeep(MAX_STACK_DEPTH));
+            }
             // First step: go over entry links and put link cursors there (if the prefix matches)
-            for (id, link) in self.fsm.links.iter().enumerate() {
-                if let Link::StartLink { node, text, .. } = link {
-                    if self.word.starts_with(text) {
-                        log::info!("Starting eval: link into {node} with prefix {text:?}");
-                        self.link_cursors_back.push((
-                            id,
-                            self.word.to_string(),
-                            self.word[text.len()..].to_string(),
-                        ));
-                    }
-                }
+            for (id, length, stack) in self.links_matching(None, &self.word, "") {
+                log::info!(
+                    "Starting eval: link into {} with prefix {:?}",
+                    self.fsm.links[id].get_nodes().1,
+                    &self.word[..length]
+                );
+                next.link_cursors.push((
+                    id,
+                    self.word.to_string(),
+                    self.word[length..].to_string(),
+                    stack,
+                ));
             }
         } else {
-            // For every link cursor, unconditionally make a node cursor
-            for (link_id, _start_word_prefix, end_word_prefix) in self.link_cursors.drain(0..) {
-                let prefix_len = end_word_prefix.len();
-                self.node_cursors_back
-                    .push((self.fsm.links[link_id].get_nodes().1, end_word_prefix));
+            // For every link cursor, unconditionally make a node cursor,
+            // unless another cursor is already at the same node with the same remaining word and stack.
+            for (link_id, _start_word_prefix, end_word_prefix, stack) in current.link_cursors.iter()
+            {
+                let node = self.fsm.links[*link_id].get_nodes().1;
 
                 // If the word is at an end, and it's coming into a node which accepts, then the FSM accepts too.
-                if prefix_len == 0 {
-                    let node = &self.fsm.nodes[self.fsm.links[link_id].get_nodes().1];
-                    if node.accept_state {
-                        self.accept = true;
-                    }
+                // A pushdown automaton may accept by its stack instead.
+                if end_word_prefix.is_empty() && self.accepts(node, stack) {
+                    next.accepted = true;
+                }
+
+                let cursor = (node, end_word_prefix.clone(), stack.clone());
+                if !next.node_cursors.contains(&cursor) {
+                    next.node_cursors.push(cursor);
                 }
             }
 
-            // For every node cursor, iterate over the links leading out of it,
-            // and if it is the prefix of the current cursor's word,
-            // then create a cursor at the corresponding link.
-            for (node_id, remaining_word) in self.node_cursors.drain(0..) {
-                for (id, link) in self
-                    .fsm
-                    .links
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, v)| v.get_nodes().0 == Some(node_id))
+            // For every node cursor, find the links leading out of it
+            // whose text is a prefix of the current cursor's word,
+            // and create a cursor at each of them.
+            for (node_id, remaining_word, stack) in current.node_cursors.iter() {
+                if too_deep(Some(*node_id), remaining_word, stack) {
+                    next.error = Some(FSMError::StackTooDeep(MAX_STACK_DEPTH));
+                }
+                for (id, length, stack) in
+                    self.links_matching(Some(*node_id), remaining_word, stack)
                 {
-                    if remaining_word.starts_with(link.get_text()) {
-                        self.link_cursors_back.push((
-                            id,
-                            remaining_word.to_string(),
-                            remaining_word[link.get_text().len()..].to_string(),
-                        ))
+                    let cursor = (
+                        id,
+                        remaining_word.to_string(),
+                        remaining_word[length..].to_string(),
+                        stack,
+                    );
+                    if !next.link_cursors.contains(&cursor) {
+                        next.link_cursors.push(cursor);
                     }
                 }
             }
         }
 
-        std::mem::swap(&mut self.link_cursors, &mut self.link_cursors_back);
-        std::mem::swap(&mut self.node_cursors, &mut self.node_cursors_back);
+        next
+    }
+
+    /// Move one step forward, computing it if this is the first time the evaluator gets there.
+    ///
+    /// Once the run is over, this does nothing.
+    pub fn step(&mut self) {
+        self.compute_until(self.position + 1);
+        if self.position + 1 < self.history.len() {
+            self.position += 1;
+        }
+    }
+
+    /// Move one step back. Returns false if the evaluator is already at the start.
+    pub fn step_back(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        self.position -= 1;
+        true
+    }
+
+    /// Move to the cursors after this many steps, or to the end of the run if it is over before that.
+    pub fn jump_to(&mut self, position: usize) {
+        self.compute_until(position);
+        self.position = position.min(self.history.len() - 1);
     }
 }
 
@@ -227,90 +416,68 @@ impl StateMachine {
         self.evaluate_unchecked(word)
     }
 
+    /// Run the word through the machine without checking it first.
+    ///
+    /// Loops of empty links do not make this hang, but they are not reported either.
+    /// To run many words, build a [CompiledMachine] once instead.
     pub fn evaluate_unchecked(&self, word: &str) -> Result<FSMOutput, FSMError> {
-        #[derive(Debug)]
-        struct Cursor<'a> {
-            remaining_word: &'a str,
-            node_idx: usize,
-        }
-        let mut cursors = vec![];
-        let start_links: Vec<_> = self
-            .links
-            .iter()
-            .filter(|v| matches!(v, Link::StartLink { .. }))
-            .collect();
-        if start_links.is_empty() {
+        let compiled = CompiledMachine::new(self);
+        if !compiled.has_start_links() {
             return Err(FSMError::NoEntryLinks);
         }
+        Ok(compiled.evaluate(word))
+    }
 
-        for link in start_links {
-            if let Some(after_prefix) = word.strip_prefix(link.get_text()) {
-                cursors.push(Cursor {
-                    remaining_word: after_prefix,
-                    node_idx: link.get_nodes().1,
-                });
-            }
+    pub fn check_error(&self) -> Option<FSMError> {
+        if let Some(err) = self.check_links() {
+            return Some(err);
         }
 
-        // TODO: better termination criteria
-        let mut deadline = 100_00usize;
-
-        loop {
-            if deadline == 0 {
-                log::error!("Runtime check failed: too many steps needed for FSM");
-                log::error!("FSM: {self:?}");
-                return Err(FSMError::InfiniteLoop);
-            }
-            deadline -= 1;
-
-            let mut new_cursors = vec![];
-
-            // If there are cursors with empty words, check if they're at accepting nodes.
-            // If they are, the machine accepts.
-            for cursor in cursors.iter() {
-                if cursor.remaining_word.is_empty() {
-                    if self.nodes[cursor.node_idx].accept_state {
-                        return Ok(FSMOutput::Accept);
-                    }
-                }
-            }
-
-            // // Remove all cursors with empty words that ended up on non-accepting nodes.
-            // cursors.retain(|v| !v.remaining_word.is_empty());
-
-            // If there are no cursors left, the machine rejects.
-            if cursors.is_empty() {
-                return Ok(FSMOutput::Reject);
-            }
-
-            for Cursor {
-                remaining_word,
-                node_idx,
-            } in cursors.iter()
-            {
-                for link in self
-                    .links
-                    .iter()
-                    .filter(|v| v.get_nodes().0 == Some(*node_idx))
-                {
-                    log::debug!("Cursor is at {remaining_word:?} at {node_idx}: link {link:?}");
-                    if let Some(after_prefix) = remaining_word.strip_prefix(link.get_text()) {
-                        log::debug!("After prefix: {after_prefix:?}");
-                        new_cursors.push(Cursor {
-                            remaining_word: after_prefix,
-                            node_idx: link.get_nodes().1,
-                        })
-                    }
+        // Check that every link's text is a valid label
+        let mut labels = Vec::with_capacity(self.links.len());
+        for (i, l) in self.links.iter().enumerate() {
+            match l.get_text().parse::<Label>() {
+                Ok(label) => labels.push(label),
+                Err(why) => {
+                    log::info!("Pre-check failed: FSM has link {i} with invalid label: {why}");
+                    return Some(FSMError::InvalidLabel(i, why));
                 }
             }
+        }
 
-            cursors.clear();
-            cursors.extend(new_cursors.into_iter());
-            log::debug!("{cursors:?}");
+        // Check for infinite loops
+        // Build a graph from only the links that can have zero length.
+        let mut graph = Graph::new();
+        let nodes: Vec<_> = self.nodes.iter().map(|_v| graph.add_node(())).collect();
+        for (link, _) in self
+            .links
+            .iter()
+            .zip(labels.iter())
+            .filter(|(_, label)| label.is_nullable())
+            .filter(|(v, _)| !matches!(v, Link::StartLink { .. }))
+        {
+            let (a, b) = link.get_nodes();
+            let a = if let Some(v) = a {
+                v
+            } else {
+                continue;
+            };
+            graph.add_edge(nodes[a], nodes[b], ());
+        }
+        // If that graph is cyclic, then we have an infinite loop
+        if petgraph::algo::is_cyclic_directed(&graph) {
+            log::info!("Pre-check failed: FSM has loop made out of zero-length links");
+            log::debug!("Graph: {graph:?}");
+            return Some(FSMError::InfiniteLoop);
         }
+
+        None
     }
 
-    pub fn check_error(&self) -> Option<FSMError> {
+    /// Check that the machine has entry links, and that every link joins nodes that exist.
+    ///
+    /// These checks do not depend on how the links' text is read.
+    pub(crate) fn check_links(&self) -> Option<FSMError> {
         // Check for existence of entry links
         let has_entry_links = self
             .links
@@ -338,31 +505,6 @@ impl StateMachine {
             }
         }
 
-        // Check for infinite loops
-        // Build a graph from only the links that have zero length.
-        let mut graph = Graph::new();
-        let nodes: Vec<_> = self.nodes.iter().map(|_v| graph.add_node(())).collect();
-        for link in self
-            .links
-            .iter()
-            .filter(|v| v.get_text().is_empty())
-            .filter(|v| !matches!(v, Link::StartLink { .. }))
-        {
-            let (a, b) = link.get_nodes();
-            let a = if let Some(v) = a {
-                v
-            } else {
-                continue;
-            };
-            graph.add_edge(nodes[a], nodes[b], ());
-        }
-        // If that graph is cyclic, then we have an infinite loop
-        if petgraph::algo::is_cyclic_directed(&graph) {
-            log::info!("Pre-check failed: FSM has loop made out of zero-length links");
-            log::debug!("Graph: {graph:?}");
-            return Some(FSMError::InfiniteLoop);
-        }
-
         None
     }
 }
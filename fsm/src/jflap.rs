@@ -0,0 +1,343 @@
+//! Reading and writing JFLAP's `.jff` files for finite automata.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::{
+    drawing::{fit_to_canvas, route_links},
+    fsm::{Link, Node, StateMachine},
+    label::{CharSet, Label, LabelError, MAX_CLASS_SIZE},
+    layout::LayoutAlgorithm,
+    stored::{ValidationError, MAX_COORDINATE},
+};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum JffError {
+    #[error("the file is not valid XML: {0}")]
+    Xml(String),
+
+    #[error("the file holds a JFLAP `{0}`, but only finite automata (`fa`) are supported")]
+    UnsupportedType(String),
+
+    #[error("a <{element}> is missing in a <{parent}>")]
+    Missing {
+        element: &'static str,
+        parent: &'static str,
+    },
+
+    #[error("<{element}> should hold a number, but holds `{text}`")]
+    NotANumber { element: &'static str, text: String },
+
+    #[error("a transition refers to state {0}, which does not exist")]
+    UnknownState(String),
+
+    #[error("the label of link {0} cannot be read: {1}")]
+    InvalidLabel(usize, LabelError),
+
+    #[error("link {0} reads too many words to write them out as JFLAP transitions")]
+    TooManyWords(usize),
+    #[error("state {state} is at ({x}, {y}), too far from the canvas")]
+    PositionTooFar { state: String, x: f64, y: f64 },
+
+    #[error("the machine is not valid: {0}")]
+    Invalid(#[from] ValidationError),
+}
+
+/// The most transitions one link may turn into when its classes are written out.
+const MAX_WORDS_PER_LINK: usize = 4 * MAX_CLASS_SIZE;
+
+/// Escape text for use in XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Every word that one alternative of a label reads, with `.` standing for the wildcard characters;
+/// None if there are more than `limit`.
+fn alternative_words(
+    alternative: &[CharSet],
+    wildcard: &[char],
+    limit: usize,
+) -> Option<Vec<String>> {
+    let mut words = vec![String::new()];
+    for set in alternative {
+        let chars = set.chars(wildcard);
+        if words.len() * chars.len() > limit {
+            return None;
+        }
+        words = words
+            .iter()
+            .flat_map(|word| chars.iter().map(move |c| format!("{word}{c}")))
+            .collect();
+    }
+    Some(words)
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|c| c.has_tag_name(name))
+}
+
+/// The text of a child element, with nothing for an empty one like `<read/>`.
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).map(|c| c.text().unwrap_or(""))
+}
+
+fn number(node: roxmltree::Node, element: &'static str) -> Result<Option<f64>, JffError> {
+    match child_text(node, element) {
+        None => Ok(None),
+        Some(text) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| JffError::NotANumber {
+                element,
+                text: text.to_string(),
+            }),
+    }
+}
+
+impl StateMachine {
+    /// Write this machine as a JFLAP finite automaton.
+    ///
+    /// JFLAP transitions read plain strings, so every alternative of a label becomes a transition of its own,
+    /// with classes and `.` written out over the characters the machine's labels use.
+    /// JFLAP has a single initial state and no text on it: if this machine has several start nodes,
+    /// or start links that read something, an extra initial state leads to them.
+    pub fn to_jff(&self) -> Result<String, JffError> {
+        let wildcard = self.label_alphabet();
+        let mut transitions = vec![];
+        let mut starts = vec![];
+        for (i, link) in self.links.iter().enumerate() {
+            let label: Label = link
+                .get_text()
+                .parse()
+                .map_err(|why| JffError::InvalidLabel(i, why))?;
+            let mut words = vec![];
+            for alternative in label.alternatives.iter() {
+                let limit = MAX_WORDS_PER_LINK - words.len();
+                words.extend(
+                    alternative_words(alternative, &wildcard, limit)
+                        .ok_or(JffError::TooManyWords(i))?,
+                );
+            }
+            match link.get_nodes() {
+                (Some(from), to) => {
+                    for word in words {
+                        transitions.push((from, to, word));
+                    }
+                }
+                (None, to) => starts.push((to, words)),
+            }
+        }
+
+        let simple_start = match starts.as_slice() {
+            [(node, words)] if words.iter().all(|w| w.is_empty()) => Some(*node),
+            _ => None,
+        };
+        let mut jff = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n<structure>\n\t<type>fa</type>\n\t<automaton>\n",
+        );
+        for (i, node) in self.nodes.iter().enumerate() {
+            jff += &format!("\t\t<state id=\"{i}\" name=\"{}\">\n", escape(&node.text));
+            jff += &format!("\t\t\t<x>{}.0</x>\n\t\t\t<y>{}.0</y>\n", node.x, node.y);
+            if simple_start == Some(i) {
+                jff += "\t\t\t<initial/>\n";
+            }
+            if node.accept_state {
+                jff += "\t\t\t<final/>\n";
+            }
+            jff += "\t\t</state>\n";
+        }
+        if simple_start.is_none() && !starts.is_empty() {
+            let start = self.nodes.len();
+            let (x, y) = self
+                .nodes
+                .iter()
+                .map(|n| (n.x, n.y))
+                .min()
+                .map_or((0, 0), |(x, y)| (x - 100, y));
+            jff += &format!(
+                "\t\t<state id=\"{start}\" name=\"start\">\n\t\t\t<x>{x}.0</x>\n\t\t\t<y>{y}.0</y>\n\t\t\t<initial/>\n\t\t</state>\n"
+            );
+            for (node, words) in starts {
+                for word in words {
+                    transitions.push((start, node, word));
+                }
+            }
+        }
+        for (from, to, word) in transitions {
+            let read = if word.is_empty() {
+                String::from("<read/>")
+            } else {
+                format!("<read>{}</read>", escape(&word))
+            };
+            jff += &format!(
+                "\t\t<transition>\n\t\t\t<from>{from}</from>\n\t\t\t<to>{to}</to>\n\t\t\t{read}\n\t\t</transition>\n"
+            );
+        }
+        jff += "\t</automaton>\n</structure>\n";
+        Ok(jff)
+    }
+
+    /// Read a JFLAP finite automaton, from JFLAP 4 or later.
+    ///
+    /// States keep their names and places, and empty (lambda) transitions become empty links,
+    /// except for the ones from a state to itself, which do nothing and are dropped.
+    /// All transitions between the same two states are joined into one link, with one alternative each.
+    /// If the states have no coordinates, the machine is laid out automatically;
+    /// coordinates further than [MAX_COORDINATE] from the origin are an error.
+    pub fn from_jff(text: &str) -> Result<StateMachine, JffError> {
+        let document =
+            roxmltree::Document::parse(text).map_err(|e| JffError::Xml(e.to_string()))?;
+        let structure = document.root_element();
+        let kind = child_text(structure, "type").unwrap_or("fa").trim();
+        if kind != "fa" {
+            return Err(JffError::UnsupportedType(kind.to_string()));
+        }
+        // JFLAP 4 puts the states right in the structure; later versions wrap them in an automaton.
+        let automaton = child(structure, "automaton").unwrap_or(structure);
+
+        let mut fsm = StateMachine::default();
+        let mut ids = BTreeMap::new();
+        let mut positions = vec![];
+        for state in automaton.children().filter(|c| c.has_tag_name("state")) {
+            let id = state.attribute("id").ok_or(JffError::Missing {
+                element: "id",
+                parent: "state",
+            })?;
+            ids.insert(id.to_string(), fsm.nodes.len());
+            if child(state, "initial").is_some() {
+                fsm.links.push(Link::StartLink {
+                    node: fsm.nodes.len(),
+                    text: String::new(),
+                    delta_x: 0,
+                    delta_y: 0,
+                });
+            }
+            fsm.nodes.push(Node {
+                x: 0,
+                y: 0,
+                text: state
+                    .attribute("name")
+                    .map_or_else(|| format!("q{id}"), String::from),
+                accept_state: child(state, "final").is_some(),
+            });
+            positions.push(match (number(state, "x")?, number(state, "y")?) {
+                (Some(x), Some(y)) => {
+                    let in_range = |c: f64| c.is_finite() && c.abs() <= MAX_COORDINATE as f64;
+                    if !in_range(x) || !in_range(y) {
+                        return Err(JffError::PositionTooFar {
+                            state: id.to_string(),
+                            x,
+                            y,
+                        });
+                    }
+                    Some((x.round() as i32, y.round() as i32))
+                }
+                _ => None,
+            });
+        }
+
+        let mut reads: BTreeMap<(usize, usize), Vec<&str>> = BTreeMap::new();
+        for transition in automaton
+            .children()
+            .filter(|c| c.has_tag_name("transition"))
+        {
+            let state = |element: &'static str| {
+                let id = child_text(transition, element).ok_or(JffError::Missing {
+                    element,
+                    parent: "transition",
+                })?;
+                ids.get(id.trim())
+                    .copied()
+                    .ok_or_else(|| JffError::UnknownState(id.to_string()))
+            };
+            let (from, to) = (state("from")?, state("to")?);
+            let read = child_text(transition, "read").unwrap_or("");
+            // An empty loop changes nothing, and would be taken for an infinite loop.
+            if from == to && read.is_empty() {
+                continue;
+            }
+            reads.entry((from, to)).or_default().push(read);
+        }
+        for ((from, to), words) in reads {
+            let text = match words.as_slice() {
+                [""] => String::new(),
+                words => Label {
+                    alternatives: words
+                        .iter()
+                        .flat_map(|w| Label::literal(w).alternatives)
+                        .collect(),
+                }
+                .to_string(),
+            };
+            fsm.links.push(if from == to {
+                Link::SelfLink {
+                    node: from,
+                    text,
+                    anchor_angle: 0.0,
+                }
+            } else {
+                Link::NormalLink {
+                    start_node: from,
+                    end_node: to,
+                    text,
+                    angle_adjust: 0.0,
+                    parallel_part: 0.5,
+                    perpendicular_part: 0.0,
+                }
+            });
+        }
+
+        let positions: Option<Vec<(i32, i32)>> = positions.into_iter().collect();
+        match positions {
+            Some(mut positions) => {
+                fit_to_canvas(&mut positions);
+                for (node, (x, y)) in fsm.nodes.iter_mut().zip(positions) {
+                    node.x = x;
+                    node.y = y;
+                }
+                route_links(&mut fsm);
+            }
+            None => fsm = fsm.layout(LayoutAlgorithm::Layered),
+        }
+        fsm.validate()?;
+        Ok(fsm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsm::StateMachine;
+
+    use super::JffError;
+
+    fn two_states(x: &str) -> String {
+        format!(
+            "<structure><type>fa</type><automaton>\
+             <state id=\"0\"><x>{x}</x><y>1</y><initial/></state>\
+             <state id=\"1\"><x>-{x}</x><y>1</y><final/></state>\
+             <transition><from>0</from><to>1</to><read>a</read></transition>\
+             </automaton></structure>"
+        )
+    }
+
+    #[test]
+    fn far_coordinates_are_an_error() {
+        let far = StateMachine::from_jff(&two_states("3e9"));
+        assert!(
+            matches!(far, Err(JffError::PositionTooFar { .. })),
+            "{far:?}"
+        );
+
+        let near = StateMachine::from_jff(&two_states("9000")).unwrap();
+        assert_eq!(near.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,472 @@
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{fsm::StateMachine, regex::Regex};
+
+/// Characters that mean something in a label, and must be written after a backslash to be read literally.
+const SPECIAL: [char; 8] = [',', '.', '[', ']', '\\', 'ε', '-', '^'];
+
+/// Whether a backslash may come before this character. Besides the special characters,
+/// `/` can be escaped, because the links of Mealy transducers are split at it.
+fn is_escapable(c: char) -> bool {
+    SPECIAL.contains(&c) || c == '/'
+}
+
+/// The most characters a single class may contain, so that expanding it stays cheap.
+pub(crate) const MAX_CLASS_SIZE: usize = 256;
+
+/// The set of characters that one position of a label accepts.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CharSet {
+    /// Written `.`: any character at all.
+    Any,
+
+    /// A single character, or a class like `[0-9a-f]`. The ranges are inclusive.
+    Ranges(Vec<(char, char)>),
+}
+
+impl CharSet {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            CharSet::Any => true,
+            CharSet::Ranges(ranges) => ranges.iter().any(|(a, b)| (*a..=*b).contains(&c)),
+        }
+    }
+
+    /// Whether some character is in both sets.
+    pub fn intersects(&self, other: &CharSet) -> bool {
+        match (self, other) {
+            (CharSet::Any, _) | (_, CharSet::Any) => true,
+            (CharSet::Ranges(a), CharSet::Ranges(b)) => a
+                .iter()
+                .any(|(a1, a2)| b.iter().any(|(b1, b2)| a1 <= b2 && b1 <= a2)),
+        }
+    }
+
+    /// The characters of this set, with [CharSet::Any] standing for the given wildcard characters.
+    pub fn chars(&self, wildcard: &[char]) -> BTreeSet<char> {
+        match self {
+            CharSet::Any => wildcard.iter().copied().collect(),
+            CharSet::Ranges(ranges) => ranges.iter().flat_map(|(a, b)| *a..=*b).collect(),
+        }
+    }
+
+    /// The single character this set is made of, if that is what it is.
+    fn as_char(&self) -> Option<char> {
+        match self {
+            CharSet::Ranges(ranges) if ranges.len() == 1 && ranges[0].0 == ranges[0].1 => {
+                Some(ranges[0].0)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for CharSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let escaped = |c: char| {
+            if SPECIAL.contains(&c) {
+                format!("\\{c}")
+            } else {
+                c.to_string()
+            }
+        };
+        if let Some(c) = self.as_char() {
+            // Outside of a class, only some of the special characters need escaping.
+            return match c {
+                '-' | '^' => write!(f, "{c}"),
+                c => write!(f, "{}", escaped(c)),
+            };
+        }
+        match self {
+            CharSet::Any => write!(f, "."),
+            CharSet::Ranges(ranges) => {
+                write!(f, "[")?;
+                for (a, b) in ranges {
+                    if a == b {
+                        write!(f, "{}", escaped(*a))?;
+                    } else if char::from_u32(*a as u32 + 1) == Some(*b) {
+                        write!(f, "{}{}", escaped(*a), escaped(*b))?;
+                    } else {
+                        write!(f, "{}-{}", escaped(*a), escaped(*b))?;
+                    }
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// The text on a link, read as a small pattern.
+///
+/// A label is a list of alternatives separated by `,`, any of which the link may read.
+/// Every alternative is a sequence of characters, classes like `[0-9]`, and `.` for any character;
+/// `\e` or `ε` alone stands for the empty word, like an empty label does.
+/// A backslash makes the next special character literal, so `\,` reads a comma; `\/` reads a slash.
+///
+/// Labels without special characters read exactly their text, as they always have;
+/// machines stored before labels were patterns have their texts escaped when they are read back.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Label {
+    pub alternatives: Vec<Vec<CharSet>>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LabelError {
+    #[error("the label ended in the middle of an escape or a class")]
+    UnexpectedEnd,
+
+    #[error("`\\{0}` is not a known escape")]
+    UnknownEscape(char),
+
+    #[error("one of the alternatives is empty; write `\\e` for the empty word")]
+    EmptyAlternative,
+
+    #[error("a class must contain at least one character")]
+    EmptyClass,
+
+    #[error("negated classes are not supported")]
+    NegatedClass,
+
+    #[error("the range {0}-{1} goes backwards")]
+    BackwardsRange(char, char),
+
+    #[error("a class may contain at most {MAX_CLASS_SIZE} characters")]
+    ClassTooLarge,
+}
+
+impl Label {
+    /// The label reading exactly this text.
+    pub fn literal(text: &str) -> Label {
+        Label {
+            alternatives: vec![text
+                .chars()
+                .map(|c| CharSet::Ranges(vec![(c, c)]))
+                .collect()],
+        }
+    }
+
+    /// The label reading any one of these characters, written as compactly as possible.
+    pub fn from_chars(chars: &[char]) -> Label {
+        Label {
+            alternatives: vec![vec![CharSet::Ranges(char_ranges(chars))]],
+        }
+    }
+
+    /// Write this text as a label that reads exactly it, escaping the special characters.
+    pub fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if SPECIAL.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Read the label, or take its text literally if it is not a valid label.
+    ///
+    /// This is for code that must do something sensible with machines that have not been checked.
+    pub fn parse_or_literal(text: &str) -> Label {
+        text.parse().unwrap_or_else(|_| Label::literal(text))
+    }
+
+    /// Whether this label can be taken without reading anything.
+    pub fn is_nullable(&self) -> bool {
+        self.alternatives.iter().any(|a| a.is_empty())
+    }
+
+    /// The plain texts this label reads, if it has no classes or wildcards.
+    pub fn as_literals(&self) -> Option<Vec<String>> {
+        self.alternatives
+            .iter()
+            .map(|alternative| alternative.iter().map(|set| set.as_char()).collect())
+            .collect()
+    }
+
+    /// Whether some alternative has a `.` in it.
+    pub fn has_wildcard(&self) -> bool {
+        self.alternatives
+            .iter()
+            .flatten()
+            .any(|set| *set == CharSet::Any)
+    }
+
+    /// The characters that are written in this label, with classes expanded.
+    pub fn mentioned_chars(&self) -> BTreeSet<char> {
+        self.alternatives
+            .iter()
+            .flatten()
+            .flat_map(|set| set.chars(&[]))
+            .collect()
+    }
+
+    /// Find the beginnings of `rest` that this label reads, as lengths in bytes, shortest first.
+    pub fn prefix_lengths(&self, rest: &str) -> Vec<usize> {
+        let mut lengths: Vec<usize> = self
+            .alternatives
+            .iter()
+            .filter_map(|alternative| {
+                let mut chars = rest.char_indices();
+                for set in alternative {
+                    match chars.next() {
+                        Some((_, c)) if set.contains(c) => {}
+                        _ => return None,
+                    }
+                }
+                Some(chars.next().map_or(rest.len(), |(i, _)| i))
+            })
+            .collect();
+        lengths.sort();
+        lengths.dedup();
+        lengths
+    }
+
+    /// Whether some word read by one label is a beginning of a different word read by the other,
+    /// so that a machine could take either link on the same input.
+    pub fn overlaps(&self, other: &Label) -> bool {
+        self.alternatives.iter().any(|a| {
+            other
+                .alternatives
+                .iter()
+                .any(|b| a.iter().zip(b.iter()).all(|(x, y)| x.intersects(y)))
+        })
+    }
+
+    /// The label reading every word of this one backwards.
+    pub fn reversed(&self) -> Label {
+        Label {
+            alternatives: self
+                .alternatives
+                .iter()
+                .map(|a| a.iter().rev().cloned().collect())
+                .collect(),
+        }
+    }
+
+    /// The regular expression for this label.
+    pub fn to_regex(&self) -> Regex {
+        self.alternatives
+            .iter()
+            .map(|alternative| {
+                alternative
+                    .iter()
+                    .map(|set| match set {
+                        CharSet::Any => Regex::Any,
+                        CharSet::Ranges(ranges) => Regex::Class(ranges.clone()),
+                    })
+                    .fold(Regex::Epsilon, Regex::concat)
+            })
+            .fold(Regex::Nothing, Regex::union)
+    }
+}
+
+/// Sort the characters and join runs of consecutive ones into ranges.
+fn char_ranges(chars: &[char]) -> Vec<(char, char)> {
+    let chars: BTreeSet<char> = chars.iter().copied().collect();
+    let mut ranges: Vec<(char, char)> = vec![];
+    for c in chars {
+        match ranges.last_mut() {
+            Some((_, end)) if char::from_u32(*end as u32 + 1) == Some(c) => *end = c,
+            _ => ranges.push((c, c)),
+        }
+    }
+    ranges
+}
+
+/// Read the inside of a class, after the opening bracket.
+fn parse_class(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<CharSet, LabelError> {
+    let mut ranges = vec![];
+    if chars.peek() == Some(&'^') {
+        return Err(LabelError::NegatedClass);
+    }
+    let next_char = |chars: &mut std::iter::Peekable<std::str::Chars>| match chars.next() {
+        None => Err(LabelError::UnexpectedEnd),
+        Some('\\') => match chars.next() {
+            None => Err(LabelError::UnexpectedEnd),
+            Some(c) if is_escapable(c) => Ok(c),
+            Some(c) => Err(LabelError::UnknownEscape(c)),
+        },
+        Some(c) => Ok(c),
+    };
+    loop {
+        if chars.peek() == Some(&']') {
+            chars.next();
+            break;
+        }
+        let a = next_char(chars)?;
+        let mut lookahead = chars.clone();
+        if lookahead.next() == Some('-') && !matches!(lookahead.next(), Some(']') | None) {
+            chars.next();
+            let b = next_char(chars)?;
+            if b < a {
+                return Err(LabelError::BackwardsRange(a, b));
+            }
+            ranges.push((a, b));
+        } else {
+            ranges.push((a, a));
+        }
+    }
+    if ranges.is_empty() {
+        return Err(LabelError::EmptyClass);
+    }
+    let size: usize = ranges
+        .iter()
+        .map(|(a, b)| *b as usize - *a as usize + 1)
+        .sum();
+    if size > MAX_CLASS_SIZE {
+        return Err(LabelError::ClassTooLarge);
+    }
+    Ok(CharSet::Ranges(ranges))
+}
+
+impl FromStr for Label {
+    type Err = LabelError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.is_empty() {
+            return Ok(Label {
+                alternatives: vec![vec![]],
+            });
+        }
+        let mut alternatives = vec![];
+        let mut current = vec![];
+        // Whether the current alternative has anything in it, counting explicit epsilons.
+        let mut written = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                ',' => {
+                    if !written {
+                        return Err(LabelError::EmptyAlternative);
+                    }
+                    alternatives.push(std::mem::take(&mut current));
+                    written = false;
+                    continue;
+                }
+                '.' => current.push(CharSet::Any),
+                'ε' => {}
+                '[' => current.push(parse_class(&mut chars)?),
+                '\\' => match chars.next() {
+                    None => return Err(LabelError::UnexpectedEnd),
+                    Some('e') => {}
+                    Some(c) if is_escapable(c) => current.push(CharSet::Ranges(vec![(c, c)])),
+                    Some(c) => return Err(LabelError::UnknownEscape(c)),
+                },
+                c => current.push(CharSet::Ranges(vec![(c, c)])),
+            }
+            written = true;
+        }
+        if !written {
+            return Err(LabelError::EmptyAlternative);
+        }
+        alternatives.push(current);
+        Ok(Label { alternatives })
+    }
+}
+
+impl Display for Label {
+    /// Write the label so that it reads back the same, with `ε` for the empty word.
+    ///
+    /// A label that only reads the empty word is written as nothing at all.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.alternatives.iter().all(|a| a.is_empty()) {
+            return Ok(());
+        }
+        for (i, alternative) in self.alternatives.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if alternative.is_empty() {
+                write!(f, "ε")?;
+            }
+            for set in alternative {
+                write!(f, "{set}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StateMachine {
+    /// The characters written in any of the labels, sorted. Labels that cannot be read are skipped.
+    ///
+    /// Analyses that need a finite alphabet, like [StateMachine::to_dfa], let `.` stand for these characters
+    /// and one more for all the others.
+    pub fn label_alphabet(&self) -> Vec<char> {
+        let set: BTreeSet<char> = self
+            .links
+            .iter()
+            .filter_map(|link| link.get_text().parse::<Label>().ok())
+            .flat_map(|label| label.mentioned_chars())
+            .collect();
+        set.into_iter().collect()
+    }
+
+    /// Whether some readable label has a `.` in it.
+    pub fn has_wildcard(&self) -> bool {
+        self.links.iter().any(|link| {
+            link.get_text()
+                .parse::<Label>()
+                .is_ok_and(|label| label.has_wildcard())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CharSet, Label, LabelError};
+
+    fn parse(text: &str) -> Result<Label, LabelError> {
+        text.parse()
+    }
+
+    #[test]
+    fn reads_alternatives_classes_and_wildcards() {
+        let label = parse("ab,[0-9x],.,\\e").unwrap();
+        assert_eq!(label.prefix_lengths("abc"), [0, 1, 2]);
+        assert_eq!(label.prefix_lengths("7"), [0, 1]);
+        assert_eq!(label.prefix_lengths("y"), [0, 1]);
+        assert_eq!(
+            label.alternatives[1],
+            [CharSet::Ranges(vec![('0', '9'), ('x', 'x')])]
+        );
+        assert!(label.has_wildcard());
+        assert!(label.is_nullable());
+
+        assert!(parse("").unwrap().is_nullable());
+        assert!(parse("ε").unwrap().is_nullable());
+        assert!(!parse("a").unwrap().is_nullable());
+        assert_eq!(parse("a\\,b").unwrap(), Label::literal("a,b"));
+    }
+
+    #[test]
+    fn errors_name_the_problem() {
+        assert_eq!(parse("a,,b"), Err(LabelError::EmptyAlternative));
+        assert_eq!(parse("a,"), Err(LabelError::EmptyAlternative));
+        assert_eq!(parse("\\q"), Err(LabelError::UnknownEscape('q')));
+        assert_eq!(parse("a\\"), Err(LabelError::UnexpectedEnd));
+        assert_eq!(parse("[ab"), Err(LabelError::UnexpectedEnd));
+        assert_eq!(parse("[]"), Err(LabelError::EmptyClass));
+        assert_eq!(parse("[^a]"), Err(LabelError::NegatedClass));
+        assert_eq!(parse("[z-a]"), Err(LabelError::BackwardsRange('z', 'a')));
+    }
+
+    #[test]
+    fn escaped_texts_read_back_literally() {
+        for text in ["", "abc", "a,b", "1.5", "[x]", "\\e", "ε", "a-^b"] {
+            assert_eq!(parse(&Label::escape(text)), Ok(Label::literal(text)));
+        }
+    }
+
+    #[test]
+    fn printed_labels_read_back_the_same() {
+        for text in ["ab,[0-9x],.,\\e", "\\,,[a\\-c]", "x[bc]y", "-^", "ε,a"] {
+            let label = parse(text).unwrap();
+            assert_eq!(parse(&label.to_string()), Ok(label));
+        }
+    }
+}
@@ -0,0 +1,492 @@
+//! Automatic placement of a machine's nodes and links on the canvas.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::{
+    drawing::{circle_positions, fit_to_canvas, route_links, CENTER},
+    fsm::{Link, StateMachine},
+};
+
+/// How [StateMachine::layout] places the nodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutAlgorithm {
+    /// Columns from left to right, following the links from the start nodes,
+    /// with the nodes in every column ordered to cross as few links as possible.
+    Layered,
+
+    /// Links pull the nodes they connect together, while all nodes push each other apart,
+    /// until they settle down.
+    ForceDirected,
+}
+
+/// How many times the columns are reordered, alternating between left-to-right and right-to-left.
+const ORDERING_SWEEPS: usize = 8;
+
+/// How many times the forces move the nodes.
+const FORCE_ITERATIONS: usize = 300;
+
+/// How far from the edge of the canvas the nodes are kept.
+const MARGIN: f64 = 60.0;
+
+/// How close two nodes' centers may be: nodes are drawn with a radius of 30.
+const MIN_DISTANCE: f64 = 80.0;
+
+impl StateMachine {
+    /// A copy of this machine with its nodes rearranged by the algorithm,
+    /// and its links drawn so that they stay clear of each other and of the other nodes.
+    ///
+    /// Nodes and links keep their indices and text; everything fits on the canvas.
+    pub fn layout(&self, algorithm: LayoutAlgorithm) -> StateMachine {
+        let count = self.nodes.len();
+        if count == 0 {
+            return self.clone();
+        }
+        let edges: BTreeSet<(usize, usize)> = self
+            .links
+            .iter()
+            .filter_map(|link| match link.get_nodes() {
+                (Some(a), b) if a != b && a < count && b < count => Some((a, b)),
+                _ => None,
+            })
+            .collect();
+        let edges: Vec<(usize, usize)> = edges.into_iter().collect();
+        let mut starts = vec![];
+        for link in self.links.iter() {
+            if let Link::StartLink { node, .. } = link {
+                if *node < count && !starts.contains(node) {
+                    starts.push(*node);
+                }
+            }
+        }
+
+        let mut positions = match algorithm {
+            LayoutAlgorithm::Layered => layered_positions(count, &starts, &edges),
+            LayoutAlgorithm::ForceDirected => force_directed_positions(count, &starts, &edges),
+        };
+        fit_to_canvas(&mut positions);
+        separate(&mut positions);
+
+        let mut fsm = self.clone();
+        for (node, (x, y)) in fsm.nodes.iter_mut().zip(positions) {
+            node.x = x;
+            node.y = y;
+        }
+        route_links(&mut fsm);
+        fsm
+    }
+}
+
+/// Push apart the nodes that are closer than [MIN_DISTANCE], keeping them on the canvas.
+///
+/// This gives up after a fixed number of rounds, in case the canvas is too crowded for every node to fit.
+fn separate(positions: &mut [(i32, i32)]) {
+    let mut points: Vec<(f64, f64)> = positions
+        .iter()
+        .map(|(x, y)| (*x as f64, *y as f64))
+        .collect();
+    for _ in 0..50 {
+        let mut moved = false;
+        for i in 0..points.len() {
+            for j in i + 1..points.len() {
+                let (mut dx, mut dy) = (points[j].0 - points[i].0, points[j].1 - points[i].1);
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance >= MIN_DISTANCE {
+                    continue;
+                }
+                if distance < 0.01 {
+                    (dx, dy) = (1.0, 0.0);
+                } else {
+                    (dx, dy) = (dx / distance, dy / distance);
+                }
+                let push = (MIN_DISTANCE - distance) / 2.0 + 0.5;
+                points[i] = clamp_to_canvas((points[i].0 - dx * push, points[i].1 - dy * push));
+                points[j] = clamp_to_canvas((points[j].0 + dx * push, points[j].1 + dy * push));
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+    for (p, (x, y)) in positions.iter_mut().zip(points) {
+        *p = (x.round() as i32, y.round() as i32);
+    }
+}
+
+fn clamp_to_canvas((x, y): (f64, f64)) -> (f64, f64) {
+    (
+        x.clamp(MARGIN, 2.0 * CENTER.0 as f64 - MARGIN),
+        y.clamp(MARGIN, 2.0 * CENTER.1 as f64 - MARGIN),
+    )
+}
+
+/// The order in which the nodes are first seen going breadth-first from the start nodes,
+/// and then from every node not seen yet.
+fn discovery_order(count: usize, starts: &[usize], edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut seen = vec![false; count];
+    let mut order = vec![];
+    for root in starts.iter().copied().chain(0..count) {
+        if seen[root] {
+            continue;
+        }
+        seen[root] = true;
+        let mut queue = VecDeque::from([root]);
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            for (a, b) in edges.iter() {
+                if *a == node && !seen[*b] {
+                    seen[*b] = true;
+                    queue.push_back(*b);
+                }
+            }
+        }
+    }
+    order
+}
+
+/// The edges with every loop broken by turning around the edges that lead back
+/// to a node still being explored, going depth-first in discovery order.
+fn acyclic_edges(count: usize, order: &[usize], edges: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // 0: not visited, 1: being explored, 2: done.
+    let mut state = vec![0u8; count];
+    let mut reversed = BTreeSet::new();
+    for root in order.iter().copied() {
+        if state[root] != 0 {
+            continue;
+        }
+        state[root] = 1;
+        let mut stack = vec![(root, 0usize)];
+        while let Some((node, next)) = stack.last_mut() {
+            let node = *node;
+            let mut outgoing = edges.iter().enumerate().filter(|(_, (a, _))| *a == node);
+            match outgoing.nth(*next) {
+                Some((i, (_, b))) => {
+                    *next += 1;
+                    match state[*b] {
+                        0 => {
+                            state[*b] = 1;
+                            stack.push((*b, 0));
+                        }
+                        1 => {
+                            reversed.insert(i);
+                        }
+                        _ => {}
+                    }
+                }
+                None => {
+                    state[node] = 2;
+                    stack.pop();
+                }
+            }
+        }
+    }
+    edges
+        .iter()
+        .enumerate()
+        .map(|(i, (a, b))| {
+            if reversed.contains(&i) {
+                (*b, *a)
+            } else {
+                (*a, *b)
+            }
+        })
+        .collect()
+}
+
+/// How many pairs of edges between two neighbouring columns cross, given every node's place in its column.
+fn crossings(between: &[(usize, usize)], place: &[usize]) -> usize {
+    let mut count = 0;
+    for (i, (a1, b1)) in between.iter().enumerate() {
+        for (a2, b2) in between[i + 1..].iter() {
+            let (a1, b1, a2, b2) = (place[*a1], place[*b1], place[*a2], place[*b2]);
+            if (a1 < a2 && b1 > b2) || (a1 > a2 && b1 < b2) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Place the nodes in columns, Sugiyama-style.
+///
+/// Loops are broken, every node goes into the column after the furthest of its predecessors,
+/// and links spanning several columns get invisible stand-ins in the columns they pass through.
+/// The columns are then repeatedly reordered by the average place of every node's neighbours
+/// in the column before (or after), and the order with the fewest crossings is kept.
+fn layered_positions(count: usize, starts: &[usize], edges: &[(usize, usize)]) -> Vec<(i32, i32)> {
+    let order = discovery_order(count, starts, edges);
+    let acyclic = acyclic_edges(count, &order, edges);
+
+    // Longest path layering, in topological order.
+    let mut incoming = vec![0usize; count];
+    for (_, b) in acyclic.iter() {
+        incoming[*b] += 1;
+    }
+    let mut layer = vec![0usize; count];
+    let mut queue: VecDeque<usize> = order
+        .iter()
+        .copied()
+        .filter(|n| incoming[*n] == 0)
+        .collect();
+    while let Some(node) = queue.pop_front() {
+        for (a, b) in acyclic.iter() {
+            if *a == node {
+                layer[*b] = layer[*b].max(layer[node] + 1);
+                incoming[*b] -= 1;
+                if incoming[*b] == 0 {
+                    queue.push_back(*b);
+                }
+            }
+        }
+    }
+
+    // Split long edges with stand-ins, which are numbered after the real nodes.
+    let mut layer_of = layer.clone();
+    let mut segments = vec![];
+    for (a, b) in acyclic.iter() {
+        let mut previous = *a;
+        for l in layer[*a] + 1..layer[*b] {
+            layer_of.push(l);
+            segments.push((previous, layer_of.len() - 1));
+            previous = layer_of.len() - 1;
+        }
+        segments.push((previous, *b));
+    }
+    let layers = layer_of.iter().max().map_or(1, |l| l + 1);
+    let mut columns: Vec<Vec<usize>> = vec![vec![]; layers];
+    for node in order.iter().copied().chain(count..layer_of.len()) {
+        columns[layer_of[node]].push(node);
+    }
+    let between: Vec<Vec<(usize, usize)>> = (0..layers.saturating_sub(1))
+        .map(|l| {
+            segments
+                .iter()
+                .copied()
+                .filter(|(a, _)| layer_of[*a] == l)
+                .collect()
+        })
+        .collect();
+
+    let places = |columns: &[Vec<usize>]| {
+        let mut place = vec![0usize; layer_of.len()];
+        for column in columns.iter() {
+            for (i, node) in column.iter().enumerate() {
+                place[*node] = i;
+            }
+        }
+        place
+    };
+    let total_crossings = |columns: &[Vec<usize>]| {
+        let place = places(columns);
+        between.iter().map(|b| crossings(b, &place)).sum::<usize>()
+    };
+
+    let mut best = (total_crossings(&columns), columns.clone());
+    for sweep in 0..ORDERING_SWEEPS {
+        let forward = sweep % 2 == 0;
+        let sequence: Vec<usize> = if forward {
+            (1..layers).collect()
+        } else {
+            (0..layers.saturating_sub(1)).rev().collect()
+        };
+        for l in sequence {
+            let place = places(&columns);
+            let barycenter = |node: usize| {
+                let neighbours: Vec<usize> = segments
+                    .iter()
+                    .filter_map(|(a, b)| match forward {
+                        true if *b == node => Some(*a),
+                        false if *a == node => Some(*b),
+                        _ => None,
+                    })
+                    .collect();
+                if neighbours.is_empty() {
+                    place[node] as f64
+                } else {
+                    neighbours.iter().map(|n| place[*n] as f64).sum::<f64>()
+                        / neighbours.len() as f64
+                }
+            };
+            let mut keyed: Vec<(f64, usize)> =
+                columns[l].iter().map(|n| (barycenter(*n), *n)).collect();
+            keyed.sort_by(|x, y| x.0.total_cmp(&y.0));
+            columns[l] = keyed.into_iter().map(|(_, n)| n).collect();
+        }
+        let score = total_crossings(&columns);
+        if score < best.0 {
+            best = (score, columns.clone());
+        }
+    }
+    let columns = best.1;
+
+    let width = 2.0 * (CENTER.0 as f64 - MARGIN);
+    let dx = (width / (layers.max(2) - 1) as f64).min(160.0);
+    let left = CENTER.0 as f64 - dx * (layers - 1) as f64 / 2.0;
+    let mut positions = vec![(0, 0); count];
+    for (l, column) in columns.iter().enumerate() {
+        let real: Vec<usize> = column.iter().copied().filter(|n| *n < count).collect();
+        let dy = (2.0 * (CENTER.1 as f64 - MARGIN) / real.len() as f64).min(120.0);
+        for (row, node) in real.iter().enumerate() {
+            let y = CENTER.1 as f64 + (row as f64 - (real.len() as f64 - 1.0) / 2.0) * dy;
+            positions[*node] = ((left + l as f64 * dx).round() as i32, y.round() as i32);
+        }
+    }
+    positions
+}
+
+/// Place the nodes by simulating forces, Fruchterman–Reingold style.
+///
+/// The nodes start on a circle with the first start node on the left, so the result is always the same.
+/// Every step moves the nodes by at most a "temperature" that cools down to nothing,
+/// and a weak pull towards the middle keeps separate parts of the machine together.
+/// The result may be larger than the canvas, and is scaled down afterwards.
+fn force_directed_positions(
+    count: usize,
+    starts: &[usize],
+    edges: &[(usize, usize)],
+) -> Vec<(i32, i32)> {
+    let mut positions: Vec<(f64, f64)> =
+        circle_positions(count, starts.first().copied().unwrap_or(0))
+            .into_iter()
+            .map(|(x, y)| (x as f64, y as f64))
+            .collect();
+    if count == 1 {
+        return vec![CENTER];
+    }
+    let (width, height) = (
+        2.0 * (CENTER.0 as f64 - MARGIN),
+        2.0 * (CENTER.1 as f64 - MARGIN),
+    );
+    // The distance at which two connected nodes are at rest.
+    let k = (0.6 * (width * height / count as f64).sqrt()).clamp(90.0, 180.0);
+    let center = (CENTER.0 as f64, CENTER.1 as f64);
+
+    for iteration in 0..FORCE_ITERATIONS {
+        let temperature = 60.0 * (1.0 - iteration as f64 / FORCE_ITERATIONS as f64) + 0.5;
+        let mut moves = vec![(0.0, 0.0); count];
+        for i in 0..count {
+            for j in i + 1..count {
+                let (mut dx, mut dy) = (
+                    positions[i].0 - positions[j].0,
+                    positions[i].1 - positions[j].1,
+                );
+                if dx.abs() < 0.01 && dy.abs() < 0.01 {
+                    // Nodes on top of each other are pushed apart in a fixed direction.
+                    let angle = (i * count + j) as f64;
+                    (dx, dy) = (0.01 * angle.cos(), 0.01 * angle.sin());
+                }
+                let distance = (dx * dx + dy * dy).sqrt();
+                let force = k * k / distance;
+                let (fx, fy) = (dx / distance * force, dy / distance * force);
+                moves[i].0 += fx;
+                moves[i].1 += fy;
+                moves[j].0 -= fx;
+                moves[j].1 -= fy;
+            }
+        }
+        for (a, b) in edges.iter() {
+            let (dx, dy) = (
+                positions[*a].0 - positions[*b].0,
+                positions[*a].1 - positions[*b].1,
+            );
+            let distance = (dx * dx + dy * dy).sqrt().max(0.01);
+            let force = distance * distance / k;
+            let (fx, fy) = (dx / distance * force, dy / distance * force);
+            moves[*a].0 -= fx;
+            moves[*a].1 -= fy;
+            moves[*b].0 += fx;
+            moves[*b].1 += fy;
+        }
+        for (p, m) in positions.iter_mut().zip(moves.iter_mut()) {
+            m.0 += 0.1 * (center.0 - p.0);
+            m.1 += 0.1 * (center.1 - p.1);
+            let length = (m.0 * m.0 + m.1 * m.1).sqrt();
+            if length > 0.0 {
+                let step = length.min(temperature);
+                p.0 += m.0 / length * step;
+                p.1 += m.1 / length * step;
+            }
+        }
+    }
+
+    positions
+        .into_iter()
+        .map(|(x, y)| (x.round() as i32, y.round() as i32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsm::StateMachine;
+
+    use super::{LayoutAlgorithm, MIN_DISTANCE};
+
+    /// Two branches from `p` that meet again in `s`.
+    fn diamond() -> StateMachine {
+        StateMachine::from_formal(
+            "states: p, q, r, s\nstart: p\naccept: s\ntransitions:\n p -a-> q\n p -b-> r\n q -c-> s\n r -d-> s\n s -e-> s",
+        )
+        .unwrap()
+    }
+
+    fn positions(fsm: &StateMachine) -> Vec<(i32, i32)> {
+        fsm.nodes.iter().map(|node| (node.x, node.y)).collect()
+    }
+
+    #[test]
+    fn layered_columns_follow_the_links() {
+        let fsm = diamond().layout(LayoutAlgorithm::Layered);
+        assert_eq!(
+            positions(&fsm),
+            [(240, 300), (400, 240), (400, 360), (560, 300)]
+        );
+    }
+
+    #[test]
+    fn force_directed_layout_settles_in_the_same_place() {
+        let fsm = diamond().layout(LayoutAlgorithm::ForceDirected);
+        assert_eq!(
+            positions(&fsm),
+            [(286, 281), (381, 186), (514, 319), (419, 414)]
+        );
+    }
+
+    #[test]
+    fn layouts_keep_links_and_fit_on_the_canvas() {
+        let mut crowded = StateMachine::from_formal(
+            "states: a, b, c, d, e, f, g, h\nstart: a, e\ntransitions:\n a -x-> b\n b -x-> c\n c -x-> d\n d -x-> a\n a -y-> c\n e -z-> f",
+        )
+        .unwrap();
+        for node in crowded.nodes.iter_mut() {
+            (node.x, node.y) = (400, 300);
+        }
+        for algorithm in [LayoutAlgorithm::Layered, LayoutAlgorithm::ForceDirected] {
+            let fsm = crowded.layout(algorithm);
+            let texts = |fsm: &StateMachine| -> Vec<(Option<usize>, usize, String)> {
+                fsm.links
+                    .iter()
+                    .map(|link| {
+                        let (a, b) = link.get_nodes();
+                        (a, b, link.get_text().to_string())
+                    })
+                    .collect()
+            };
+            assert_eq!(texts(&fsm), texts(&crowded));
+            let points = positions(&fsm);
+            for (i, (x, y)) in points.iter().enumerate() {
+                assert!(
+                    (0..=800).contains(x) && (0..=600).contains(y),
+                    "{algorithm:?}"
+                );
+                for (other_x, other_y) in points[i + 1..].iter() {
+                    let distance = (((x - other_x).pow(2) + (y - other_y).pow(2)) as f64).sqrt();
+                    assert!(distance >= MIN_DISTANCE - 1.0, "{algorithm:?}: {points:?}");
+                }
+            }
+        }
+        assert_eq!(
+            StateMachine::default().layout(LayoutAlgorithm::Layered),
+            StateMachine::default()
+        );
+    }
+}
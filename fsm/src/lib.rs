@@ -1,2 +1,26 @@
+pub mod closure;
+pub mod compiled;
+pub mod counting;
+pub mod dfa;
+pub mod diff;
+pub mod dot;
+mod drawing;
+pub mod enumerate;
+pub mod equivalence;
+pub mod explain;
+pub mod fingerprint;
+pub mod formal;
 pub mod fsm;
+pub mod jflap;
+pub mod label;
+pub mod layout;
+pub mod lint;
+pub mod minimize;
+pub mod product;
+pub mod pushdown;
+pub mod regex;
+pub mod stored;
 pub mod tester;
+pub mod tikz;
+pub mod transducer;
+pub mod turing;
@@ -0,0 +1,286 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{fsm::StateMachine, label::Label};
+
+/// A warning about a machine that works, but probably not the way its author meant.
+///
+/// In all of these, `node: None` stands for the start links, which leave no node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lint {
+    /// No word leads to this node from the start links.
+    UnreachableNode(usize),
+
+    /// No accepting node can be reached from this node, so every word that gets here is rejected.
+    DeadNode(usize),
+
+    /// No word leads to this accepting node, so it never accepts anything.
+    UnreachableAcceptState(usize),
+
+    /// A word read by one of these links leaving the node is a beginning of a word read by another,
+    /// so the machine can go several ways on the same input.
+    Nondeterminism {
+        node: Option<usize>,
+        links: Vec<usize>,
+    },
+
+    /// These links leave the node with the same text, but lead to different nodes.
+    SameLabel {
+        node: Option<usize>,
+        links: Vec<usize>,
+    },
+
+    /// These links join the same nodes with the same text, so all but one of them are useless.
+    DuplicateLinks(Vec<usize>),
+}
+
+impl Lint {
+    /// The nodes this warning is about.
+    pub fn nodes(&self) -> Vec<usize> {
+        match self {
+            Lint::UnreachableNode(node)
+            | Lint::DeadNode(node)
+            | Lint::UnreachableAcceptState(node) => vec![*node],
+            Lint::Nondeterminism { node, .. } | Lint::SameLabel { node, .. } => {
+                node.iter().copied().collect()
+            }
+            Lint::DuplicateLinks(_) => vec![],
+        }
+    }
+
+    /// The links this warning is about.
+    pub fn links(&self) -> Vec<usize> {
+        match self {
+            Lint::UnreachableNode(_) | Lint::DeadNode(_) | Lint::UnreachableAcceptState(_) => {
+                vec![]
+            }
+            Lint::Nondeterminism { links, .. }
+            | Lint::SameLabel { links, .. }
+            | Lint::DuplicateLinks(links) => links.clone(),
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let from = |node: &Option<usize>| match node {
+            Some(n) => format!("node {n}"),
+            None => String::from("the start"),
+        };
+        match self {
+            Lint::UnreachableNode(n) => write!(f, "node {n} cannot be reached"),
+            Lint::DeadNode(n) => write!(f, "no accepting node can be reached from node {n}"),
+            Lint::UnreachableAcceptState(n) => {
+                write!(f, "accepting node {n} cannot be reached")
+            }
+            Lint::Nondeterminism { node, links } => write!(
+                f,
+                "links {links:?} from {} can be taken on the same input",
+                from(node)
+            ),
+            Lint::SameLabel { node, links } => write!(
+                f,
+                "links {links:?} from {} have the same text but lead to different nodes",
+                from(node)
+            ),
+            Lint::DuplicateLinks(links) => write!(f, "links {links:?} are duplicates"),
+        }
+    }
+}
+
+/// Find every node reachable from the given ones along the edges.
+fn reachable(count: usize, roots: &[usize], edges: &[(usize, usize)]) -> Vec<bool> {
+    let mut seen = vec![false; count];
+    let mut queue = VecDeque::new();
+    for root in roots {
+        if !seen[*root] {
+            seen[*root] = true;
+            queue.push_back(*root);
+        }
+    }
+    while let Some(node) = queue.pop_front() {
+        for (a, b) in edges.iter() {
+            if *a == node && !seen[*b] {
+                seen[*b] = true;
+                queue.push_back(*b);
+            }
+        }
+    }
+    seen
+}
+
+impl StateMachine {
+    /// Look for likely mistakes that [StateMachine::check_error] does not consider errors.
+    ///
+    /// Links pointing at nodes that do not exist are ignored.
+    pub fn lint(&self) -> Vec<Lint> {
+        let count = self.nodes.len();
+        let mut lints = vec![];
+
+        // Every link is (source, target, text, index), with source None for start links.
+        let links: Vec<(Option<usize>, usize, &str, usize)> = self
+            .links
+            .iter()
+            .enumerate()
+            .map(|(i, link)| {
+                let (a, b) = link.get_nodes();
+                (a, b, link.get_text(), i)
+            })
+            .filter(|(a, b, _, _)| *b < count && !a.is_some_and(|a| a >= count))
+            .collect();
+
+        // Since every text can be read by some word, reachability is just reachability in the graph.
+        let starts: Vec<usize> = links
+            .iter()
+            .filter(|(a, ..)| a.is_none())
+            .map(|(_, b, ..)| *b)
+            .collect();
+        let edges: Vec<(usize, usize)> = links
+            .iter()
+            .filter_map(|(a, b, ..)| Some(((*a)?, *b)))
+            .collect();
+        let reached = reachable(count, &starts, &edges);
+        let accepting: Vec<usize> = (0..count).filter(|n| self.nodes[*n].accept_state).collect();
+        let reversed: Vec<(usize, usize)> = edges.iter().map(|(a, b)| (*b, *a)).collect();
+        let live = reachable(count, &accepting, &reversed);
+
+        for node in 0..count {
+            if !reached[node] {
+                lints.push(if self.nodes[node].accept_state {
+                    Lint::UnreachableAcceptState(node)
+                } else {
+                    Lint::UnreachableNode(node)
+                });
+            } else if !live[node] {
+                lints.push(Lint::DeadNode(node));
+            }
+        }
+
+        // Group the links by where they leave from.
+        let mut by_source: BTreeMap<Option<usize>, Vec<(usize, &str, usize)>> = BTreeMap::new();
+        for (a, b, text, i) in links.iter() {
+            by_source.entry(*a).or_default().push((*b, text, *i));
+        }
+
+        for (node, outgoing) in by_source.iter() {
+            let mut by_text: BTreeMap<&str, BTreeMap<usize, Vec<usize>>> = BTreeMap::new();
+            for (target, text, i) in outgoing.iter() {
+                by_text
+                    .entry(text)
+                    .or_default()
+                    .entry(*target)
+                    .or_default()
+                    .push(*i);
+            }
+            for targets in by_text.values() {
+                for group in targets.values().filter(|g| g.len() > 1) {
+                    lints.push(Lint::DuplicateLinks(group.clone()));
+                }
+                if targets.len() > 1 {
+                    lints.push(Lint::SameLabel {
+                        node: *node,
+                        links: targets.values().map(|g| g[0]).collect(),
+                    });
+                }
+            }
+
+            // Different labels where a word read by one begins a word read by the other.
+            let labels: Vec<Label> = outgoing
+                .iter()
+                .map(|(_, text, _)| Label::parse_or_literal(text))
+                .collect();
+            let mut branching: Vec<usize> = outgoing
+                .iter()
+                .zip(labels.iter())
+                .filter(|((_, text, _), label)| {
+                    outgoing
+                        .iter()
+                        .zip(labels.iter())
+                        .any(|((_, other, _), other_label)| {
+                            other != text && label.overlaps(other_label)
+                        })
+                })
+                .map(|((_, _, i), _)| *i)
+                .collect();
+            branching.sort();
+            if !branching.is_empty() {
+                lints.push(Lint::Nondeterminism {
+                    node: *node,
+                    links: branching,
+                });
+            }
+        }
+
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{drawing::test_machine, fsm::StateMachine};
+
+    use super::Lint;
+
+    /// The links from node 0 to node 1 that the lints say can be taken on the same input.
+    fn overlapping(texts: &[&str]) -> Vec<usize> {
+        let links: Vec<(usize, usize, &str)> = texts.iter().map(|text| (0, 1, *text)).collect();
+        test_machine(&[false, true], &links)
+            .lint()
+            .into_iter()
+            .find_map(|lint| match lint {
+                Lint::Nondeterminism {
+                    node: Some(0),
+                    links,
+                } => Some(links),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn overlapping_labels_are_nondeterministic() {
+        assert_eq!(overlapping(&["a,b", "b"]), [1, 2]);
+        assert_eq!(overlapping(&["a,c", "b"]), [] as [usize; 0]);
+        assert_eq!(overlapping(&["[a-c]", "b", "d"]), [1, 2]);
+        assert_eq!(overlapping(&["x", "\\e"]), [1, 2]);
+        assert_eq!(overlapping(&["x", "ε,yz"]), [1, 2]);
+        assert_eq!(overlapping(&["ab", "a", "b"]), [1, 2]);
+        assert_eq!(overlapping(&[".", "ж"]), [1, 2]);
+        assert_eq!(overlapping(&["\\.", "ж"]), [] as [usize; 0]);
+    }
+
+    #[test]
+    fn finds_unreachable_and_dead_nodes() {
+        let fsm = test_machine(
+            &[false, true, false, true, false],
+            &[(0, 1, "a"), (0, 2, "b"), (4, 3, "c")],
+        );
+        assert_eq!(
+            fsm.lint(),
+            [
+                Lint::DeadNode(2),
+                Lint::UnreachableAcceptState(3),
+                Lint::UnreachableNode(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_repeated_texts() {
+        let fsm = test_machine(
+            &[false, true, true],
+            &[(0, 1, "a"), (0, 1, "a"), (0, 2, "a")],
+        );
+        let lints = fsm.lint();
+        assert!(lints.contains(&Lint::DuplicateLinks(vec![1, 2])));
+        assert!(lints.contains(&Lint::SameLabel {
+            node: Some(0),
+            links: vec![1, 3],
+        }));
+        assert!(StateMachine::default().lint().is_empty());
+    }
+}
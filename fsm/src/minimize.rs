@@ -0,0 +1,311 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dfa::{Dfa, Nfa},
+    fsm::{FSMError, StateMachine},
+};
+
+impl Dfa {
+    /// Find the states from which at least one word is accepted.
+    pub fn live_states(&self) -> Vec<bool> {
+        let mut live = self.accept.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (state, row) in self.transitions.iter().enumerate() {
+                if !live[state] && row.iter().flatten().any(|t| live[*t]) {
+                    live[state] = true;
+                    changed = true;
+                }
+            }
+        }
+        live
+    }
+
+    /// Group the states that accept the same words, using Moore's partition refinement.
+    ///
+    /// Returns a class number for every state.
+    /// States that accept no words at all get None, the same as a missing transition.
+    pub fn equivalence_classes(&self) -> Vec<Option<usize>> {
+        let live = self.live_states();
+        let mut classes: Vec<Option<usize>> = (0..self.len())
+            .map(|s| live[s].then_some(self.accept[s] as usize))
+            .collect();
+        let mut class_count = classes.iter().flatten().collect::<BTreeSet<_>>().len();
+
+        loop {
+            let mut signatures: BTreeMap<(usize, Vec<Option<usize>>), usize> = BTreeMap::new();
+            let mut new_classes = vec![None; self.len()];
+            for (state, class) in classes.iter().enumerate() {
+                let class = match class {
+                    Some(c) => *c,
+                    None => continue,
+                };
+                let targets = self.transitions[state]
+                    .iter()
+                    .map(|t| t.and_then(|t| classes[t]))
+                    .collect();
+                let next_id = signatures.len();
+                new_classes[state] = Some(*signatures.entry((class, targets)).or_insert(next_id));
+            }
+
+            let new_count = signatures.len();
+            classes = new_classes;
+            if new_count == class_count {
+                return classes;
+            }
+            class_count = new_count;
+        }
+    }
+
+    /// The column indices in the order that canonical numberings follow:
+    /// the alphabet in order, with the [other](Dfa::other) character last,
+    /// so that which character was picked to stand for the others does not matter.
+    pub(crate) fn column_order(&self) -> Vec<usize> {
+        let other = self.other.and_then(|o| self.char_index(o));
+        (0..self.alphabet.len())
+            .filter(|ci| Some(*ci) != other)
+            .chain(other)
+            .collect()
+    }
+
+    /// Build the minimal automaton for the same language.
+    ///
+    /// Returns the automaton, and for every state of this one the state of the result that accepts the same words, if any.
+    /// States of the result are numbered in breadth-first order from the start following the [column order](Dfa::column_order).
+    /// Unused characters are dropped, and so are characters that go everywhere the other character goes,
+    /// so equal languages always give equal automata.
+    pub fn minimize(&self) -> (Dfa, Vec<Option<usize>>) {
+        let classes = self.equivalence_classes();
+        let start_class = match classes[self.start] {
+            Some(c) => c,
+            None => {
+                // Nothing is accepted: a single rejecting state stands for every dead state.
+                let mapping = classes.iter().map(|c| c.is_none().then_some(0)).collect();
+                let dfa = Dfa {
+                    alphabet: vec![],
+                    start: 0,
+                    accept: vec![false],
+                    transitions: vec![vec![]],
+                    other: None,
+                };
+                return (dfa, mapping);
+            }
+        };
+
+        // Pick a representative state for every class.
+        let mut representative = BTreeMap::new();
+        for (state, class) in classes.iter().enumerate() {
+            if let Some(c) = class {
+                representative.entry(*c).or_insert(state);
+            }
+        }
+
+        let class_row = |class: usize| -> Vec<Option<usize>> {
+            self.transitions[representative[&class]]
+                .iter()
+                .map(|t| t.and_then(|t| classes[t]))
+                .collect()
+        };
+
+        // Find the classes reachable from the start, to see which characters they use.
+        let mut reachable = BTreeSet::from([start_class]);
+        let mut queue = VecDeque::from([start_class]);
+        while let Some(class) = queue.pop_front() {
+            for target in class_row(class).into_iter().flatten() {
+                if reachable.insert(target) {
+                    queue.push_back(target);
+                }
+            }
+        }
+        let other = self.other.and_then(|o| self.char_index(o));
+        let used: Vec<bool> = (0..self.alphabet.len())
+            .map(|ci| {
+                let rows = || reachable.iter().map(|class| class_row(*class));
+                let used = rows().any(|row| row[ci].is_some());
+                let like_other =
+                    other.is_some_and(|oi| oi != ci && rows().all(|row| row[ci] == row[oi]));
+                used && !like_other
+            })
+            .collect();
+
+        // Number the classes in breadth-first order.
+        let columns: Vec<usize> = self
+            .column_order()
+            .into_iter()
+            .filter(|ci| used[*ci])
+            .collect();
+        let mut order: BTreeMap<usize, usize> = BTreeMap::from([(start_class, 0)]);
+        let mut queue = VecDeque::from([start_class]);
+        let mut class_rows = vec![];
+        while let Some(class) = queue.pop_front() {
+            let row = class_row(class);
+            for target in columns.iter().filter_map(|ci| row[*ci]) {
+                if !order.contains_key(&target) {
+                    order.insert(target, order.len());
+                    queue.push_back(target);
+                }
+            }
+            class_rows.push((class, row));
+        }
+
+        let alphabet: Vec<char> = self
+            .alphabet
+            .iter()
+            .zip(used.iter())
+            .filter(|(_, used)| **used)
+            .map(|(c, _)| *c)
+            .collect();
+        let transitions = class_rows
+            .iter()
+            .map(|(_, row)| {
+                row.iter()
+                    .zip(used.iter())
+                    .filter(|(_, used)| **used)
+                    .map(|(t, _)| t.map(|t| order[&t]))
+                    .collect()
+            })
+            .collect();
+        let accept = class_rows
+            .iter()
+            .map(|(class, _)| self.accept[representative[class]])
+            .collect();
+
+        let mapping = classes
+            .iter()
+            .map(|c| c.and_then(|c| order.get(&c).copied()))
+            .collect();
+
+        (
+            Dfa {
+                other: self.other.filter(|o| alphabet.contains(o)),
+                alphabet,
+                start: 0,
+                accept,
+                transitions,
+            },
+            mapping,
+        )
+    }
+}
+
+/// The result of minimizing a [StateMachine].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Minimization {
+    /// The minimal deterministic machine for the same language.
+    pub machine: StateMachine,
+
+    /// For every node of the original machine: the node of the minimal machine that accepts the same words, if there is one.
+    ///
+    /// Nodes with no such counterpart are usually unreachable, or cannot reach an accepting node.
+    pub node_classes: Vec<Option<usize>>,
+
+    /// Groups of original nodes that accept exactly the same words.
+    /// Only groups with more than one node are listed.
+    pub indistinguishable: Vec<Vec<usize>>,
+}
+
+impl StateMachine {
+    /// Build the minimal deterministic machine for this machine's language,
+    /// and find out which of this machine's nodes are indistinguishable.
+    pub fn minimize(&self) -> Result<Minimization, FSMError> {
+        if let Some(err) = self.check_error() {
+            return Err(err);
+        }
+        let nfa = Nfa::from_state_machine(self);
+
+        // Determinize from every node at once, so that every node's language is a state we can classify.
+        let mut roots = vec![nfa.start];
+        roots.extend(0..self.nodes.len());
+        let (dfa, _, root_states) = Dfa::from_nfa_roots(&nfa, &roots)?;
+        let classes = dfa.equivalence_classes();
+        let (minimal, mapping) = dfa.minimize();
+
+        let node_states = &root_states[1..];
+        let node_classes: Vec<Option<usize>> = node_states.iter().map(|s| mapping[*s]).collect();
+
+        let mut groups: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+        for (node, state) in node_states.iter().enumerate() {
+            groups.entry(classes[*state]).or_default().push(node);
+        }
+        let indistinguishable = groups.into_values().filter(|g| g.len() > 1).collect();
+
+        let names = (0..minimal.len())
+            .map(|state| {
+                let originals = node_classes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| **c == Some(state))
+                    .map(|(node, _)| node);
+                self.node_set_name(originals)
+            })
+            .collect();
+
+        Ok(Minimization {
+            machine: minimal.to_state_machine_with_names(names)?,
+            node_classes,
+            indistinguishable,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fsm::StateMachine, regex::Regex};
+
+    #[test]
+    fn merges_indistinguishable_nodes() {
+        let fsm = StateMachine::from_formal(
+            "states: q0, q1, q2, q3
+             alphabet: a, b
+             start: q0
+             accept: q3
+             transitions:
+                 q0 -a-> q1
+                 q0 -b-> q2
+                 q1 -a,b-> q3
+                 q2 -a,b-> q3
+                 q3 -a,b-> q3",
+        )
+        .unwrap();
+        let minimization = fsm.minimize().unwrap();
+        assert_eq!(minimization.machine.nodes.len(), 3);
+        assert_eq!(minimization.indistinguishable, vec![vec![1, 2]]);
+        assert_eq!(minimization.node_classes[1], minimization.node_classes[2]);
+        assert_ne!(minimization.node_classes[0], minimization.node_classes[3]);
+    }
+
+    #[test]
+    fn minimal_machines_accept_the_same_words() {
+        for regex in [
+            "(a|b)*abb",
+            "a*b*",
+            "(ab|ba)+",
+            "a?b?c?",
+            "[0-9]+(\\.[0-9]+)?",
+            ".a",
+            "∅",
+        ] {
+            let fsm = Regex::parse(regex).unwrap().to_state_machine();
+            let minimal = fsm.minimize().unwrap().machine;
+            assert_eq!(fsm.distinguishing_word(&minimal), Ok(None), "{regex}");
+
+            // Minimizing again, or minimizing the determinized machine, gives nothing smaller.
+            let size = minimal.nodes.len();
+            assert_eq!(
+                minimal.minimize().unwrap().machine.nodes.len(),
+                size,
+                "{regex}"
+            );
+            let determinized = fsm.determinize().unwrap();
+            assert!(determinized.nodes.len() >= size, "{regex}");
+            assert_eq!(
+                determinized.minimize().unwrap().machine.nodes.len(),
+                size,
+                "{regex}"
+            );
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::{
+    dfa::Dfa,
+    fsm::{FSMError, StateMachine},
+};
+
+impl Dfa {
+    /// Run both automata side by side, accepting a word when `accept` says so for the two outputs.
+    ///
+    /// A missing transition on one side is followed as a rejecting sink, so `accept(false, false)` must be false.
+    /// Both automata must have been built over the same characters, like [StateMachine::to_dfa_over] does,
+    /// so that they agree on their [other](Dfa::other) character.
+    pub fn product(&self, other: &Dfa, accept: impl Fn(bool, bool) -> bool) -> Dfa {
+        let alphabet: Vec<char> = self
+            .alphabet
+            .iter()
+            .chain(other.alphabet.iter())
+            .copied()
+            .collect::<BTreeSet<char>>()
+            .into_iter()
+            .collect();
+        let accepts = |dfa: &Dfa, state: Option<usize>| state.is_some_and(|s| dfa.accept[s]);
+
+        type Pair = (Option<usize>, Option<usize>);
+        let start: Pair = (Some(self.start), Some(other.start));
+        let mut known: BTreeMap<Pair, usize> = BTreeMap::from([(start, 0)]);
+        let mut pairs = vec![start];
+        let mut transitions = vec![];
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(pair) = queue.pop_front() {
+            let row = alphabet
+                .iter()
+                .map(|c| {
+                    let next = (
+                        pair.0.and_then(|s| self.step(s, *c)),
+                        pair.1.and_then(|s| other.step(s, *c)),
+                    );
+                    if next == (None, None) {
+                        return None;
+                    }
+                    Some(*known.entry(next).or_insert_with(|| {
+                        pairs.push(next);
+                        queue.push_back(next);
+                        pairs.len() - 1
+                    }))
+                })
+                .collect();
+            transitions.push(row);
+        }
+
+        Dfa {
+            alphabet,
+            start: 0,
+            other: self.other.or(other.other),
+            accept: pairs
+                .iter()
+                .map(|(a, b)| accept(accepts(self, *a), accepts(other, *b)))
+                .collect(),
+            transitions,
+        }
+    }
+
+    /// Build the automaton accepting exactly the words over `alphabet` that this one rejects.
+    ///
+    /// Characters outside of `alphabet` are dropped.
+    pub fn complement(&self, alphabet: &[char]) -> Dfa {
+        let alphabet: Vec<char> = alphabet
+            .iter()
+            .copied()
+            .collect::<BTreeSet<char>>()
+            .into_iter()
+            .collect();
+        let sink = self.len();
+        let mut transitions: Vec<Vec<Option<usize>>> = (0..self.len())
+            .map(|state| {
+                alphabet
+                    .iter()
+                    .map(|c| Some(self.step(state, *c).unwrap_or(sink)))
+                    .collect()
+            })
+            .collect();
+        transitions.push(vec![Some(sink); alphabet.len()]);
+        let mut accept: Vec<bool> = self.accept.iter().map(|a| !a).collect();
+        accept.push(true);
+
+        Dfa {
+            alphabet,
+            start: self.start,
+            accept,
+            transitions,
+            other: None,
+        }
+    }
+}
+
+impl StateMachine {
+    fn product(
+        &self,
+        other: &StateMachine,
+        accept: impl Fn(bool, bool) -> bool,
+    ) -> Result<StateMachine, FSMError> {
+        let mine = self.to_dfa_over(&other.label_alphabet())?;
+        let product = mine.product(&other.to_dfa_over(&self.label_alphabet())?, accept);
+        product.minimize().0.to_state_machine()
+    }
+
+    /// Build a machine accepting the words that either machine accepts.
+    pub fn union(&self, other: &StateMachine) -> Result<StateMachine, FSMError> {
+        self.product(other, |a, b| a || b)
+    }
+
+    /// Build a machine accepting the words that both machines accept.
+    pub fn intersection(&self, other: &StateMachine) -> Result<StateMachine, FSMError> {
+        self.product(other, |a, b| a && b)
+    }
+
+    /// Build a machine accepting the words that this machine accepts and the other one does not.
+    pub fn difference(&self, other: &StateMachine) -> Result<StateMachine, FSMError> {
+        self.product(other, |a, b| a && !b)
+    }
+
+    /// Build a machine accepting the words that exactly one of the machines accepts.
+    ///
+    /// It accepts nothing if and only if the machines are equivalent.
+    pub fn symmetric_difference(&self, other: &StateMachine) -> Result<StateMachine, FSMError> {
+        self.product(other, |a, b| a != b)
+    }
+
+    /// Build a machine accepting the words made of characters in `alphabet` that this machine rejects.
+    pub fn complement(&self, alphabet: &[char]) -> Result<StateMachine, FSMError> {
+        let complement = self.to_dfa_over(alphabet)?.complement(alphabet);
+        complement.minimize().0.to_state_machine()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{counting::LanguageSize, fsm::StateMachine, regex::Regex};
+
+    fn compile(regex: &str) -> StateMachine {
+        Regex::parse(regex).unwrap().to_state_machine()
+    }
+
+    fn assert_same_language(fsm: StateMachine, regex: &str) {
+        assert_eq!(
+            fsm.distinguishing_word(&compile(regex)),
+            Ok(None),
+            "{regex}"
+        );
+    }
+
+    #[test]
+    fn combine_languages() {
+        let (a, b) = (compile("a*"), compile("b*"));
+        assert_same_language(a.union(&b).unwrap(), "a*|b*");
+        assert_same_language(a.intersection(&b).unwrap(), "()");
+
+        let has_a = compile("(a|b)*a(a|b)*");
+        let has_b = compile("(a|b)*b(a|b)*");
+        assert_same_language(
+            has_a.intersection(&has_b).unwrap(),
+            "(a|b)*(a(a|b)*b|b(a|b)*a)(a|b)*",
+        );
+        assert_same_language(has_a.difference(&has_b).unwrap(), "aa*");
+        assert_same_language(has_a.symmetric_difference(&has_b).unwrap(), "aa*|bb*");
+    }
+
+    #[test]
+    fn wildcards_meet_written_characters() {
+        assert_same_language(compile(".a").intersection(&compile("b.")).unwrap(), "ba");
+        assert_same_language(compile("a").difference(&compile(".")).unwrap(), "∅");
+    }
+
+    #[test]
+    fn equivalent_machines_differ_nowhere() {
+        let difference = compile("(ab)*a")
+            .symmetric_difference(&compile("a(ba)*"))
+            .unwrap();
+        assert_eq!(difference.language_size(), Ok(LanguageSize::Empty));
+    }
+
+    #[test]
+    fn complements_cover_the_rest_of_the_alphabet() {
+        assert_same_language(
+            compile("a*").complement(&['a', 'b']).unwrap(),
+            "(a|b)*b(a|b)*",
+        );
+        let complement = compile("ab").complement(&['a', 'b']).unwrap();
+        let words: Vec<String> = complement.accepted_words(2, usize::MAX).unwrap().collect();
+        assert_eq!(words, ["", "a", "b", "aa", "ba", "bb"]);
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    compiled::CompiledMachine,
+    fsm::{FSMError, FSMOutput, Link, StateMachine},
+    label::{Label, LabelError},
+};
+
+/// The deepest a stack may grow: configurations with deeper stacks are dropped,
+/// so that links pushing in a loop cannot run forever.
+/// A word that is not accepted after dropping some is reported as [FSMError::StackTooDeep], not rejected.
+pub const MAX_STACK_DEPTH: usize = 256;
+
+/// The most configurations one evaluation may visit before it gives up with [FSMError::TooManyConfigurations].
+pub const MAX_CONFIGURATIONS: usize = 1 << 16;
+
+/// When a pushdown automaton accepts a word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PushdownAcceptance {
+    /// The word is read completely, ending in an accepting node.
+    FinalState,
+
+    /// The word is read completely, ending with an empty stack, in any node.
+    EmptyStack,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PushdownLabelError {
+    #[error("the label has no `→` (or `->`) before what it pushes")]
+    MissingArrow,
+
+    #[error("the label has no `,` between its input and what it pops")]
+    MissingComma,
+
+    #[error("only one symbol can be popped, but the label pops `{0}`")]
+    LongPop(String),
+
+    #[error("the input cannot be read as a label: {0}")]
+    Input(#[from] LabelError),
+}
+
+/// The label of a pushdown automaton's link, written `input, pop → push`.
+///
+/// The input is an ordinary [Label]. The pop is a single stack symbol, or nothing if left empty;
+/// the push is a string of symbols, the first of which ends up on top of the stack.
+/// Either can be written as `\e` or `ε` to mean nothing.
+/// A link with no text at all reads nothing and leaves the stack alone, like the usual start link.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PushdownLabel {
+    pub input: Label,
+    pub pop: Option<char>,
+    pub push: Vec<char>,
+}
+
+/// The stack part of a label, with the explicit epsilon written as nothing.
+fn stack_text(text: &str) -> &str {
+    match text.trim() {
+        "\\e" | "ε" => "",
+        other => other,
+    }
+}
+
+impl PushdownLabel {
+    /// Split a link's text into the input label's text, and the text of the pop and push parts.
+    fn split(text: &str) -> Result<(&str, &str, &str), PushdownLabelError> {
+        if text.trim().is_empty() {
+            return Ok(("", "", ""));
+        }
+        let (before, push) = text
+            .rsplit_once('→')
+            .or_else(|| text.rsplit_once("->"))
+            .ok_or(PushdownLabelError::MissingArrow)?;
+        let (input, pop) = before
+            .rsplit_once(',')
+            .ok_or(PushdownLabelError::MissingComma)?;
+        Ok((input.trim(), stack_text(pop), stack_text(push)))
+    }
+
+    /// The stack after taking a link with this label, with the top at the end;
+    /// None if the label pops a symbol that is not on top.
+    pub fn apply(&self, stack: &str) -> Option<String> {
+        let mut stack = stack.to_string();
+        if let Some(pop) = self.pop {
+            if stack.pop() != Some(pop) {
+                return None;
+            }
+        }
+        stack.extend(self.push.iter().rev());
+        Some(stack)
+    }
+}
+
+impl FromStr for PushdownLabel {
+    type Err = PushdownLabelError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (input, pop, push) = Self::split(text)?;
+        let mut pop_chars = pop.chars();
+        let pop = match (pop_chars.next(), pop_chars.next()) {
+            (None, _) => None,
+            (Some(c), None) => Some(c),
+            (Some(_), Some(_)) => return Err(PushdownLabelError::LongPop(pop.to_string())),
+        };
+        Ok(PushdownLabel {
+            input: input.parse()?,
+            pop,
+            push: push.chars().collect(),
+        })
+    }
+}
+
+impl Display for PushdownLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pop = self.pop.map_or(String::from("ε"), String::from);
+        let push = if self.push.is_empty() {
+            String::from("ε")
+        } else {
+            self.push.iter().collect()
+        };
+        let input = match self.input.to_string() {
+            input if input.is_empty() => String::from("ε"),
+            input => input,
+        };
+        write!(f, "{input}, {pop} → {push}")
+    }
+}
+
+/// A [StateMachine] read as a pushdown automaton, prepared for running many words through it.
+#[derive(Clone, Debug)]
+pub struct CompiledPushdown {
+    inputs: CompiledMachine,
+    labels: Vec<PushdownLabel>,
+    acceptance: PushdownAcceptance,
+}
+
+impl CompiledPushdown {
+    /// Prepare the machine, which must have been checked with [StateMachine::check_pushdown_error].
+    pub fn new(fsm: &StateMachine, acceptance: PushdownAcceptance) -> Result<Self, FSMError> {
+        Ok(CompiledPushdown {
+            inputs: CompiledMachine::new(&fsm.pushdown_input_machine()),
+            labels: fsm.pushdown_labels()?,
+            acceptance,
+        })
+    }
+
+    /// Whether a configuration that has read the whole word accepts it.
+    pub fn accepts(&self, node: usize, stack: &str) -> bool {
+        match self.acceptance {
+            PushdownAcceptance::FinalState => self.inputs.is_accepting(node),
+            PushdownAcceptance::EmptyStack => stack.is_empty(),
+        }
+    }
+
+    /// The links that can be taken from the node, with this much of the word left and this stack:
+    /// each with how much of the word it reads, and the stack after it.
+    ///
+    /// The stacks are not checked against [MAX_STACK_DEPTH].
+    pub fn links_matching(
+        &self,
+        node: Option<usize>,
+        rest: &str,
+        stack: &str,
+    ) -> Vec<(usize, usize, String)> {
+        self.inputs
+            .links_matching(node, rest)
+            .into_iter()
+            .filter_map(|(link, length)| Some((link, length, self.labels[link].apply(stack)?)))
+            .collect()
+    }
+
+    pub fn target(&self, link: usize) -> usize {
+        self.inputs.target(link)
+    }
+
+    /// Run the word through the automaton, starting with an empty stack.
+    ///
+    /// If the word is not accepted, but some configuration had too deep a stack,
+    /// or there were too many configurations to look through, this is an error instead of a rejection.
+    pub fn evaluate(&self, word: &str) -> Result<FSMOutput, FSMError> {
+        // Every (node, position, stack) is explored once; the first one is before the start links.
+        let mut seen: HashSet<(Option<usize>, usize, String)> = HashSet::new();
+        let mut queue = VecDeque::from([(None, 0, String::new())]);
+        let mut too_deep = false;
+        while let Some((node, position, stack)) = queue.pop_front() {
+            if node.is_some_and(|node| position == word.len() && self.accepts(node, &stack)) {
+                return Ok(FSMOutput::Accept);
+            }
+            if seen.len() > MAX_CONFIGURATIONS {
+                log::warn!("Pushdown evaluation of {word:?} gave up after {MAX_CONFIGURATIONS} configurations");
+                return Err(FSMError::TooManyConfigurations(MAX_CONFIGURATIONS));
+            }
+            for (link, length, stack) in self.links_matching(node, &word[position..], &stack) {
+                if stack.chars().count() > MAX_STACK_DEPTH {
+                    too_deep = true;
+                    continue;
+                }
+                let state = (Some(self.target(link)), position + length, stack);
+                if seen.insert(state.clone()) {
+                    queue.push_back(state);
+                }
+            }
+        }
+        if too_deep {
+            return Err(FSMError::StackTooDeep(MAX_STACK_DEPTH));
+        }
+        Ok(FSMOutput::Reject)
+    }
+}
+
+impl StateMachine {
+    /// Read the text of every link as a [PushdownLabel].
+    pub fn pushdown_labels(&self) -> Result<Vec<PushdownLabel>, FSMError> {
+        self.links
+            .iter()
+            .enumerate()
+            .map(|(i, link)| {
+                link.get_text()
+                    .parse()
+                    .map_err(|why| FSMError::InvalidPushdownLabel(i, why))
+            })
+            .collect()
+    }
+
+    /// The machine reading this pushdown automaton's inputs, with the stack parts removed from the links.
+    ///
+    /// Nodes and links keep their indices. Links whose text cannot be split keep it whole.
+    pub fn pushdown_input_machine(&self) -> StateMachine {
+        let mut inputs = self.clone();
+        for link in inputs.links.iter_mut() {
+            let text = match link {
+                Link::NormalLink { text, .. }
+                | Link::StartLink { text, .. }
+                | Link::SelfLink { text, .. } => text,
+            };
+            if let Ok((input, _, _)) = PushdownLabel::split(text) {
+                *text = input.to_string();
+            }
+        }
+        inputs
+    }
+
+    /// Check this machine as a pushdown automaton.
+    ///
+    /// Loops of empty links are allowed: they may change the stack,
+    /// and evaluation visits every configuration only once anyway.
+    pub fn check_pushdown_error(&self) -> Option<FSMError> {
+        if let Err(err) = self.pushdown_labels() {
+            return Some(err);
+        }
+        match self.pushdown_input_machine().check_error() {
+            Some(FSMError::InfiniteLoop) => None,
+            other => other,
+        }
+    }
+
+    /// Run the word through this machine as a pushdown automaton, starting with an empty stack.
+    pub fn evaluate_pushdown(
+        &self,
+        word: &str,
+        acceptance: PushdownAcceptance,
+    ) -> Result<FSMOutput, FSMError> {
+        if let Some(err) = self.check_pushdown_error() {
+            return Err(err);
+        }
+        CompiledPushdown::new(self, acceptance)?.evaluate(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        drawing::test_machine,
+        fsm::{FSMError, FSMOutput, StateMachine},
+        tester::{FSMTester, FSMTestingOutput},
+    };
+
+    use super::{PushdownAcceptance, MAX_STACK_DEPTH};
+
+    /// Accepts aⁿbⁿ by emptying its stack.
+    fn a_n_b_n() -> StateMachine {
+        test_machine(
+            &[false, false],
+            &[
+                (0, 0, "a, \\e → A"),
+                (0, 1, "\\e, \\e → \\e"),
+                (1, 1, "b, A → \\e"),
+            ],
+        )
+    }
+
+    #[test]
+    fn counts_with_the_stack() {
+        let pda = a_n_b_n();
+        for (word, output) in [
+            ("", FSMOutput::Accept),
+            ("ab", FSMOutput::Accept),
+            ("aaabbb", FSMOutput::Accept),
+            ("aab", FSMOutput::Reject),
+            ("abb", FSMOutput::Reject),
+            ("ba", FSMOutput::Reject),
+        ] {
+            assert_eq!(
+                pda.evaluate_pushdown(word, PushdownAcceptance::EmptyStack),
+                Ok(output),
+                "{word:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn deep_stacks_are_an_error_not_a_rejection() {
+        let pushing = test_machine(&[false], &[(0, 0, "\\e, \\e → A")]);
+        assert_eq!(
+            pushing.evaluate_pushdown("a", PushdownAcceptance::FinalState),
+            Err(FSMError::StackTooDeep(MAX_STACK_DEPTH))
+        );
+    }
+
+    #[test]
+    fn tasks_can_ask_for_a_pushdown_automaton() {
+        let script = r#"
+            fn pushdown_acceptance() { "empty stack" }
+            fn gen_word(ok) {
+                let word = "";
+                let n = rng.gen_range(0, 6);
+                for i in 0..n { word = "a" + word + "b"; }
+                if !ok { word += "a"; }
+                word
+            }
+            fn check_word(word) {
+                let expected = "";
+                for i in 0..word.len() / 2 { expected = "a" + expected + "b"; }
+                word == expected
+            }
+        "#;
+        assert_eq!(
+            FSMTester::script_pushdown_acceptance(script),
+            Some(PushdownAcceptance::EmptyStack)
+        );
+        let mut tester = FSMTester::new(a_n_b_n(), script).unwrap();
+        assert!(matches!(
+            tester.run_testing(0).unwrap(),
+            FSMTestingOutput::Ok(_)
+        ));
+    }
+}
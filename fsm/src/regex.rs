@@ -0,0 +1,761 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Display,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    drawing::{assemble, column_positions, epsilon_loop_representatives, loop_text},
+    fsm::{FSMError, StateMachine},
+    label::Label,
+};
+
+/// A regular expression over characters.
+///
+/// Syntax: `a|b` is union, `ab` is concatenation, `a*`, `a+` and `a?` are repetitions,
+/// `[a-z0-9_]` is a character class, `.` is any character, `ε` (or `()`) is the empty word, `∅` matches nothing,
+/// and `\` escapes any of the special characters.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Regex {
+    /// Matches no words at all
+    Nothing,
+    /// Matches only the empty word
+    Epsilon,
+    /// Matches one character from any of these inclusive ranges
+    Class(Vec<(char, char)>),
+    /// Matches any one character
+    Any,
+    Concat(Vec<Regex>),
+    Union(Vec<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RegexError {
+    #[error("unexpected character {1:?} at position {0}")]
+    UnexpectedChar(usize, char),
+
+    #[error("unexpected end of the expression")]
+    UnexpectedEnd,
+
+    #[error("the character class at position {0} is empty")]
+    EmptyClass(usize),
+
+    #[error("the character class at position {0} is negated, which is not supported")]
+    NegatedClass(usize),
+
+    #[error("the range at position {0} goes backwards")]
+    BackwardsRange(usize),
+
+    #[error("the character class at position {0} has too many characters")]
+    ClassTooLarge(usize),
+}
+
+/// The most characters a single class may expand into.
+const MAX_CLASS_SIZE: usize = 256;
+
+const SPECIAL_CHARS: &[char] = &['|', '*', '+', '?', '(', ')', '[', ']', '.', '\\', 'ε', '∅'];
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<char, RegexError> {
+        let c = self.peek().ok_or(RegexError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn union(&mut self) -> Result<Regex, RegexError> {
+        let mut options = vec![self.concat()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            options.push(self.concat()?);
+        }
+        Ok(if options.len() == 1 {
+            options.pop().unwrap()
+        } else {
+            Regex::Union(options)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Regex, RegexError> {
+        let mut parts = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            parts.push(self.repeat()?);
+        }
+        Ok(match parts.len() {
+            0 => Regex::Epsilon,
+            1 => parts.pop().unwrap(),
+            _ => Regex::Concat(parts),
+        })
+    }
+
+    fn repeat(&mut self) -> Result<Regex, RegexError> {
+        let mut inner = self.atom()?;
+        loop {
+            inner = match self.peek() {
+                Some('*') => Regex::Star(Box::new(inner)),
+                Some('+') => Regex::Plus(Box::new(inner)),
+                Some('?') => Regex::Optional(Box::new(inner)),
+                _ => return Ok(inner),
+            };
+            self.pos += 1;
+        }
+    }
+
+    fn atom(&mut self) -> Result<Regex, RegexError> {
+        let pos = self.pos;
+        match self.next()? {
+            '(' => {
+                let inner = self.union()?;
+                match self.next()? {
+                    ')' => Ok(inner),
+                    c => Err(RegexError::UnexpectedChar(self.pos - 1, c)),
+                }
+            }
+            '[' => self.class(pos),
+            'ε' => Ok(Regex::Epsilon),
+            '∅' => Ok(Regex::Nothing),
+            '.' => Ok(Regex::Any),
+            '\\' => {
+                let c = self.next()?;
+                Ok(Regex::Class(vec![(c, c)]))
+            }
+            c if SPECIAL_CHARS.contains(&c) => Err(RegexError::UnexpectedChar(pos, c)),
+            c => Ok(Regex::Class(vec![(c, c)])),
+        }
+    }
+
+    fn class_char(&mut self) -> Result<char, RegexError> {
+        match self.next()? {
+            '\\' => self.next(),
+            c => Ok(c),
+        }
+    }
+
+    fn class(&mut self, start: usize) -> Result<Regex, RegexError> {
+        if self.peek() == Some('^') {
+            return Err(RegexError::NegatedClass(start));
+        }
+        let mut ranges = vec![];
+        loop {
+            match self.peek() {
+                None => return Err(RegexError::UnexpectedEnd),
+                Some(']') => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => {}
+            }
+            let range_pos = self.pos;
+            let from = self.class_char()?;
+            // A dash is a range, unless it is the last character in the class.
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let to = self.class_char()?;
+                if to < from {
+                    return Err(RegexError::BackwardsRange(range_pos));
+                }
+                ranges.push((from, to));
+            } else {
+                ranges.push((from, from));
+            }
+        }
+        if ranges.is_empty() {
+            return Err(RegexError::EmptyClass(start));
+        }
+        let size: usize = ranges
+            .iter()
+            .map(|(a, b)| *b as usize - *a as usize + 1)
+            .sum();
+        if size > MAX_CLASS_SIZE {
+            return Err(RegexError::ClassTooLarge(start));
+        }
+        Ok(Regex::Class(ranges))
+    }
+}
+
+impl FromStr for Regex {
+    type Err = RegexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+        };
+        let regex = parser.union()?;
+        match parser.peek() {
+            None => Ok(regex),
+            Some(c) => Err(RegexError::UnexpectedChar(parser.pos, c)),
+        }
+    }
+}
+
+impl Regex {
+    pub fn parse(text: &str) -> Result<Self, RegexError> {
+        text.parse()
+    }
+
+    /// Binding strength, used to decide where parentheses are needed when printing.
+    fn precedence(&self) -> u8 {
+        match self {
+            Regex::Union(_) => 0,
+            Regex::Concat(_) => 1,
+            Regex::Star(_) | Regex::Plus(_) | Regex::Optional(_) => 2,
+            Regex::Nothing | Regex::Epsilon | Regex::Class(_) | Regex::Any => 3,
+        }
+    }
+
+    fn fmt_wrapped(&self, f: &mut std::fmt::Formatter<'_>, min_precedence: u8) -> std::fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+fn fmt_char(f: &mut std::fmt::Formatter<'_>, c: char, in_class: bool) -> std::fmt::Result {
+    let special = if in_class {
+        matches!(c, ']' | '\\' | '-' | '^')
+    } else {
+        SPECIAL_CHARS.contains(&c)
+    };
+    if special {
+        write!(f, "\\{c}")
+    } else {
+        write!(f, "{c}")
+    }
+}
+
+impl Display for Regex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Regex::Nothing => write!(f, "∅"),
+            Regex::Epsilon => write!(f, "ε"),
+            Regex::Any => write!(f, "."),
+            Regex::Class(ranges) => {
+                if let [(a, b)] = ranges.as_slice() {
+                    if a == b {
+                        return fmt_char(f, *a, false);
+                    }
+                }
+                write!(f, "[")?;
+                for (a, b) in ranges {
+                    fmt_char(f, *a, true)?;
+                    // Two neighbouring characters read better without a dash.
+                    if *b as u32 == *a as u32 + 1 {
+                        fmt_char(f, *b, true)?;
+                    } else if a != b {
+                        write!(f, "-")?;
+                        fmt_char(f, *b, true)?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Regex::Concat(parts) => {
+                for part in parts {
+                    part.fmt_wrapped(f, 2)?;
+                }
+                Ok(())
+            }
+            Regex::Union(options) => {
+                for (i, option) in options.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+                    option.fmt_wrapped(f, 1)?;
+                }
+                Ok(())
+            }
+            Regex::Star(inner) => {
+                inner.fmt_wrapped(f, 3)?;
+                write!(f, "*")
+            }
+            Regex::Plus(inner) => {
+                inner.fmt_wrapped(f, 3)?;
+                write!(f, "+")
+            }
+            Regex::Optional(inner) => {
+                inner.fmt_wrapped(f, 3)?;
+                write!(f, "?")
+            }
+        }
+    }
+}
+
+/// Thompson's construction, where every fragment has one entry state without incoming edges
+/// and one exit state without outgoing edges.
+#[derive(Default)]
+struct Thompson {
+    states: usize,
+    /// (from, to, label); an empty label is an epsilon edge.
+    edges: Vec<(usize, usize, String)>,
+}
+
+impl Thompson {
+    fn new_state(&mut self) -> usize {
+        self.states += 1;
+        self.states - 1
+    }
+
+    fn epsilon(&mut self, from: usize, to: usize) {
+        self.edges.push((from, to, String::new()));
+    }
+
+    /// Build a fragment for the expression, returning its entry and exit states.
+    fn build(&mut self, regex: &Regex) -> (usize, usize) {
+        match regex {
+            Regex::Nothing => (self.new_state(), self.new_state()),
+            Regex::Epsilon => {
+                let s = self.new_state();
+                (s, s)
+            }
+            Regex::Class(ranges) => {
+                let (s, e) = (self.new_state(), self.new_state());
+                let chars: Vec<char> = ranges.iter().flat_map(|(a, b)| *a..=*b).collect();
+                self.edges
+                    .push((s, e, Label::from_chars(&chars).to_string()));
+                (s, e)
+            }
+            Regex::Any => {
+                let (s, e) = (self.new_state(), self.new_state());
+                self.edges.push((s, e, ".".to_string()));
+                (s, e)
+            }
+            Regex::Concat(parts) => {
+                let mut result: Option<(usize, usize)> = None;
+                for part in parts {
+                    let (s, e) = self.build(part);
+                    result = Some(match result {
+                        None => (s, e),
+                        Some((rs, re)) => {
+                            // Glue the fragments together by merging the first one's exit with the second one's entry.
+                            for edge in self.edges.iter_mut() {
+                                if edge.0 == s {
+                                    edge.0 = re;
+                                }
+                            }
+                            (rs, if e == s { re } else { e })
+                        }
+                    });
+                }
+                result.unwrap_or_else(|| self.build(&Regex::Epsilon))
+            }
+            Regex::Union(options) => {
+                let (s, e) = (self.new_state(), self.new_state());
+                for option in options {
+                    let (os, oe) = self.build(option);
+                    self.epsilon(s, os);
+                    self.epsilon(oe, e);
+                }
+                (s, e)
+            }
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                let (s, e) = (self.new_state(), self.new_state());
+                let (is, ie) = self.build(inner);
+                self.epsilon(s, is);
+                self.epsilon(ie, e);
+                if !matches!(regex, Regex::Optional(_)) {
+                    self.epsilon(ie, is);
+                }
+                if !matches!(regex, Regex::Plus(_)) {
+                    self.epsilon(s, e);
+                }
+                (s, e)
+            }
+        }
+    }
+}
+
+impl Regex {
+    /// Compile this expression into a [StateMachine] using Thompson's construction.
+    ///
+    /// Nodes that are joined into a loop of epsilon links are merged into one,
+    /// so that the result always passes [StateMachine::check_error].
+    pub fn to_state_machine(&self) -> StateMachine {
+        let mut thompson = Thompson::default();
+        let (start, end) = thompson.build(self);
+
+        // Merge the nodes that are joined into loops of epsilon edges.
+        let representative = epsilon_loop_representatives(thompson.states, &thompson.edges);
+        let mut edges: Vec<(usize, usize, String)> = vec![];
+        for (a, b, text) in thompson.edges.iter() {
+            let (a, b) = (representative[*a], representative[*b]);
+            let text = if a == b {
+                match loop_text(text) {
+                    Some(text) => text,
+                    None => continue,
+                }
+            } else {
+                text.clone()
+            };
+            let edge = (a, b, text);
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+        let (start, end) = (representative[start], representative[end]);
+
+        // Keep the states reachable from the start, numbered in breadth-first order.
+        let mut number = vec![None; thompson.states];
+        number[start] = Some(0);
+        let mut order = vec![start];
+        let mut queue = VecDeque::from([start]);
+        while let Some(state) = queue.pop_front() {
+            for (a, b, _) in edges.iter() {
+                if *a == state && number[*b].is_none() {
+                    number[*b] = Some(order.len());
+                    order.push(*b);
+                    queue.push_back(*b);
+                }
+            }
+        }
+        let edges: Vec<(usize, usize, String)> = edges
+            .into_iter()
+            .filter_map(|(a, b, text)| Some((number[a]?, number[b]?, text)))
+            .collect();
+        let accept: Vec<bool> = order.iter().map(|s| *s == end).collect();
+
+        let pairs: Vec<(usize, usize)> = edges.iter().map(|(a, b, _)| (*a, *b)).collect();
+        let positions = column_positions(order.len(), 0, &pairs);
+        let names = vec![String::new(); order.len()];
+        assemble(&positions, names, &accept, vec![(0, String::new())], edges)
+    }
+}
+
+impl Regex {
+    /// Whether this expression matches the empty word.
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            Regex::Nothing | Regex::Class(_) | Regex::Any => false,
+            Regex::Epsilon | Regex::Star(_) | Regex::Optional(_) => true,
+            Regex::Plus(inner) => inner.is_nullable(),
+            Regex::Concat(parts) => parts.iter().all(|p| p.is_nullable()),
+            Regex::Union(options) => options.iter().any(|o| o.is_nullable()),
+        }
+    }
+
+    /// The expression matching exactly this text.
+    pub fn literal(text: &str) -> Regex {
+        text.chars()
+            .map(|c| Regex::Class(vec![(c, c)]))
+            .fold(Regex::Epsilon, Regex::concat)
+    }
+
+    /// Build `a|b`, simplifying where possible.
+    pub fn union(a: Regex, b: Regex) -> Regex {
+        let mut options = vec![];
+        let mut ranges = vec![];
+        let mut has_epsilon = false;
+        let mut has_any = false;
+        for option in [a, b] {
+            let parts = match option {
+                Regex::Union(parts) => parts,
+                other => vec![other],
+            };
+            for part in parts {
+                match part {
+                    Regex::Nothing => {}
+                    Regex::Epsilon => has_epsilon = true,
+                    Regex::Class(r) => ranges.extend(r),
+                    Regex::Any => has_any = true,
+                    other => options.push(other),
+                }
+            }
+        }
+
+        // Any character covers every class; otherwise, join all single characters into one class,
+        // merging adjacent ranges.
+        if has_any {
+            ranges.clear();
+            options.push(Regex::Any);
+        }
+        ranges.sort();
+        let mut merged: Vec<(char, char)> = vec![];
+        for (from, to) in ranges {
+            match merged.last_mut() {
+                Some(last) if from as u32 <= last.1 as u32 + 1 => last.1 = last.1.max(to),
+                _ => merged.push((from, to)),
+            }
+        }
+        if !merged.is_empty() {
+            options.push(Regex::Class(merged));
+        }
+        options.sort();
+        options.dedup();
+
+        let result = match options.len() {
+            0 => {
+                return if has_epsilon {
+                    Regex::Epsilon
+                } else {
+                    Regex::Nothing
+                }
+            }
+            1 => options.pop().unwrap(),
+            _ => Regex::Union(options),
+        };
+        match result {
+            _ if !has_epsilon || result.is_nullable() => result,
+            Regex::Plus(inner) => Regex::Star(inner),
+            other => Regex::Optional(Box::new(other)),
+        }
+    }
+
+    /// Build `ab`, simplifying where possible.
+    pub fn concat(a: Regex, b: Regex) -> Regex {
+        let mut parts: Vec<Regex> = vec![];
+        for part in [a, b] {
+            let pieces = match part {
+                Regex::Concat(pieces) => pieces,
+                other => vec![other],
+            };
+            for piece in pieces {
+                match piece {
+                    Regex::Nothing => return Regex::Nothing,
+                    Regex::Epsilon => {}
+                    // `x x*` and `x* x` are both `x+`
+                    Regex::Star(ref inner) if parts.last() == Some(&**inner) => {
+                        let inner = parts.pop().unwrap();
+                        parts.push(Regex::Plus(Box::new(inner)));
+                    }
+                    ref other if matches!(parts.last(), Some(Regex::Star(inner)) if **inner == *other) =>
+                    {
+                        let Some(Regex::Star(inner)) = parts.pop() else {
+                            unreachable!()
+                        };
+                        parts.push(Regex::Plus(inner));
+                    }
+                    other => parts.push(other),
+                }
+            }
+        }
+        match parts.len() {
+            0 => Regex::Epsilon,
+            1 => parts.pop().unwrap(),
+            _ => Regex::Concat(parts),
+        }
+    }
+
+    /// Build `a*`, simplifying where possible.
+    pub fn star(a: Regex) -> Regex {
+        match a {
+            Regex::Nothing | Regex::Epsilon => Regex::Epsilon,
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => Regex::star(*inner),
+            other => Regex::Star(Box::new(other)),
+        }
+    }
+}
+
+/// Derive a regular expression by state elimination, removing the node with the fewest paths through it first.
+fn eliminate(fsm: &StateMachine) -> Regex {
+    let n = fsm.nodes.len();
+    let (start, end) = (n, n + 1);
+    let mut edges: BTreeMap<(usize, usize), Regex> = BTreeMap::new();
+    let add = |edges: &mut BTreeMap<(usize, usize), Regex>, from, to, regex| {
+        let old = edges.remove(&(from, to)).unwrap_or(Regex::Nothing);
+        edges.insert((from, to), Regex::union(old, regex));
+    };
+    for link in fsm.links.iter() {
+        let (a, b) = link.get_nodes();
+        add(
+            &mut edges,
+            a.unwrap_or(start),
+            b,
+            Label::parse_or_literal(link.get_text()).to_regex(),
+        );
+    }
+    for (i, node) in fsm.nodes.iter().enumerate() {
+        if node.accept_state {
+            add(&mut edges, i, end, Regex::Epsilon);
+        }
+    }
+
+    let mut remaining: BTreeSet<usize> = (0..n).collect();
+    while !remaining.is_empty() {
+        let neighbours = |node: usize, edges: &BTreeMap<(usize, usize), Regex>| {
+            let ins: Vec<usize> = edges
+                .keys()
+                .filter(|(a, b)| *b == node && *a != node)
+                .map(|(a, _)| *a)
+                .collect();
+            let outs: Vec<usize> = edges
+                .keys()
+                .filter(|(a, b)| *a == node && *b != node)
+                .map(|(_, b)| *b)
+                .collect();
+            (ins, outs)
+        };
+        let node = *remaining
+            .iter()
+            .min_by_key(|node| {
+                let (ins, outs) = neighbours(**node, &edges);
+                ins.len() * outs.len()
+            })
+            .unwrap();
+        remaining.remove(&node);
+
+        let (ins, outs) = neighbours(node, &edges);
+        let around = Regex::star(edges.remove(&(node, node)).unwrap_or(Regex::Nothing));
+        for from in ins.iter() {
+            for to in outs.iter() {
+                let path = Regex::concat(
+                    Regex::concat(edges[&(*from, node)].clone(), around.clone()),
+                    edges[&(node, *to)].clone(),
+                );
+                add(&mut edges, *from, *to, path);
+            }
+        }
+        edges.retain(|(a, b), _| *a != node && *b != node);
+    }
+    edges.remove(&(start, end)).unwrap_or(Regex::Nothing)
+}
+
+impl StateMachine {
+    /// Derive a regular expression for the language of this machine.
+    ///
+    /// Both this machine and its minimal version are converted, and the shorter expression is returned.
+    /// If the minimal version cannot be drawn, or is too big to build, only this machine is converted.
+    pub fn to_regex(&self) -> Result<Regex, FSMError> {
+        if let Some(err) = self.check_error() {
+            return Err(err);
+        }
+        let minimal = match self.minimize() {
+            Ok(minimization) => minimization.machine,
+            Err(FSMError::WildcardNotExpressible | FSMError::TooManyStates(_)) => {
+                return Ok(eliminate(self))
+            }
+            Err(err) => return Err(err),
+        };
+        let mut options = [eliminate(self), eliminate(&minimal)];
+        options.sort_by_key(|r| r.to_string().chars().count());
+        let [best, _] = options;
+        Ok(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsm::{FSMOutput, StateMachine};
+
+    use super::{Regex, RegexError};
+
+    fn compile(regex: &str) -> StateMachine {
+        Regex::parse(regex).unwrap().to_state_machine()
+    }
+
+    #[test]
+    fn compiled_machines_read_the_expression() {
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            ("(a|b)*abb", &["abb", "aabb", "babb"], &["", "ab", "abba"]),
+            ("a+b?", &["a", "aab"], &["", "b", "abb"]),
+            ("[0-9]+(\\.[0-9]+)?", &["7", "3.14"], &["", ".5", "1."]),
+            ("ε|x", &["", "x"], &["xx"]),
+            (".a", &["aa", "☃a"], &["a", "aaa"]),
+            ("∅", &[], &["", "a"]),
+        ];
+        for (regex, accepted, rejected) in cases {
+            let fsm = compile(regex);
+            assert_eq!(fsm.check_error(), None, "{regex}");
+            for word in accepted.iter() {
+                assert_eq!(
+                    fsm.evaluate(word),
+                    Ok(FSMOutput::Accept),
+                    "{regex} {word:?}"
+                );
+            }
+            for word in rejected.iter() {
+                assert_eq!(
+                    fsm.evaluate(word),
+                    Ok(FSMOutput::Reject),
+                    "{regex} {word:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn printed_expressions_read_back_the_same() {
+        for regex in [
+            "(a|b)*abb",
+            "a(b|c)?d+",
+            "[a-c_]\\*",
+            "(\\(|\\))*",
+            "ε|.",
+            "∅",
+        ] {
+            let parsed = Regex::parse(regex).unwrap();
+            let reparsed = Regex::parse(&parsed.to_string()).unwrap();
+            assert_eq!(
+                compile(regex).distinguishing_word(&reparsed.to_state_machine()),
+                Ok(None),
+                "{regex} printed as {parsed}"
+            );
+        }
+    }
+
+    #[test]
+    fn machines_round_trip_through_expressions() {
+        let mut machines: Vec<StateMachine> = [
+            "(a|b)*abb",
+            "(ab|ba)+",
+            "a*b*c*",
+            "[0-9]+,[0-9]+",
+            ".x|y",
+            "ε",
+            "∅",
+        ]
+        .iter()
+        .map(|regex| compile(regex))
+        .collect();
+        // Drawn machines may have several start links, empty links and multi-character labels.
+        machines.push(
+            StateMachine::from_formal(
+                "states: p, q, r
+                 start: p, q
+                 accept: r
+                 transitions:
+                     p -ab-> r
+                     q --> r
+                     r -c,\\e-> p",
+            )
+            .unwrap(),
+        );
+        for fsm in machines {
+            let regex = fsm.to_regex().unwrap();
+            let reparsed = Regex::parse(&regex.to_string()).unwrap();
+            assert_eq!(
+                fsm.distinguishing_word(&reparsed.to_state_machine()),
+                Ok(None),
+                "{}",
+                fsm.to_formal()
+            );
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        assert_eq!(Regex::parse("ab)"), Err(RegexError::UnexpectedChar(2, ')')));
+        assert_eq!(Regex::parse("(ab"), Err(RegexError::UnexpectedEnd));
+        assert!(matches!(
+            Regex::parse("[z-a]"),
+            Err(RegexError::BackwardsRange(_))
+        ));
+    }
+}
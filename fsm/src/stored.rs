@@ -0,0 +1,321 @@
+//! The versioned JSON format that machines are stored in.
+//!
+//! A stored machine is an envelope like `{"version": 1, "machine": {...}}`.
+//! Machines stored before there were versions are the bare machine object; rows read from storage count them as version 0,
+//! while a bare machine pasted by a user is taken to be current.
+//! Before version 2, link texts were plain text rather than label patterns.
+//! Reading a machine brings it up to the current version one migration at a time,
+//! and then checks that it makes sense, so that broken data is an error rather than a panic later on.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    fsm::{Link, StateMachine},
+    label::Label,
+};
+
+/// The version that [StateMachine::to_stored_json] writes.
+pub const CURRENT_VERSION: u64 = 2;
+
+/// How far from the canvas a node or the tail of a start link may be, in pixels.
+pub const MAX_COORDINATE: i32 = 10_000;
+
+/// The migration from each version to the next, starting from version 0.
+const MIGRATIONS: [fn(Value) -> Value; CURRENT_VERSION as usize] = [
+    // Version 1 only wraps the machine in an envelope; the machine itself is unchanged.
+    |machine| machine,
+    // Version 2 reads link texts as label patterns, so the old literal texts have their special characters escaped.
+    escape_link_texts,
+];
+
+/// Escape every link text, so that a text that used to be read literally still reads the same as a [Label].
+fn escape_link_texts(mut machine: Value) -> Value {
+    if let Some(links) = machine.get_mut("links").and_then(Value::as_array_mut) {
+        for link in links {
+            if let Some(text) = link.get_mut("text") {
+                if let Some(escaped) = text.as_str().map(Label::escape) {
+                    *text = Value::String(escaped);
+                }
+            }
+        }
+    }
+    machine
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<M> {
+    version: u64,
+    machine: M,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum StoredMachineError {
+    #[error("the text is not valid JSON: {0}")]
+    Json(String),
+
+    #[error("the version envelope around the machine cannot be read: {0}")]
+    Envelope(String),
+
+    #[error("the machine is stored in version {0}, but only versions up to {CURRENT_VERSION} can be read")]
+    UnknownVersion(u64),
+
+    #[error("the machine, stored in version {version}, cannot be read: {why}")]
+    Shape { version: u64, why: String },
+
+    #[error("the machine is not valid: {0}")]
+    Invalid(#[from] ValidationError),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    #[error("link {link} points at node {node}, but there are only {count} nodes")]
+    MissingNode {
+        link: usize,
+        node: usize,
+        count: usize,
+    },
+
+    #[error("node {node} is at ({x}, {y}), too far from the canvas")]
+    NodeTooFar { node: usize, x: i32, y: i32 },
+
+    #[error(
+        "start link {link} begins at ({delta_x}, {delta_y}) from its node, too far from the canvas"
+    )]
+    StartLinkTooLong {
+        link: usize,
+        delta_x: i32,
+        delta_y: i32,
+    },
+
+    #[error("the {field} of link {link} is {value}, which is not a finite number")]
+    NotFinite {
+        link: usize,
+        field: &'static str,
+        value: f64,
+    },
+}
+
+impl StateMachine {
+    /// Check that every link points at existing nodes, and that every position and angle is a usable number.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let in_range = |c: i32| (-MAX_COORDINATE..=MAX_COORDINATE).contains(&c);
+        for (i, node) in self.nodes.iter().enumerate() {
+            if !in_range(node.x) || !in_range(node.y) {
+                return Err(ValidationError::NodeTooFar {
+                    node: i,
+                    x: node.x,
+                    y: node.y,
+                });
+            }
+        }
+
+        for (i, link) in self.links.iter().enumerate() {
+            let (from, to) = link.get_nodes();
+            for node in from.into_iter().chain([to]) {
+                if node >= self.nodes.len() {
+                    return Err(ValidationError::MissingNode {
+                        link: i,
+                        node,
+                        count: self.nodes.len(),
+                    });
+                }
+            }
+
+            let numbers: &[(&'static str, f64)] = match link {
+                Link::NormalLink {
+                    angle_adjust,
+                    parallel_part,
+                    perpendicular_part,
+                    ..
+                } => &[
+                    ("angle adjustment", *angle_adjust),
+                    ("parallel part", *parallel_part),
+                    ("perpendicular part", *perpendicular_part),
+                ],
+                Link::SelfLink { anchor_angle, .. } => &[("anchor angle", *anchor_angle)],
+                Link::StartLink {
+                    delta_x, delta_y, ..
+                } => {
+                    if !in_range(*delta_x) || !in_range(*delta_y) {
+                        return Err(ValidationError::StartLinkTooLong {
+                            link: i,
+                            delta_x: *delta_x,
+                            delta_y: *delta_y,
+                        });
+                    }
+                    &[]
+                }
+            };
+            if let Some((field, value)) = numbers.iter().find(|(_, value)| !value.is_finite()) {
+                return Err(ValidationError::NotFinite {
+                    link: i,
+                    field,
+                    value: *value,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Write this machine in the current version of the stored format.
+    pub fn to_stored_json(&self) -> String {
+        let envelope = Envelope {
+            version: CURRENT_VERSION,
+            machine: self,
+        };
+        serde_json::to_string(&envelope).expect("a machine can always be written as JSON")
+    }
+
+    /// Read a machine stored in any version of the stored format, including the bare machines of version 0,
+    /// and check it with [StateMachine::validate].
+    ///
+    /// This is for stored rows: a bare machine was written before label patterns, so its texts are escaped.
+    pub fn from_stored_json(text: &str) -> Result<StateMachine, StoredMachineError> {
+        Self::read_stored_json(text, 0)
+    }
+
+    /// Read a machine that a user pasted, which may be in any version of the stored format.
+    ///
+    /// Unlike [StateMachine::from_stored_json], a bare machine is taken to be written in the current version,
+    /// so that pasted label patterns keep their meaning; only envelopes of older versions are migrated.
+    pub fn from_pasted_json(text: &str) -> Result<StateMachine, StoredMachineError> {
+        Self::read_stored_json(text, CURRENT_VERSION)
+    }
+
+    /// Read a machine in the stored format, counting a bare machine as this version.
+    fn read_stored_json(text: &str, bare_version: u64) -> Result<StateMachine, StoredMachineError> {
+        let value: Value =
+            serde_json::from_str(text).map_err(|e| StoredMachineError::Json(e.to_string()))?;
+        let (version, mut machine) = if value.get("version").is_some() {
+            let envelope: Envelope<Value> = serde_json::from_value(value)
+                .map_err(|e| StoredMachineError::Envelope(e.to_string()))?;
+            (envelope.version, envelope.machine)
+        } else {
+            (bare_version, value)
+        };
+        if version > CURRENT_VERSION {
+            return Err(StoredMachineError::UnknownVersion(version));
+        }
+
+        for migration in &MIGRATIONS[version as usize..] {
+            machine = migration(machine);
+        }
+        let fsm: StateMachine =
+            serde_json::from_value(machine).map_err(|e| StoredMachineError::Shape {
+                version,
+                why: e.to_string(),
+            })?;
+        fsm.validate()?;
+        Ok(fsm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{
+        drawing::test_machine,
+        fsm::{Link, StateMachine},
+    };
+
+    use super::{StoredMachineError, ValidationError, CURRENT_VERSION};
+
+    fn patterns() -> StateMachine {
+        test_machine(&[false, true], &[(0, 1, "a,b"), (1, 1, "[0-9].")])
+    }
+
+    fn texts(fsm: &StateMachine) -> Vec<&str> {
+        fsm.links.iter().map(|link| link.get_text()).collect()
+    }
+
+    #[test]
+    fn old_versions_have_their_texts_escaped() {
+        let bare = serde_json::to_string(&patterns()).unwrap();
+        let v1 = json!({"version": 1, "machine": serde_json::to_value(patterns()).unwrap()});
+        for text in [bare, v1.to_string()] {
+            let fsm = StateMachine::from_stored_json(&text).unwrap();
+            assert_eq!(texts(&fsm), ["", "a\\,b", "\\[0\\-9\\]\\."]);
+        }
+    }
+
+    #[test]
+    fn current_and_pasted_machines_keep_their_patterns() {
+        let current = patterns().to_stored_json();
+        assert_eq!(StateMachine::from_stored_json(&current), Ok(patterns()));
+        assert_eq!(StateMachine::from_pasted_json(&current), Ok(patterns()));
+
+        let bare = serde_json::to_string(&patterns()).unwrap();
+        assert_eq!(StateMachine::from_pasted_json(&bare), Ok(patterns()));
+
+        let v1 = json!({"version": 1, "machine": serde_json::to_value(patterns()).unwrap()});
+        let fsm = StateMachine::from_pasted_json(&v1.to_string()).unwrap();
+        assert_eq!(texts(&fsm)[1], "a\\,b");
+    }
+
+    #[test]
+    fn unreadable_text_is_an_error() {
+        let read = StateMachine::from_stored_json;
+        assert!(matches!(read("{"), Err(StoredMachineError::Json(_))));
+        assert!(matches!(
+            read(r#"{"version": "two", "machine": {}}"#),
+            Err(StoredMachineError::Envelope(_))
+        ));
+        assert_eq!(
+            read(&json!({"version": CURRENT_VERSION + 1, "machine": {}}).to_string()),
+            Err(StoredMachineError::UnknownVersion(CURRENT_VERSION + 1))
+        );
+        assert!(matches!(
+            read(r#"{"version": 2, "machine": {"nodes": 3}}"#),
+            Err(StoredMachineError::Shape { version: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn broken_machines_are_rejected() {
+        let mut value: Value = serde_json::from_str(&patterns().to_stored_json()).unwrap();
+        value["machine"]["nodes"][0]["x"] = json!(20_000);
+        assert!(matches!(
+            StateMachine::from_stored_json(&value.to_string()),
+            Err(StoredMachineError::Invalid(ValidationError::NodeTooFar {
+                node: 0,
+                ..
+            }))
+        ));
+
+        let mut fsm = patterns();
+        fsm.nodes.pop();
+        assert!(matches!(
+            fsm.validate(),
+            Err(ValidationError::MissingNode {
+                node: 1,
+                count: 1,
+                ..
+            })
+        ));
+
+        let mut fsm = patterns();
+        if let Link::StartLink { delta_x, .. } = &mut fsm.links[0] {
+            *delta_x = -50_000;
+        }
+        assert!(matches!(
+            fsm.validate(),
+            Err(ValidationError::StartLinkTooLong { link: 0, .. })
+        ));
+
+        let mut fsm = patterns();
+        for link in fsm.links.iter_mut() {
+            match link {
+                Link::NormalLink { angle_adjust, .. } => *angle_adjust = f64::NAN,
+                Link::SelfLink { anchor_angle, .. } => *anchor_angle = f64::INFINITY,
+                Link::StartLink { .. } => {}
+            }
+        }
+        assert!(matches!(
+            fsm.validate(),
+            Err(ValidationError::NotFinite { link: 1, .. })
+        ));
+    }
+}
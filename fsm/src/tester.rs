@@ -1,10 +1,27 @@
 use rand::{Rng, SeedableRng};
 use rhai::{Engine, Scope, AST};
 
-use crate::fsm::{FSMError, FSMOutput, StateMachine};
+use crate::{
+    compiled::CompiledMachine,
+    explain::Explanation,
+    fsm::{FSMError, FSMOutput, StateMachine},
+    pushdown::{CompiledPushdown, PushdownAcceptance},
+    transducer::{Transducer, Transduction},
+};
 
 pub struct FSMTester<'a> {
     pub fsm: StateMachine,
+    /// The FSM prepared for running the tests; built from `fsm` when the tester is created.
+    /// For transducer tasks, this is the acceptor of the FSM's inputs.
+    compiled: CompiledMachine,
+    /// Present if the script defines `transduce`, which makes this a transducer task.
+    transducer: Option<Transducer>,
+    /// Present if the script defines `pushdown_acceptance`, which makes this a pushdown automaton task;
+    /// an error if the FSM's links cannot be read as pushdown labels.
+    pushdown: Option<Result<CompiledPushdown, FSMError>>,
+    /// Present if the script defines `turing_machine`, which makes this a Turing machine task:
+    /// how many steps the machine may take on a word.
+    turing: Option<usize>,
     engine: Engine,
     ast: AST,
     scope: Scope<'a>,
@@ -65,7 +82,19 @@ impl<'a> FSMTester<'a> {
         let ast = engine.compile(script)?;
         let mut scope = Scope::new();
         Self::check_script_api(&mut engine, &ast, &mut scope)?;
+        let transducer = Self::ast_is_transducer(&ast).then(|| Transducer::new(&fsm));
+        let pushdown = Self::ast_pushdown_acceptance(&engine, &ast)?
+            .map(|acceptance| CompiledPushdown::new(&fsm, acceptance));
+        let turing = Self::ast_turing_budget(&engine, &ast)?;
         Ok(Self {
+            compiled: match (&transducer, &pushdown) {
+                (Some(t), _) => t.inputs().clone(),
+                (None, Some(_)) => CompiledMachine::new(&fsm.pushdown_input_machine()),
+                (None, None) => CompiledMachine::new(&fsm),
+            },
+            transducer,
+            pushdown,
+            turing,
             fsm,
             engine,
             ast,
@@ -82,12 +111,101 @@ impl<'a> FSMTester<'a> {
 
         Self {
             fsm: self.fsm.clone(),
+            compiled: self.compiled.clone(),
+            transducer: self.transducer.clone(),
+            pushdown: self.pushdown.clone(),
+            turing: self.turing,
             engine,
             ast: self.ast.clone(),
             scope: self.scope.clone(),
         }
     }
 
+    fn ast_is_transducer(ast: &AST) -> bool {
+        ast.iter_functions()
+            .any(|f| f.name == "transduce" && f.params.len() == 1)
+    }
+
+    /// Whether the script defines `transduce(word)`, so that the task asks for a transducer.
+    ///
+    /// Scripts that do not compile are not transducer tasks.
+    pub fn script_is_transducer(script: &str) -> bool {
+        Engine::new()
+            .compile(script)
+            .is_ok_and(|ast| Self::ast_is_transducer(&ast))
+    }
+
+    pub fn is_transducer(&self) -> bool {
+        self.transducer.is_some()
+    }
+
+    /// How a pushdown automaton accepts, if the script defines `pushdown_acceptance()`:
+    /// it returns `"final state"` or `"empty stack"`.
+    fn ast_pushdown_acceptance(
+        engine: &Engine,
+        ast: &AST,
+    ) -> anyhow::Result<Option<PushdownAcceptance>> {
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == "pushdown_acceptance" && f.params.is_empty())
+        {
+            return Ok(None);
+        }
+        let acceptance =
+            engine.call_fn::<String>(&mut Scope::new(), ast, "pushdown_acceptance", ())?;
+        match acceptance.as_str() {
+            "final state" => Ok(Some(PushdownAcceptance::FinalState)),
+            "empty stack" => Ok(Some(PushdownAcceptance::EmptyStack)),
+            other => anyhow::bail!(
+                "pushdown_acceptance() returned {other:?}, but it should be \"final state\" or \"empty stack\""
+            ),
+        }
+    }
+
+    /// How the FSM should accept words if the script asks for a pushdown automaton, with `pushdown_acceptance()`.
+    ///
+    /// Scripts that do not compile, or do not say, are not pushdown automaton tasks.
+    pub fn script_pushdown_acceptance(script: &str) -> Option<PushdownAcceptance> {
+        let engine = Engine::new();
+        let ast = engine.compile(script).ok()?;
+        Self::ast_pushdown_acceptance(&engine, &ast).ok()?
+    }
+
+    pub fn is_pushdown(&self) -> bool {
+        self.pushdown.is_some()
+    }
+
+    /// How many steps a Turing machine may take on a word, if the script defines `turing_machine()`:
+    /// it returns that number.
+    fn ast_turing_budget(engine: &Engine, ast: &AST) -> anyhow::Result<Option<usize>> {
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == "turing_machine" && f.params.is_empty())
+        {
+            return Ok(None);
+        }
+        let budget = engine.call_fn::<i64>(&mut Scope::new(), ast, "turing_machine", ())?;
+        match usize::try_from(budget) {
+            Ok(budget) if budget > 0 => Ok(Some(budget)),
+            _ => anyhow::bail!(
+                "turing_machine() returned {budget}, but it should be a positive number of steps"
+            ),
+        }
+    }
+
+    /// How many steps the FSM may take on a word if the script asks for a Turing machine, with `turing_machine()`.
+    ///
+    /// Scripts that do not compile, or do not say, are not Turing machine tasks.
+    pub fn script_turing_budget(script: &str) -> Option<usize> {
+        let engine = Engine::new();
+        let ast = engine.compile(script).ok()?;
+        Self::ast_turing_budget(&engine, &ast).ok()?
+    }
+
+    pub fn is_turing(&self) -> bool {
+        self.turing.is_some()
+    }
+
     fn check_script_api(
         engine: &mut Engine,
         ast: &AST,
@@ -105,6 +223,10 @@ impl<'a> FSMTester<'a> {
         if !is_accept {
             anyhow::bail!("gen_word(true) returned {accept_test}, but check_word says False");
         }
+        if Self::ast_is_transducer(ast) {
+            log::debug!("Testing accept case: transducing");
+            engine.call_fn::<String>(scope, ast, "transduce", (accept_test.clone(),))?;
+        }
 
         let rng = RhaiRng::new(0);
         scope.clear();
@@ -135,10 +257,21 @@ impl<'a> FSMTester<'a> {
         let mut first_fail_seed = None;
         let mut first_fail_seed_true_outcome = None;
         let mut first_fail_len = usize::MAX;
+        let mut first_fail_word = String::new();
+        let mut first_fail_transductions = None;
         let mut successes = 0;
 
         // If the FSM is obviously invalid, bail.
-        if let Some(err) = self.fsm.check_error() {
+        let error = if self.is_transducer() {
+            self.fsm.check_transducer_error()
+        } else if self.is_pushdown() {
+            self.fsm.check_pushdown_error()
+        } else if self.is_turing() {
+            self.fsm.check_turing_error()
+        } else {
+            self.fsm.check_error()
+        };
+        if let Some(err) = error {
             return Ok(FSMTestingOutput::FSMInvalid(err));
         }
 
@@ -153,7 +286,19 @@ impl<'a> FSMTester<'a> {
                     return Ok(FSMTestingOutput::FSMInvalid(error));
                 }
                 Ok((user_answer, true_answer)) => {
-                    if user_answer == true_answer {
+                    // For transducers, the outputs have to match as well.
+                    let transductions = if self.is_transducer() {
+                        let expected = self.transduce_word(test_outcome.0.clone())?;
+                        let actual =
+                            match self.transducer.as_ref().unwrap().transduce(&test_outcome.0) {
+                                Ok(actual) => actual,
+                                Err(error) => return Ok(FSMTestingOutput::FSMInvalid(error)),
+                            };
+                        (expected != actual).then_some((expected, actual))
+                    } else {
+                        None
+                    };
+                    if user_answer == true_answer && transductions.is_none() {
                         successes += 1;
                     } else {
                         match first_fail_seed {
@@ -161,12 +306,16 @@ impl<'a> FSMTester<'a> {
                                 first_fail_seed = Some(test_seed);
                                 first_fail_seed_true_outcome = Some(true_answer);
                                 first_fail_len = test_outcome.0.len();
+                                first_fail_word = test_outcome.0;
+                                first_fail_transductions = transductions;
                             }
                             Some(_) => {
                                 if test_outcome.0.len() < first_fail_len {
                                     first_fail_seed = Some(test_seed);
                                     first_fail_seed_true_outcome = Some(true_answer);
                                     first_fail_len = test_outcome.0.len();
+                                    first_fail_word = test_outcome.0;
+                                    first_fail_transductions = transductions;
                                 }
                             }
                         }
@@ -183,10 +332,94 @@ impl<'a> FSMTester<'a> {
                 total_tests: Self::TESTS,
                 first_failure_seed: first_fail_seed.unwrap(),
                 first_failure_expected_result: first_fail_seed_true_outcome.unwrap(),
+                // The explanation follows the links, not a pushdown automaton's stack or a Turing machine's tape.
+                first_failure_explanation: (!self.is_pushdown() && !self.is_turing())
+                    .then(|| self.compiled.explain(&first_fail_word)),
+                first_failure_transductions: first_fail_transductions,
             })
         }
     }
 
+    /// How many of the script's tests an equivalent FSM must also pass, in case the model solution is wrong.
+    const EQUIVALENCE_SAMPLE: usize = 64;
+
+    /// Check the FSM by deciding exactly whether it accepts the same language as the model solution.
+    ///
+    /// First a sample of the script's tests is run on the model, seeded like [Self::run_testing].
+    /// Only words made of the characters in those tests and in the model's labels are compared,
+    /// so a machine is not faulted for what it does with characters the task never asks about.
+    /// If the languages differ, the shortest word where they differ is checked against the script as well.
+    /// Returns None if the model is invalid, or if the script disagrees with the model:
+    /// then the model cannot be trusted, and the caller should fall back to [Self::run_testing].
+    /// It also returns None if either machine is too big to determinize, or its words cannot be counted.
+    /// Only finite automata can be compared exactly, so for other tasks this always returns None.
+    pub fn run_equivalence(
+        &mut self,
+        model: &StateMachine,
+        init_random_seed: i64,
+    ) -> anyhow::Result<Option<FSMTestingOutput>> {
+        if self.is_transducer() || self.is_pushdown() || self.is_turing() {
+            return Ok(None);
+        }
+        if let Some(err) = model.check_error() {
+            log::error!("Model solution is invalid: {err}");
+            return Ok(None);
+        }
+        if let Some(err) = self.fsm.check_error() {
+            return Ok(Some(FSMTestingOutput::FSMInvalid(err)));
+        }
+
+        // The script's words show which characters the task asks about, along with the ones the model mentions.
+        // A sample of them also checks that the model agrees with the script.
+        let compiled_model = CompiledMachine::new(model);
+        let mut alphabet = model.label_alphabet();
+        let mut test_seed_rng = rand_chacha::ChaCha8Rng::from_seed(expand_seed(init_random_seed));
+        for _ in 0..Self::EQUIVALENCE_SAMPLE {
+            let test_seed = test_seed_rng.gen();
+            let (word, true_output) = self.make_test_case(test_seed, test_seed % 2 == 0)?;
+            if compiled_model.evaluate(&word) != true_output {
+                log::error!("Model solution disagrees with the script on {word:?}");
+                return Ok(None);
+            }
+            alphabet.extend(word.chars());
+        }
+        alphabet.sort();
+        alphabet.dedup();
+
+        match self.fsm.distinguishing_word_over(model, &alphabet) {
+            Err(FSMError::TooManyStates(limit)) => {
+                log::warn!("Cannot compare with the model solution: over {limit} states");
+                Ok(None)
+            }
+            Err(err) => Ok(Some(FSMTestingOutput::FSMInvalid(err))),
+            Ok(None) => Ok(Some(FSMTestingOutput::Equivalent)),
+            Ok(Some((word, model_output))) => {
+                let true_output = self.check_word(word.clone())?;
+                if true_output != model_output {
+                    log::error!("Model solution disagrees with the script on {word:?}");
+                    return Ok(None);
+                }
+                let length = word.chars().count();
+                let count_at = |fsm: &StateMachine| -> Result<u64, FSMError> {
+                    Ok(fsm.count_words(length)?[length])
+                };
+                let (expected_count, actual_count) = match (count_at(model), count_at(&self.fsm)) {
+                    (Ok(expected), Ok(actual)) => (expected, actual),
+                    (Err(err), _) | (_, Err(err)) => {
+                        log::warn!("Cannot count the words of length {length}: {err}");
+                        return Ok(None);
+                    }
+                };
+                Ok(Some(FSMTestingOutput::Counterexample {
+                    word,
+                    expected_result: true_output,
+                    expected_count,
+                    actual_count,
+                }))
+            }
+        }
+    }
+
     pub fn check_word(&mut self, word: String) -> anyhow::Result<FSMOutput> {
         let true_output: bool =
             self.engine
@@ -197,6 +430,18 @@ impl<'a> FSMTester<'a> {
         })
     }
 
+    /// Ask the script what the transducer should write for this word:
+    /// nothing if `check_word` rejects it, and otherwise what `transduce` returns.
+    pub fn transduce_word(&mut self, word: String) -> anyhow::Result<Transduction> {
+        if self.check_word(word.clone())? == FSMOutput::Reject {
+            return Ok(Transduction::Rejected);
+        }
+        let output =
+            self.engine
+                .call_fn::<String>(&mut self.scope, &self.ast, "transduce", (word,))?;
+        Ok(Transduction::Output(output))
+    }
+
     pub fn make_test_case(
         &mut self,
         seed: i64,
@@ -221,18 +466,23 @@ impl<'a> FSMTester<'a> {
     /// If the FSM evaluation fails, the inner Result contains the error, and the errored word is returned;
     /// if the script fails, the outer Result contains the error.
     ///
-    /// Uses evaluate_unchecked -- make sure to check the FSM first, to avoid long loops.
+    /// Does not check the FSM -- make sure to check it first, because loops of empty links are not reported here.
     pub fn test_once(
         &mut self,
         seed: i64,
     ) -> Result<(String, Result<(FSMOutput, FSMOutput), FSMError>), anyhow::Error> {
         let (test_case, true_output) = self.make_test_case(seed, seed % 2 == 0)?;
 
-        let fsm_output = self.fsm.evaluate_unchecked(&test_case);
-        match fsm_output {
-            Ok(out) => Ok((test_case, Ok((out, true_output)))),
-            Err(err) => Ok((test_case, Err(err))),
+        if !self.compiled.has_start_links() {
+            return Ok((test_case, Err(FSMError::NoEntryLinks)));
         }
+        let fsm_output = match (&self.pushdown, self.turing) {
+            (Some(Ok(pushdown)), _) => pushdown.evaluate(&test_case),
+            (Some(Err(err)), _) => Err(err.clone()),
+            (None, Some(budget)) => self.fsm.run_turing(&test_case, budget),
+            (None, None) => Ok(self.compiled.evaluate(&test_case)),
+        };
+        Ok((test_case, fsm_output.map(|output| (output, true_output))))
     }
 }
 
@@ -247,8 +497,24 @@ pub enum FSMTestingOutput {
         total_tests: usize,
         first_failure_seed: i64,
         first_failure_expected_result: FSMOutput,
+        /// Why the FSM gave the wrong answer on the first failed test; not given for pushdown automata and Turing machines.
+        first_failure_explanation: Option<Explanation>,
+        /// For transducer tasks, if the outputs differed on the first failed test: the expected one, and the FSM's.
+        first_failure_transductions: Option<(Transduction, Transduction)>,
     },
 
     /// FSM is invalid
     FSMInvalid(FSMError),
+
+    /// FSM is proven to accept the same language as the model solution
+    Equivalent,
+
+    /// FSM is proven to differ from the model solution: the shortest word where they differ is given,
+    /// along with how many words of that length each machine accepts.
+    Counterexample {
+        word: String,
+        expected_result: FSMOutput,
+        expected_count: u64,
+        actual_count: u64,
+    },
 }
@@ -0,0 +1,412 @@
+//! Exporting machines as TikZ pictures for LaTeX, drawn the same way as on the canvas.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::fsm::{Link, StateMachine};
+
+/// The radius of a node on the canvas, in pixels.
+const NODE_RADIUS: f64 = 30.0;
+
+/// Pixels to TikZ units: TikZ breaks on big numbers, so the picture is scaled up again with `scale=0.2`.
+const SCALE: f64 = 0.1;
+
+/// Where a piece of text goes, relative to the point it is attached to.
+fn text_anchor(angle: Option<f64>) -> &'static str {
+    let Some(angle) = angle else {
+        return "";
+    };
+    let (dx, dy) = (angle.cos(), angle.sin());
+    if dx.abs() > dy.abs() {
+        if dx > 0.0 {
+            "[right] "
+        } else {
+            "[left] "
+        }
+    } else if dy > 0.0 {
+        "[below] "
+    } else {
+        "[above] "
+    }
+}
+
+/// Escape text for LaTeX's text mode, writing the empty word as ε and arrows as arrows.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped += "\\textbackslash{}",
+            '{' | '}' | '_' | '#' | '$' | '%' | '&' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '^' => escaped += "\\textasciicircum{}",
+            '~' => escaped += "\\textasciitilde{}",
+            '|' => escaped += "\\textbar{}",
+            '<' => escaped += "\\textless{}",
+            '>' => escaped += "\\textgreater{}",
+            'ε' => escaped += "$\\varepsilon$",
+            '→' => escaped += "$\\to$",
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The text of a link as the canvas shows it: `\e` alone is ε, and an empty label on a link
+/// between nodes is `(ε)`.
+fn caption(text: &str, between_nodes: bool) -> String {
+    match text.trim() {
+        "" if between_nodes => String::from("(ε)"),
+        "\\e" => String::from("ε"),
+        _ => text.to_string(),
+    }
+}
+
+/// The circle through three points, as (center, radius).
+fn circle_through(
+    (x1, y1): (f64, f64),
+    (x2, y2): (f64, f64),
+    (x3, y3): (f64, f64),
+) -> ((f64, f64), f64) {
+    let det = |a: f64, b: f64, c: f64, d: f64, e: f64, f: f64, g: f64, h: f64, i: f64| {
+        a * e * i + b * f * g + c * d * h - a * f * h - b * d * i - c * e * g
+    };
+    let (s1, s2, s3) = (x1 * x1 + y1 * y1, x2 * x2 + y2 * y2, x3 * x3 + y3 * y3);
+    let a = det(x1, y1, 1.0, x2, y2, 1.0, x3, y3, 1.0);
+    let bx = -det(s1, y1, 1.0, s2, y2, 1.0, s3, y3, 1.0);
+    let by = det(s1, x1, 1.0, s2, x2, 1.0, s3, x3, 1.0);
+    let c = -det(s1, x1, y1, s2, x2, y2, s3, x3, y3);
+    (
+        (-bx / (2.0 * a), -by / (2.0 * a)),
+        (bx * bx + by * by - 4.0 * a * c).sqrt() / (2.0 * a.abs()),
+    )
+}
+
+/// The point on the node's circle closest to the given point.
+fn closest_point_on_circle((cx, cy): (f64, f64), (x, y): (f64, f64)) -> (f64, f64) {
+    let (dx, dy) = (x - cx, y - cy);
+    let scale = (dx * dx + dy * dy).sqrt();
+    (cx + dx * NODE_RADIUS / scale, cy + dy * NODE_RADIUS / scale)
+}
+
+/// Collects TikZ commands for shapes given in canvas pixels.
+#[derive(Default)]
+struct Picture {
+    commands: String,
+}
+
+impl Picture {
+    fn point((x, y): (f64, f64)) -> String {
+        format!("({:.2},{:.2})", x * SCALE, -y * SCALE)
+    }
+
+    fn circle(&mut self, center: (f64, f64), radius: f64) {
+        self.commands += &format!(
+            "\\draw [black] {} circle ({:.3});\n",
+            Self::point(center),
+            radius * SCALE
+        );
+    }
+
+    fn line(&mut self, from: (f64, f64), to: (f64, f64)) {
+        self.commands += &format!(
+            "\\draw [black] {} -- {};\n",
+            Self::point(from),
+            Self::point(to)
+        );
+    }
+
+    /// An arc like the canvas's `arc`: clockwise on the screen from the start angle to the end angle,
+    /// or the other way if reversed.
+    fn arc(
+        &mut self,
+        (x, y): (f64, f64),
+        radius: f64,
+        mut start: f64,
+        mut end: f64,
+        reversed: bool,
+    ) {
+        if reversed {
+            std::mem::swap(&mut start, &mut end);
+        }
+        if end < start {
+            end += 2.0 * PI;
+        }
+        // TikZ needs the angles to be between -2π and 2π.
+        if start.min(end) < -2.0 * PI {
+            start += 2.0 * PI;
+            end += 2.0 * PI;
+        } else if start.max(end) > 2.0 * PI {
+            start -= 2.0 * PI;
+            end -= 2.0 * PI;
+        }
+        // The y axis points up in TikZ.
+        let (start, end) = (-start, -end);
+        let (x, y, radius) = (x * SCALE, -y * SCALE, radius * SCALE);
+        self.commands += &format!(
+            "\\draw [black] ({:.3},{:.3}) arc ({:.5}:{:.5}:{:.3});\n",
+            x + radius * start.cos(),
+            y + radius * start.sin(),
+            start.to_degrees(),
+            end.to_degrees(),
+            radius
+        );
+    }
+
+    /// A filled arrow head with its tip at the point, pointing in the direction of the angle.
+    fn arrow(&mut self, (x, y): (f64, f64), angle: f64) {
+        let (dx, dy) = (angle.cos(), angle.sin());
+        self.commands += &format!(
+            "\\fill [black] {} -- {} -- {};\n",
+            Self::point((x, y)),
+            Self::point((x - 12.0 * dx + 7.0 * dy, y - 12.0 * dy - 7.0 * dx)),
+            Self::point((x - 12.0 * dx - 7.0 * dy, y - 12.0 * dy + 7.0 * dx))
+        );
+    }
+
+    /// Text at the point, or next to it on the side the angle points to.
+    fn text(&mut self, text: &str, at: (f64, f64), angle: Option<f64>) {
+        if text.trim().is_empty() {
+            return;
+        }
+        self.commands += &format!(
+            "\\draw {} node {}{{{}}};\n",
+            Self::point(at),
+            text_anchor(angle),
+            escape(text)
+        );
+    }
+}
+
+impl StateMachine {
+    /// Draw this machine as a TikZ picture, to be put in a LaTeX document that uses the `tikz` package.
+    ///
+    /// Everything is where it is on the canvas: nodes, links curved by their `parallel_part` and
+    /// `perpendicular_part`, self links at their anchor angle, and start links with their arrows.
+    /// Links pointing at nodes that do not exist are left out.
+    pub fn to_tikz(&self) -> String {
+        let mut picture = Picture::default();
+        let center = |node: usize| self.nodes.get(node).map(|n| (n.x as f64, n.y as f64));
+
+        for node in self.nodes.iter() {
+            let at = (node.x as f64, node.y as f64);
+            picture.circle(at, NODE_RADIUS);
+            picture.text(&node.text, at, None);
+            if node.accept_state {
+                picture.circle(at, NODE_RADIUS - 6.0);
+            }
+        }
+
+        for link in self.links.iter() {
+            match link {
+                Link::NormalLink {
+                    start_node,
+                    end_node,
+                    text,
+                    angle_adjust,
+                    parallel_part,
+                    perpendicular_part,
+                } => {
+                    let (Some(a), Some(b)) = (center(*start_node), center(*end_node)) else {
+                        continue;
+                    };
+                    if a == b {
+                        continue;
+                    }
+                    let text = caption(text, true);
+                    if *perpendicular_part == 0.0 {
+                        let middle = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+                        let start = closest_point_on_circle(a, middle);
+                        let end = closest_point_on_circle(b, middle);
+                        picture.line(start, end);
+                        picture.arrow(end, f64::atan2(end.1 - start.1, end.0 - start.0));
+                        let angle = f64::atan2(end.0 - start.0, start.1 - end.1) + angle_adjust;
+                        let middle = ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0);
+                        picture.text(&text, middle, Some(angle));
+                        continue;
+                    }
+
+                    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                    let scale = (dx * dx + dy * dy).sqrt();
+                    let anchor = (
+                        a.0 + dx * parallel_part - dy * perpendicular_part / scale,
+                        a.1 + dy * parallel_part + dx * perpendicular_part / scale,
+                    );
+                    let (circle, radius) = circle_through(a, b, anchor);
+                    let reversed = *perpendicular_part > 0.0;
+                    let reverse_scale = if reversed { 1.0 } else { -1.0 };
+                    let start_angle = f64::atan2(a.1 - circle.1, a.0 - circle.0)
+                        - reverse_scale * NODE_RADIUS / radius;
+                    let end_angle = f64::atan2(b.1 - circle.1, b.0 - circle.0)
+                        + reverse_scale * NODE_RADIUS / radius;
+                    let end = (
+                        circle.0 + radius * end_angle.cos(),
+                        circle.1 + radius * end_angle.sin(),
+                    );
+                    picture.arc(circle, radius, start_angle, end_angle, reversed);
+                    picture.arrow(end, end_angle - reverse_scale * FRAC_PI_2);
+
+                    let (start_angle, mut end_angle) = (-start_angle, -end_angle);
+                    if start_angle < end_angle {
+                        end_angle += 2.0 * PI;
+                    }
+                    let text_angle =
+                        (start_angle + end_angle) / 2.0 + if reversed { PI } else { 0.0 };
+                    let at = (
+                        circle.0 + radius * text_angle.cos(),
+                        circle.1 - radius * text_angle.sin(),
+                    );
+                    picture.text(&text, at, Some(-text_angle));
+                }
+                Link::SelfLink {
+                    node,
+                    text,
+                    anchor_angle,
+                } => {
+                    let Some(at) = center(*node) else {
+                        continue;
+                    };
+                    let circle = (
+                        at.0 + 1.5 * NODE_RADIUS * anchor_angle.cos(),
+                        at.1 + 1.5 * NODE_RADIUS * anchor_angle.sin(),
+                    );
+                    let radius = 0.75 * NODE_RADIUS;
+                    let (start_angle, end_angle) =
+                        (anchor_angle - PI * 0.8, anchor_angle + PI * 0.8);
+                    let end = (
+                        circle.0 + radius * end_angle.cos(),
+                        circle.1 + radius * end_angle.sin(),
+                    );
+                    picture.arc(circle, radius, start_angle, end_angle, false);
+                    picture.arrow(end, end_angle + PI * 0.4);
+                    let text_at = (
+                        circle.0 + radius * anchor_angle.cos(),
+                        circle.1 + radius * anchor_angle.sin(),
+                    );
+                    picture.text(&caption(text, true), text_at, Some(*anchor_angle));
+                }
+                Link::StartLink {
+                    node,
+                    text,
+                    delta_x,
+                    delta_y,
+                } => {
+                    let Some(at) = center(*node) else {
+                        continue;
+                    };
+                    // A start link with no length is drawn from the left, as if it had been pulled out.
+                    let (delta_x, delta_y) = match (*delta_x, *delta_y) {
+                        (0, 0) => (-2.0 * NODE_RADIUS, 0.0),
+                        (x, y) => (x as f64, y as f64),
+                    };
+                    let start = (at.0 + delta_x, at.1 + delta_y);
+                    let end = closest_point_on_circle(at, start);
+                    picture.line(start, end);
+                    picture.arrow(end, f64::atan2(-delta_y, -delta_x));
+                    let text_angle = f64::atan2(start.1 - end.1, start.0 - end.0);
+                    picture.text(&caption(text, false), start, Some(text_angle));
+                }
+            }
+        }
+
+        format!(
+            "\\begin{{tikzpicture}}[scale=0.2]\n{}\\end{{tikzpicture}}\n",
+            picture.commands
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use crate::fsm::{Link, Node, StateMachine};
+
+    use super::escape;
+
+    #[test]
+    fn escapes_text_for_latex() {
+        assert_eq!(escape("a_b{c}$%&#"), r"a\_b\{c\}\$\%\&\#");
+        assert_eq!(
+            escape(r"a→b, ^~|<>\"),
+            r"a$\to$b, \textasciicircum{}\textasciitilde{}\textbar{}\textless{}\textgreater{}\textbackslash{}"
+        );
+    }
+
+    #[test]
+    fn draws_what_the_canvas_draws() {
+        let fsm = StateMachine {
+            nodes: vec![
+                Node {
+                    x: 100,
+                    y: 100,
+                    text: "q_0".into(),
+                    accept_state: false,
+                },
+                Node {
+                    x: 300,
+                    y: 100,
+                    text: "q1".into(),
+                    accept_state: true,
+                },
+            ],
+            links: vec![
+                Link::StartLink {
+                    node: 0,
+                    text: String::new(),
+                    delta_x: -80,
+                    delta_y: 0,
+                },
+                Link::NormalLink {
+                    start_node: 0,
+                    end_node: 1,
+                    text: "a,b".into(),
+                    angle_adjust: 0.0,
+                    parallel_part: 0.5,
+                    perpendicular_part: 0.0,
+                },
+                Link::NormalLink {
+                    start_node: 1,
+                    end_node: 0,
+                    text: String::new(),
+                    angle_adjust: 0.0,
+                    parallel_part: 0.5,
+                    perpendicular_part: 40.0,
+                },
+                Link::SelfLink {
+                    node: 1,
+                    text: "\\e".into(),
+                    anchor_angle: -FRAC_PI_2,
+                },
+                // Links to missing nodes are left out.
+                Link::SelfLink {
+                    node: 7,
+                    text: "x".into(),
+                    anchor_angle: 0.0,
+                },
+            ],
+        };
+        assert_eq!(
+            fsm.to_tikz(),
+            r"\begin{tikzpicture}[scale=0.2]
+\draw [black] (10.00,-10.00) circle (3.000);
+\draw (10.00,-10.00) node {q\_0};
+\draw [black] (30.00,-10.00) circle (3.000);
+\draw (30.00,-10.00) node {q1};
+\draw [black] (30.00,-10.00) circle (2.400);
+\draw [black] (2.00,-10.00) -- (7.00,-10.00);
+\fill [black] (7.00,-10.00) -- (5.80,-9.30) -- (5.80,-10.70);
+\draw [black] (13.00,-10.00) -- (27.00,-10.00);
+\fill [black] (27.00,-10.00) -- (25.80,-9.30) -- (25.80,-10.70);
+\draw (20.00,-10.00) node [below] {a,b};
+\draw [black] (12.370,-8.170) arc (121.74852:58.25148:14.500);
+\fill [black] (12.37,-8.17) -- (13.76,-8.13) -- (13.02,-6.94);
+\draw (20.00,-6.00) node [above] {($\varepsilon$)};
+\draw [black] (28.677,-7.320) arc (234.00000:-54.00000:2.250);
+\fill [black] (31.32,-7.32) -- (32.59,-6.76) -- (31.46,-5.94);
+\draw (30.00,-3.25) node [above] {$\varepsilon$};
+\end{tikzpicture}
+"
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compiled::CompiledMachine,
+    fsm::{FSMError, Link, StateMachine},
+};
+
+/// Where a transducer writes its output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransducerKind {
+    /// Links are labelled `input/output`, and taking a link writes its output.
+    Mealy,
+
+    /// Entering a node writes the node's text, starting with the node the start link leads to.
+    Moore,
+}
+
+/// What a transducer produces for a word.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Transduction {
+    /// The word is accepted, and every accepting path writes this output.
+    Output(String),
+
+    /// No path reads the whole word and ends in an accepting node.
+    Rejected,
+
+    /// Accepting paths write different outputs; two of them are given.
+    Ambiguous(String, String),
+}
+
+/// The byte offset of the last `/` in the text that is not escaped as `\/`.
+fn last_slash(text: &str) -> Option<usize> {
+    let mut last = None;
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '/' => last = Some(i),
+            _ => {}
+        }
+    }
+    last
+}
+
+/// Split a Mealy link's text at its last unescaped `/` into the input label and the output.
+///
+/// Text without a `/` has an empty output.
+/// An output written as `\e` or `ε` is empty too: that way a link can write nothing
+/// and still have a `/` that marks the machine as a Mealy machine.
+/// A `/` in the output is written as `\/`; in the input, the label reads `\/` as a slash by itself.
+pub fn split_label(text: &str) -> (&str, String) {
+    match last_slash(text) {
+        Some(i) => (&text[..i], output_text(&text[i + 1..])),
+        None => (text, String::new()),
+    }
+}
+
+/// The text a transducer writes for this output, with the explicit epsilon written as nothing
+/// and escaped slashes unescaped.
+fn output_text(output: &str) -> String {
+    match output {
+        "\\e" | "ε" => String::new(),
+        other => other.replace("\\/", "/"),
+    }
+}
+
+/// A [StateMachine] read as a transducer, prepared for running many words through it.
+#[derive(Clone, Debug)]
+pub struct Transducer {
+    inputs: CompiledMachine,
+    /// What taking every link writes.
+    link_outputs: Vec<String>,
+    /// What entering every node writes.
+    node_outputs: Vec<String>,
+    /// Whether the node is on a loop of links that read nothing but write something.
+    endless: Vec<bool>,
+}
+
+impl Transducer {
+    /// Prepare the machine. Like [CompiledMachine::new], this does not check the machine.
+    ///
+    /// Loops that write without reading are found here, so that [Transducer::transduce] can stop at them.
+    pub fn new(fsm: &StateMachine) -> Self {
+        let kind = fsm.transducer_kind();
+        let link_outputs: Vec<String> = fsm
+            .links
+            .iter()
+            .map(|link| match kind {
+                TransducerKind::Mealy => split_label(link.get_text()).1,
+                TransducerKind::Moore => String::new(),
+            })
+            .collect();
+        let node_outputs: Vec<String> = fsm
+            .nodes
+            .iter()
+            .map(|node| match kind {
+                TransducerKind::Mealy => String::new(),
+                TransducerKind::Moore => output_text(&node.text),
+            })
+            .collect();
+        let inputs = CompiledMachine::new(&fsm.input_machine());
+        let endless = endless_nodes(&inputs, &link_outputs, &node_outputs);
+        Transducer {
+            inputs,
+            link_outputs,
+            node_outputs,
+            endless,
+        }
+    }
+
+    /// The acceptor reading the transducer's inputs, with the same node and link indices.
+    pub fn inputs(&self) -> &CompiledMachine {
+        &self.inputs
+    }
+
+    /// Run the word through the transducer, following every path that reads it.
+    ///
+    /// A path that reaches a loop of links writing output without reading anything
+    /// could write forever, so that is reported as [FSMError::InfiniteLoop].
+    pub fn transduce(&self, word: &str) -> Result<Transduction, FSMError> {
+        // Every (node, position, output written so far) is explored once.
+        let mut seen: HashSet<(usize, usize, String)> = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut outputs = BTreeSet::new();
+
+        let mut visit = |node: usize,
+                         position: usize,
+                         output: String,
+                         queue: &mut VecDeque<(usize, usize, String)>| {
+            let state = (node, position, output + self.node_outputs[node].as_str());
+            if seen.insert(state.clone()) {
+                queue.push_back(state);
+            }
+        };
+
+        for (link, length) in self.inputs.links_matching(None, word) {
+            let output = self.link_outputs[link].clone();
+            visit(self.inputs.target(link), length, output, &mut queue);
+        }
+        while let Some((node, position, output)) = queue.pop_front() {
+            if self.endless[node] {
+                return Err(FSMError::InfiniteLoop);
+            }
+            if position == word.len() && self.inputs.is_accepting(node) {
+                outputs.insert(output.clone());
+                if outputs.len() > 1 {
+                    break;
+                }
+            }
+            for (link, length) in self.inputs.links_matching(Some(node), &word[position..]) {
+                let output = output.clone() + self.link_outputs[link].as_str();
+                visit(
+                    self.inputs.target(link),
+                    position + length,
+                    output,
+                    &mut queue,
+                );
+            }
+        }
+
+        let mut outputs = outputs.into_iter();
+        Ok(match (outputs.next(), outputs.next()) {
+            (None, _) => Transduction::Rejected,
+            (Some(output), None) => Transduction::Output(output),
+            (Some(a), Some(b)) => Transduction::Ambiguous(a, b),
+        })
+    }
+}
+
+/// Find the nodes on loops of links that read nothing, where taking a link or entering a node writes something.
+fn endless_nodes(
+    inputs: &CompiledMachine,
+    link_outputs: &[String],
+    node_outputs: &[String],
+) -> Vec<bool> {
+    let count = inputs.node_count();
+    let empty_links: Vec<Vec<(usize, usize)>> = (0..count)
+        .map(|node| {
+            inputs
+                .links_matching(Some(node), "")
+                .into_iter()
+                .map(|(link, _)| (link, inputs.target(link)))
+                .collect()
+        })
+        .collect();
+    // The nodes reachable from every node through at least one link that reads nothing.
+    let reachable: Vec<Vec<bool>> = (0..count)
+        .map(|start| {
+            let mut seen = vec![false; count];
+            let mut stack: Vec<usize> = empty_links[start].iter().map(|(_, to)| *to).collect();
+            while let Some(node) = stack.pop() {
+                if !std::mem::replace(&mut seen[node], true) {
+                    stack.extend(empty_links[node].iter().map(|(_, to)| *to));
+                }
+            }
+            seen
+        })
+        .collect();
+
+    let mut endless = vec![false; count];
+    for (from, links) in empty_links.iter().enumerate() {
+        for (link, to) in links {
+            let on_loop = *to == from || reachable[*to][from];
+            if on_loop && !(link_outputs[*link].is_empty() && node_outputs[*to].is_empty()) {
+                endless[from] = true;
+            }
+        }
+    }
+    endless
+}
+
+impl StateMachine {
+    /// Decide how this machine writes output: if any link has a `/` in its text it is a Mealy machine,
+    /// and otherwise a Moore machine. A `/` escaped as `\/` does not count.
+    pub fn transducer_kind(&self) -> TransducerKind {
+        if self
+            .links
+            .iter()
+            .any(|l| last_slash(l.get_text()).is_some())
+        {
+            TransducerKind::Mealy
+        } else {
+            TransducerKind::Moore
+        }
+    }
+
+    /// The acceptor reading this transducer's inputs: for a Mealy machine, the outputs are removed from the links.
+    ///
+    /// Nodes and links keep their indices, so the acceptor can be checked, run and explained in place of the transducer.
+    pub fn input_machine(&self) -> StateMachine {
+        if self.transducer_kind() == TransducerKind::Moore {
+            return self.clone();
+        }
+        let mut inputs = self.clone();
+        for link in inputs.links.iter_mut() {
+            let text = match link {
+                Link::NormalLink { text, .. }
+                | Link::StartLink { text, .. }
+                | Link::SelfLink { text, .. } => text,
+            };
+            *text = split_label(text).0.to_string();
+        }
+        inputs
+    }
+
+    /// Check this machine as a transducer, by checking the acceptor of its inputs.
+    pub fn check_transducer_error(&self) -> Option<FSMError> {
+        self.input_machine().check_error()
+    }
+
+    /// Run the word through this machine as a transducer, and return what it writes.
+    ///
+    /// The word must be read completely and end in an accepting node, just like for an acceptor.
+    pub fn transduce(&self, word: &str) -> Result<Transduction, FSMError> {
+        if let Some(err) = self.check_transducer_error() {
+            return Err(err);
+        }
+        Transducer::new(self).transduce(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        drawing::test_machine,
+        fsm::{FSMError, StateMachine},
+    };
+
+    use super::{split_label, Transducer, TransducerKind, Transduction};
+
+    /// Run the word through the transducer without checking the machine first, as the tester does.
+    fn output(fsm: &StateMachine, word: &str) -> Result<Transduction, FSMError> {
+        Transducer::new(fsm).transduce(word)
+    }
+
+    #[test]
+    fn escaped_slashes_are_not_outputs() {
+        assert_eq!(split_label("a/b"), ("a", "b".to_string()));
+        assert_eq!(split_label("a\\//b\\/c"), ("a\\/", "b/c".to_string()));
+        assert_eq!(split_label("a\\/b"), ("a\\/b", String::new()));
+        assert_eq!(split_label("a/\\e"), ("a", String::new()));
+
+        let moore = test_machine(&[false, true], &[(0, 1, "\\/,a")]);
+        assert_eq!(moore.transducer_kind(), TransducerKind::Moore);
+        assert_eq!(output(&moore, "/"), Ok(Transduction::Output(String::new())));
+
+        let mealy = test_machine(&[true], &[(0, 0, "\\//\\/"), (0, 0, "a/x\\/y")]);
+        assert_eq!(mealy.transducer_kind(), TransducerKind::Mealy);
+        assert_eq!(
+            output(&mealy, "/a"),
+            Ok(Transduction::Output("/x/y".into()))
+        );
+    }
+
+    #[test]
+    fn loops_writing_without_reading_are_errors() {
+        let quiet = test_machine(&[false, true], &[(0, 1, "a/x"), (1, 1, "\\e/\\e")]);
+        assert_eq!(output(&quiet, "a"), Ok(Transduction::Output("x".into())));
+        // Checked machines have no loops of links that read nothing at all.
+        assert_eq!(quiet.transduce("a"), Err(FSMError::InfiniteLoop));
+
+        let mealy = test_machine(
+            &[false, true, true],
+            &[(0, 1, "a/x"), (1, 2, "b/y"), (2, 2, "\\e/z")],
+        );
+        assert_eq!(output(&mealy, "a"), Ok(Transduction::Output("x".into())));
+        assert_eq!(output(&mealy, "ab"), Err(FSMError::InfiniteLoop));
+
+        let mut moore = test_machine(
+            &[false, true, false],
+            &[(0, 1, "a"), (1, 2, ""), (2, 1, "")],
+        );
+        moore.nodes[2].text = "z".into();
+        assert_eq!(output(&moore, "a"), Err(FSMError::InfiniteLoop));
+    }
+}
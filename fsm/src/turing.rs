@@ -0,0 +1,380 @@
+use std::{collections::HashSet, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::fsm::{FSMError, FSMOutput, Link, StateMachine};
+
+/// The symbol written `_` in labels: what every tape cell holds before anything is written to it.
+pub const BLANK: char = '_';
+
+/// How many steps [StateMachine::run_turing] takes by default before giving up.
+pub const DEFAULT_STEP_BUDGET: usize = 10_000;
+
+/// The most configurations a nondeterministic machine may be in at once;
+/// past that, the run ends with [FSMError::TooManyConfigurations] unless it accepts in that step.
+pub const MAX_CURSORS: usize = 512;
+
+/// The text of a node that is a halting state that rejects, compared ignoring case.
+pub const REJECT_NODE: &str = "reject";
+
+/// Where the head goes after a link is taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Move {
+    Left,
+    Right,
+    Stay,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TuringLabelError {
+    #[error("the label has no `→` (or `->`) between what it reads and what it writes")]
+    MissingArrow,
+
+    #[error("the label has no `,` before the direction to move in")]
+    MissingMove,
+
+    #[error("`{0}` is not a direction; write L, R or S")]
+    UnknownMove(String),
+
+    #[error("a label reads and writes exactly one symbol, but `{0}` is not one symbol")]
+    NotOneSymbol(String),
+}
+
+/// The label of a Turing machine's link, written `read → write, L/R/S`.
+///
+/// The link can be taken when the head is on `read`; it replaces that with `write`, and moves the head.
+/// The blank cell is written `_`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TuringLabel {
+    pub read: char,
+    pub write: char,
+    pub movement: Move,
+}
+
+impl FromStr for TuringLabel {
+    type Err = TuringLabelError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (read, rest) = text
+            .split_once('→')
+            .or_else(|| text.split_once("->"))
+            .ok_or(TuringLabelError::MissingArrow)?;
+        let (write, movement) = rest.rsplit_once(',').ok_or(TuringLabelError::MissingMove)?;
+        let symbol = |text: &str| {
+            let text = text.trim();
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(TuringLabelError::NotOneSymbol(text.to_string())),
+            }
+        };
+        let movement = match movement.trim() {
+            "L" | "l" => Move::Left,
+            "R" | "r" => Move::Right,
+            "S" | "s" => Move::Stay,
+            other => return Err(TuringLabelError::UnknownMove(other.to_string())),
+        };
+        Ok(TuringLabel {
+            read: symbol(read)?,
+            write: symbol(write)?,
+            movement,
+        })
+    }
+}
+
+impl Display for TuringLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let movement = match self.movement {
+            Move::Left => 'L',
+            Move::Right => 'R',
+            Move::Stay => 'S',
+        };
+        write!(f, "{} → {}, {movement}", self.read, self.write)
+    }
+}
+
+/// The tape of a Turing machine, unbounded in both directions.
+///
+/// Only the cells between the leftmost and rightmost ones that are not blank, or under the head, are kept.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tape {
+    cells: Vec<char>,
+    head: usize,
+}
+
+impl Tape {
+    /// A tape holding the word, with the head on its first symbol.
+    pub fn new(word: &str) -> Self {
+        let mut tape = Tape {
+            cells: word.chars().collect(),
+            head: 0,
+        };
+        tape.trim();
+        tape
+    }
+
+    /// The cells kept on the tape, from left to right.
+    pub fn cells(&self) -> &[char] {
+        &self.cells
+    }
+
+    /// The position of the head among [Tape::cells].
+    pub fn head(&self) -> usize {
+        self.head
+    }
+
+    pub fn read(&self) -> char {
+        self.cells[self.head]
+    }
+
+    /// The tape after the head writes the symbol and moves.
+    pub fn apply(&self, write: char, movement: Move) -> Tape {
+        let mut tape = self.clone();
+        tape.cells[tape.head] = write;
+        match movement {
+            Move::Left if tape.head == 0 => tape.cells.insert(0, BLANK),
+            Move::Left => tape.head -= 1,
+            Move::Right => tape.head += 1,
+            Move::Stay => {}
+        }
+        tape.trim();
+        tape
+    }
+
+    /// The text on the tape, without the blanks around it.
+    pub fn contents(&self) -> String {
+        self.cells
+            .iter()
+            .collect::<String>()
+            .trim_matches(BLANK)
+            .to_string()
+    }
+
+    /// Drop the blank cells at the ends that the head is not on, and make sure the head is on a cell.
+    fn trim(&mut self) {
+        while self.cells.len() <= self.head {
+            self.cells.push(BLANK);
+        }
+        while self.cells.len() > self.head + 1 && self.cells.last() == Some(&BLANK) {
+            self.cells.pop();
+        }
+        let leading = self.cells[..self.head]
+            .iter()
+            .take_while(|c| **c == BLANK)
+            .count();
+        self.cells.drain(..leading);
+        self.head -= leading;
+    }
+}
+
+/// Steps a [StateMachine] as a Turing machine over a word, one move at a time.
+///
+/// Accepting nodes are halting states that accept, and nodes named [REJECT_NODE] are halting states that reject,
+/// even if links leave them. A machine that is not in an accepting node and cannot move rejects as well.
+/// If it is nondeterministic, every configuration is followed, and it accepts if any of them does.
+pub struct TuringEvaluator {
+    fsm: StateMachine,
+    labels: Vec<Option<TuringLabel>>,
+    budget: usize,
+    steps: usize,
+    /// (node, tape) for every configuration the machine is in.
+    cursors: Vec<(usize, Tape)>,
+    /// The links taken to reach the current configurations.
+    links_taken: Vec<usize>,
+    outcome: Option<Result<FSMOutput, FSMError>>,
+}
+
+impl TuringEvaluator {
+    /// Start the machine on the word: the head is on the word's first symbol,
+    /// in every node that a start link leads to. Start links' text is ignored.
+    pub fn new(fsm: StateMachine, word: &str, budget: usize) -> Result<Self, FSMError> {
+        if let Some(e) = fsm.check_turing_error() {
+            return Err(e);
+        }
+        let labels = fsm
+            .links
+            .iter()
+            .map(|link| match link {
+                Link::StartLink { .. } => None,
+                _ => link.get_text().parse().ok(),
+            })
+            .collect();
+        let tape = Tape::new(word);
+        let mut cursors = vec![];
+        let mut links_taken = vec![];
+        for (i, link) in fsm.links.iter().enumerate() {
+            if let Link::StartLink { node, .. } = link {
+                links_taken.push(i);
+                if !cursors.contains(&(*node, tape.clone())) {
+                    cursors.push((*node, tape.clone()));
+                }
+            }
+        }
+        let mut eval = Self {
+            fsm,
+            labels,
+            budget,
+            steps: 0,
+            cursors,
+            links_taken,
+            outcome: None,
+        };
+        eval.update_outcome();
+        Ok(eval)
+    }
+
+    pub fn cursors(&self) -> &Vec<(usize, Tape)> {
+        &self.cursors
+    }
+
+    pub fn links_taken(&self) -> &Vec<usize> {
+        &self.links_taken
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// How the run ended, or None if it is still going.
+    ///
+    /// Running for longer than the step budget is reported as [FSMError::InfiniteLoop],
+    /// and being in too many configurations at once as [FSMError::TooManyConfigurations].
+    pub fn outcome(&self) -> Option<Result<FSMOutput, FSMError>> {
+        self.outcome.clone()
+    }
+
+    /// Move every configuration along every link it can take. Does nothing once the run has ended.
+    pub fn step(&mut self) {
+        if self.outcome.is_some() {
+            return;
+        }
+        self.steps += 1;
+        let mut seen = HashSet::new();
+        let mut cursors = vec![];
+        let mut links_taken = vec![];
+        let mut too_many = false;
+        for (node, tape) in self.cursors.iter() {
+            if self.rejects(*node) {
+                continue;
+            }
+            for (i, link) in self.fsm.links.iter().enumerate() {
+                let (Some(label), (Some(from), to)) = (&self.labels[i], link.get_nodes()) else {
+                    continue;
+                };
+                if from != *node || label.read != tape.read() {
+                    continue;
+                }
+                let cursor = (to, tape.apply(label.write, label.movement));
+                if seen.insert(cursor.clone()) {
+                    if cursors.len() < MAX_CURSORS {
+                        cursors.push(cursor);
+                    } else {
+                        too_many = true;
+                    }
+                }
+                if !links_taken.contains(&i) {
+                    links_taken.push(i);
+                }
+            }
+        }
+        self.cursors = cursors;
+        self.links_taken = links_taken;
+        self.update_outcome();
+        if too_many && self.outcome != Some(Ok(FSMOutput::Accept)) {
+            self.outcome = Some(Err(FSMError::TooManyConfigurations(MAX_CURSORS)));
+        }
+    }
+
+    /// Whether the node is a halting state that rejects.
+    fn rejects(&self, node: usize) -> bool {
+        let node = &self.fsm.nodes[node];
+        !node.accept_state && node.text.trim().eq_ignore_ascii_case(REJECT_NODE)
+    }
+
+    fn update_outcome(&mut self) {
+        if self
+            .cursors
+            .iter()
+            .any(|(node, _)| self.fsm.nodes[*node].accept_state)
+        {
+            self.outcome = Some(Ok(FSMOutput::Accept));
+        } else if self.cursors.iter().all(|(node, _)| self.rejects(*node)) {
+            self.outcome = Some(Ok(FSMOutput::Reject));
+        } else if self.steps >= self.budget {
+            self.outcome = Some(Err(FSMError::InfiniteLoop));
+        }
+    }
+}
+
+impl StateMachine {
+    /// Check this machine as a Turing machine: every link but the start links must have a [TuringLabel].
+    pub fn check_turing_error(&self) -> Option<FSMError> {
+        if let Some(err) = self.check_links() {
+            return Some(err);
+        }
+        for (i, link) in self.links.iter().enumerate() {
+            if matches!(link, Link::StartLink { .. }) {
+                continue;
+            }
+            if let Err(why) = link.get_text().parse::<TuringLabel>() {
+                log::info!(
+                    "Pre-check failed: Turing machine has link {i} with invalid label: {why}"
+                );
+                return Some(FSMError::InvalidTuringLabel(i, why));
+            }
+        }
+        None
+    }
+
+    /// Run this machine as a Turing machine on the word, for at most `budget` steps.
+    pub fn run_turing(&self, word: &str, budget: usize) -> Result<FSMOutput, FSMError> {
+        let mut eval = TuringEvaluator::new(self.clone(), word, budget)?;
+        loop {
+            if let Some(outcome) = eval.outcome() {
+                return outcome;
+            }
+            eval.step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        drawing::test_machine,
+        fsm::{FSMError, FSMOutput, StateMachine},
+    };
+
+    use super::{DEFAULT_STEP_BUDGET, MAX_CURSORS, REJECT_NODE};
+
+    /// A machine starting at node 0, with these node names and links and no accepting nodes.
+    fn machine(names: &[&str], links: &[(usize, usize, &str)]) -> StateMachine {
+        let mut fsm = test_machine(&vec![false; names.len()], links);
+        for (node, name) in fsm.nodes.iter_mut().zip(names) {
+            node.text = name.to_string();
+        }
+        fsm
+    }
+
+    #[test]
+    fn reject_nodes_halt() {
+        let tm = machine(
+            &["q0", REJECT_NODE],
+            &[(0, 1, "a → a, R"), (1, 1, "_ → _, R")],
+        );
+        assert_eq!(
+            tm.run_turing("a", DEFAULT_STEP_BUDGET),
+            Ok(FSMOutput::Reject)
+        );
+    }
+
+    #[test]
+    fn too_many_configurations_are_an_error() {
+        let branching = machine(&["q0"], &[(0, 0, "_ → a, R"), (0, 0, "_ → b, R")]);
+        assert_eq!(
+            branching.run_turing("", DEFAULT_STEP_BUDGET),
+            Err(FSMError::TooManyConfigurations(MAX_CURSORS))
+        );
+    }
+}